@@ -51,8 +51,144 @@ pub enum Format {
     Toml,
 }
 
+/// The automata the server runs, each at its own path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Automaton {
+    GameOfLife,
+    Wireworld,
+    Line,
+    Turmites,
+}
+
+impl Automaton {
+    fn url(&self) -> &'static str {
+        match self {
+            Automaton::GameOfLife => "ws://localhost:9001/life",
+            Automaton::Wireworld => "ws://localhost:9001/wireworld",
+            Automaton::Line => "ws://localhost:9001/line",
+            Automaton::Turmites => "ws://localhost:9001/turmite",
+        }
+    }
+}
+
+/// The latest state received from the server.
+#[derive(Debug)]
+pub enum Board {
+    Life(Box<gameoflife::Simulation>),
+    Wireworld(gameoflife::Wireworld),
+    Line(gameoflife::LineAutomaton),
+    Turmites(gameoflife::Turmites),
+}
+
+impl Board {
+    fn width(&self) -> usize {
+        match self {
+            Board::Life(sim) => sim.width(),
+            Board::Wireworld(circuit) => circuit.width(),
+            Board::Line(automaton) => automaton.width(),
+            Board::Turmites(board) => board.width(),
+        }
+    }
+
+    fn height(&self) -> usize {
+        match self {
+            Board::Life(sim) => sim.height(),
+            Board::Wireworld(circuit) => circuit.height(),
+            Board::Line(automaton) => automaton.height(),
+            Board::Turmites(board) => board.height(),
+        }
+    }
+
+    fn grid(&self) -> gameoflife::Grid {
+        match self {
+            Board::Life(sim) => sim.grid(),
+            Board::Wireworld(_) | Board::Line(_) | Board::Turmites(_) => gameoflife::Grid::Square,
+        }
+    }
+
+    fn status(&self) -> &'static str {
+        match self {
+            Board::Life(sim) => sim.state().as_str(),
+            Board::Wireworld(circuit) => circuit.state().as_str(),
+            Board::Line(automaton) => automaton.state().as_str(),
+            Board::Turmites(board) => board.state().as_str(),
+        }
+    }
+
+    /// The state number of every cell, in row major order. Live cells on teams other than the first
+    /// and cells with an ant on them get states of their own past those of the rule.
+    fn states(&self) -> Vec<usize> {
+        match self {
+            Board::Life(sim) => {
+                let states = sim.rule().states();
+                sim.cellules()
+                    .zip(sim.cellule_teams())
+                    .map(|(cell, team)| {
+                        match team {
+                            0 => cell.state(),
+                            team => states + team - 1,
+                        }
+                    })
+                    .collect()
+            }
+            Board::Wireworld(circuit) => circuit.cells().map(|cell| cell.state()).collect(),
+            Board::Line(automaton) => automaton.cells().map(usize::from).collect(),
+            Board::Turmites(board) => {
+                let mut states = board.cells().map(usize::from).collect::<Vec<_>>();
+                for ant in board.ants() {
+                    states[ant.y() * board.width() + ant.x()] = board.rule().colors() as usize;
+                }
+                states
+            }
+        }
+    }
+
+    /// How many state numbers there are, and the fill color of each.
+    fn palette(&self) -> Vec<String> {
+        match self {
+            // Dying cells of Generations rules fade from orange towards the gray of dead cells.
+            // Teams past the first, which is drawn green like any live cell, get colors of their own.
+            Board::Life(sim) => {
+                let states = sim.rule().states();
+                let teams = ["deepskyblue", "crimson", "gold"].iter().map(|color| color.to_string());
+                (0..states)
+                    .map(|state| {
+                        match state {
+                            0 => "gray".to_string(),
+                            1 => "green".to_string(),
+                            state => format!("hsl(30, {}%, 50%)", 100 - 100 * (state - 1) / (states - 1)),
+                        }
+                    })
+                    .chain(teams.take(sim.rule().teams() - 1))
+                    .collect()
+            }
+            Board::Wireworld(_) => {
+                ["gray", "deepskyblue", "white", "orange"].iter().map(|color| color.to_string()).collect()
+            }
+            Board::Line(automaton) => wheel(automaton.rule().colors() as usize),
+            Board::Turmites(board) => {
+                let mut palette = wheel(board.rule().colors() as usize);
+                palette.push("red".to_string());
+                palette
+            }
+        }
+    }
+}
+
+/// Gray for color 0, with the other colors spread evenly around the color wheel starting from green.
+fn wheel(colors: usize) -> Vec<String> {
+    (0..colors)
+        .map(|color| {
+            match color {
+                0 => "gray".to_string(),
+                color => format!("hsl({}, 100%, 35%)", 120 + 360 * (color - 1) / (colors - 1)),
+            }
+        })
+        .collect()
+}
+
 pub enum WsAction {
-    Connect,
+    Connect(Automaton),
     SendData(AsBinary),
     Disconnect,
     Lost,
@@ -60,7 +196,7 @@ pub enum WsAction {
 
 pub enum Msg {
     WsAction(WsAction),
-    WsReady(Result<gameoflife::Simulation, Error>),
+    WsReady(Result<Board, Error>),
     Ignore,
 }
 
@@ -88,7 +224,7 @@ pub struct Props {}
 
 pub struct GameOfLifeClient {
     link:     ComponentLink<GameOfLifeClient>,
-    data:     Option<gameoflife::Simulation>,
+    data:     Option<Board>,
     ws:       Option<WebSocketTask>,
     canvas:   Option<HtmlCanvasElement>,
     ctx:      Option<RenderingContext>,
@@ -98,55 +234,62 @@ pub struct GameOfLifeClient {
 impl GameOfLifeClient {
     fn render(
         &mut self,
-        last_data: Option<&gameoflife::Simulation>,
+        last_data: Option<&Board>,
     ) {
         const CELL_SIZE: f64 = 9.0;
         const PAD: f64 = 1.0;
 
         let ctx: &RenderingContext = self.ctx.as_ref().expect("Canvas Rendering Context not initialized!");
-        let data: &gameoflife::Simulation = self.data.as_ref().expect("Simulation data not initialized!");
+        let data: &Board = self.data.as_ref().expect("Simulation data not initialized!");
 
-        let should_render = |idx: usize, cell: &gameoflife::Cellule, alive: bool| {
-            alive == cell.alive()
-                && last_data
-                    .map(|d: &gameoflife::Simulation| d.cellules()[idx].alive() != cell.alive())
-                    .unwrap_or(true)
-        };
-
-        ctx.set_fill_style(&JsValue::from_str("green"));
+        let last_states = last_data.map(Board::states).unwrap_or_default();
+        let should_render = |idx: usize, state: usize| last_states.get(idx).map(|&last| last != state).unwrap_or(true);
 
-        let mut x = 0.0;
-        let mut y = 0.0;
-        let mut idx = 0usize;
+        // Hexagonal boards are skewed into a parallelogram so that each cell touches its six
+        // neighbors, and triangular ones interlock cells pointing up and down.
+        let (width, height, grid) = (data.width(), data.height(), data.grid());
+        let paint = |idx: usize| {
+            let (x, y) = (idx % width, idx / width);
+            let top = y as f64 * (CELL_SIZE + PAD);
+            match grid {
+                gameoflife::Grid::Square => ctx.fill_rect(x as f64 * (CELL_SIZE + PAD), top, CELL_SIZE, CELL_SIZE),
+                gameoflife::Grid::Hexagonal => {
+                    let shift = (height - 1 - y) as f64 * (CELL_SIZE + PAD) / 2.0;
+                    ctx.fill_rect(shift + x as f64 * (CELL_SIZE + PAD), top, CELL_SIZE, CELL_SIZE)
+                }
+                gameoflife::Grid::Triangular => {
+                    let left = x as f64 * (CELL_SIZE + PAD) / 2.0;
+                    let (apex, base) = if gameoflife::Grid::points_up(x, y) {
+                        (top, top + CELL_SIZE)
+                    } else {
+                        (top + CELL_SIZE, top)
+                    };
+                    ctx.begin_path();
+                    ctx.move_to(left, base);
+                    ctx.line_to(left + CELL_SIZE / 2.0 + PAD / 2.0, apex);
+                    ctx.line_to(left + CELL_SIZE + PAD, base);
+                    ctx.close_path();
+                    ctx.fill();
+                }
+            }
+        };
 
-        for line in data.cellules().chunks(data.width()) {
-            for &cell in line {
-                if should_render(idx, &cell, true) {
-                    ctx.fill_rect(x, y, CELL_SIZE, CELL_SIZE);
+        let palette = data.palette();
+        let mut changed = vec![Vec::new(); palette.len()];
+        for (idx, state) in data.states().into_iter().enumerate() {
+            if should_render(idx, state) {
+                if let Some(cells) = changed.get_mut(state) {
+                    cells.push(idx);
                 }
-                x += CELL_SIZE + PAD;
-                idx += 1;
             }
-            y += CELL_SIZE + PAD;
-            x = 0.0;
         }
 
-        ctx.set_fill_style(&JsValue::from_str("gray"));
-
-        let mut x = 0.0;
-        let mut y = 0.0;
-        let mut idx = 0usize;
+        for (state, cells) in changed.iter().enumerate().filter(|(_, cells)| !cells.is_empty()) {
+            ctx.set_fill_style(&JsValue::from_str(&palette[state]));
 
-        for line in data.cellules().chunks(data.width()) {
-            for &cell in line {
-                if should_render(idx, &cell, false) {
-                    ctx.fill_rect(x, y, CELL_SIZE, CELL_SIZE);
-                }
-                x += CELL_SIZE + PAD;
-                idx += 1;
+            for &idx in cells {
+                paint(idx);
             }
-            y += CELL_SIZE + PAD;
-            x = 0.0;
         }
     }
 }
@@ -192,16 +335,40 @@ impl Component for GameOfLifeClient {
         match msg {
             Msg::WsAction(action) => {
                 match action {
-                    WsAction::Connect => {
-                        let callback = self.link.callback(|Bincode(data)| Msg::WsReady(data));
+                    WsAction::Connect(automaton) => {
                         let notification = self.link.callback(|status| {
                             match status {
                                 WebSocketStatus::Opened => Msg::Ignore,
                                 WebSocketStatus::Closed | WebSocketStatus::Error => WsAction::Lost.into(),
                             }
                         });
-                        let task = WebSocketService::connect("ws://localhost:9001/", callback, notification).unwrap();
-                        self.ws = Some(task);
+                        let task = match automaton {
+                            Automaton::GameOfLife => {
+                                let callback = self.link.callback(|Bincode(data): Bincode<Result<_, Error>>| {
+                                    Msg::WsReady(data.map(|sim| Board::Life(Box::new(sim))))
+                                });
+                                WebSocketService::connect(automaton.url(), callback, notification)
+                            }
+                            Automaton::Wireworld => {
+                                let callback = self.link.callback(|Bincode(data): Bincode<Result<_, Error>>| {
+                                    Msg::WsReady(data.map(Board::Wireworld))
+                                });
+                                WebSocketService::connect(automaton.url(), callback, notification)
+                            }
+                            Automaton::Line => {
+                                let callback = self.link.callback(|Bincode(data): Bincode<Result<_, Error>>| {
+                                    Msg::WsReady(data.map(Board::Line))
+                                });
+                                WebSocketService::connect(automaton.url(), callback, notification)
+                            }
+                            Automaton::Turmites => {
+                                let callback = self.link.callback(|Bincode(data): Bincode<Result<_, Error>>| {
+                                    Msg::WsReady(data.map(Board::Turmites))
+                                });
+                                WebSocketService::connect(automaton.url(), callback, notification)
+                            }
+                        };
+                        self.ws = Some(task.unwrap());
                     }
                     WsAction::SendData(binary) => {
                         let request = WsRequest { value: 321 };
@@ -243,13 +410,37 @@ impl Component for GameOfLifeClient {
     fn view(&self) -> Html {
         html! {
             <div>
-                <p> {  self.data.as_ref().map(|sim| sim.state().as_str()).unwrap_or("not running") }</p>
+                <p> {  self.data.as_ref().map(Board::status).unwrap_or("not running") }</p>
+                <p> {
+                    match &self.data {
+                        Some(Board::Life(sim)) if sim.rule().teams() > 1 => {
+                            let populations: Vec<_> = sim.team_populations().iter().map(|n| n.to_string()).collect();
+                            format!("teams: {}", populations.join(" / "))
+                        }
+                        Some(Board::Life(sim)) => {
+                            sim.stabilization().map(|s| format!("stabilized: {}", s)).unwrap_or_default()
+                        }
+                        _ => String::new(),
+                    }
+                }</p>
               <canvas width="850" height="640" ref={self.node_ref.clone()} />
                 <nav class="menu">
                     <button disabled=self.ws.is_some()
-                            onclick=self.link.callback(|_| WsAction::Connect)>
+                            onclick=self.link.callback(|_| WsAction::Connect(Automaton::GameOfLife))>
                         { "Start Simulation" }
                     </button>
+                    <button disabled=self.ws.is_some()
+                            onclick=self.link.callback(|_| WsAction::Connect(Automaton::Wireworld))>
+                        { "Start Wireworld" }
+                    </button>
+                    <button disabled=self.ws.is_some()
+                            onclick=self.link.callback(|_| WsAction::Connect(Automaton::Line))>
+                        { "Start 1D Automaton" }
+                    </button>
+                    <button disabled=self.ws.is_some()
+                            onclick=self.link.callback(|_| WsAction::Connect(Automaton::Turmites))>
+                        { "Start Turmites" }
+                    </button>
                     <button disabled=self.ws.is_none()
                             onclick=self.link.callback(|_| WsAction::Disconnect)>
                         { "Terminate Simulation" }
@@ -0,0 +1,1130 @@
+use crate::{
+    check_dimensions,
+    deps::serde,
+    Bounds,
+    Error,
+    Grid,
+    Rule,
+    Topology,
+};
+use std::{
+    borrow::Cow,
+    convert::TryFrom,
+};
+
+const WORD_BITS: usize = 64;
+
+/// A board of cells packed 64 to a `u64`.
+///
+/// Each row starts on a fresh word and the bits past `width` in the last word of a row are always
+/// zero. Under a Generations rule the age of each dying cell, counted from 1 when it stops being
+/// alive, is kept alongside as a binary counter with one plane of words per bit. Under a colored
+/// rule the team of each live cell is kept the same way, and is zero for every other cell.
+///
+/// Stepping counts all eight Moore neighbors of 64 cells at once with bitwise adders and writes the
+/// next generation into a reused back buffer. The [`Topology`] only comes into play at the edges,
+/// where the rows above and below and the cells just past either end are looked up through it.
+/// Neighborhoods of range 1 that leave out some of those eight are counted the same way, while
+/// those that reach further or, being triangular, depend on the cell, count through a summed-area
+/// table instead.
+///
+/// The grid also keeps a [Zobrist hash] of its cell states, updated with every change to a cell, so
+/// that repeated boards can be spotted without comparing them cell by cell.
+///
+/// [Zobrist hash]: https://en.wikipedia.org/wiki/Zobrist_hashing
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "BitGridRepr")]
+pub(crate) struct BitGrid {
+    width:  usize,
+    height: usize,
+    words:  Vec<u64>,
+    ages:   Vec<Vec<u64>>,
+    teams:  Vec<Vec<u64>>,
+    hash:   u64,
+    #[serde(skip)]
+    back:   Vec<u64>,
+    #[serde(skip)]
+    sums:   Vec<u32>,
+}
+
+/// Clones the cells but not the back buffer or the summed-area table, which are scratch space for
+/// [`BitGrid::step`].
+impl Clone for BitGrid {
+    fn clone(&self) -> Self {
+        Self {
+            width:  self.width,
+            height: self.height,
+            words:  self.words.clone(),
+            ages:   self.ages.clone(),
+            teams:  self.teams.clone(),
+            hash:   self.hash,
+            back:   Vec::new(),
+            sums:   Vec::new(),
+        }
+    }
+}
+
+impl BitGrid {
+    /// Roughly how many words each parallel task steps, enough to outweigh scheduling it.
+    #[cfg(feature = "parallel")]
+    const MIN_BAND_WORDS: usize = 1 << 10;
+    /// Boards smaller than this are stepped on the calling thread alone.
+    #[cfg(feature = "parallel")]
+    const MIN_PARALLEL_WORDS: usize = 1 << 12;
+
+    pub(crate) fn new(
+        width: usize,
+        height: usize,
+    ) -> Self {
+        Self {
+            width,
+            height,
+            words: vec![0; Self::stride_of(width) * height],
+            ages: Vec::new(),
+            teams: Vec::new(),
+            hash: 0,
+            back: Vec::new(),
+            sums: Vec::new(),
+        }
+    }
+
+    fn stride_of(width: usize) -> usize {
+        width.div_ceil(WORD_BITS)
+    }
+
+    fn stride(&self) -> usize {
+        Self::stride_of(self.width)
+    }
+
+    /// The mask of the bits in the last word of a row that hold cells.
+    fn tail_mask(&self) -> u64 {
+        match self.width % WORD_BITS {
+            0 => !0,
+            bits => (1 << bits) - 1,
+        }
+    }
+
+    fn position(
+        &self,
+        x: usize,
+        y: usize,
+    ) -> (usize, u64) {
+        debug_assert!(x < self.width && y < self.height);
+        (y * self.stride() + x / WORD_BITS, 1 << (x % WORD_BITS))
+    }
+
+    pub(crate) fn width(&self) -> usize {
+        self.width
+    }
+
+    pub(crate) fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The bytes taken by the cells.
+    pub(crate) fn memory(&self) -> usize {
+        self.words.len() * self.planes() * std::mem::size_of::<u64>()
+    }
+
+    /// How many bit planes hold numbers up to `count - 1`.
+    fn planes_for(count: usize) -> usize {
+        (usize::BITS - count.saturating_sub(1).leading_zeros()) as usize
+    }
+
+    /// Makes room for the ages of a rule with `states` states, turning every dying cell dead.
+    pub(crate) fn set_states(
+        &mut self,
+        states: usize,
+    ) {
+        let planes = match states {
+            0..=2 => 0,
+            states => Self::planes_for(states),
+        };
+        self.ages = vec![vec![0; self.words.len()]; planes];
+        self.rehash();
+    }
+
+    /// Makes room for the teams of a rule with `teams` teams, putting every live cell on team 0.
+    pub(crate) fn set_teams(
+        &mut self,
+        teams: usize,
+    ) {
+        self.teams = vec![vec![0; self.words.len()]; Self::planes_for(teams)];
+        self.rehash();
+    }
+
+    /// A `width` by `height` grid for the same states and teams, holding every cell of this one moved
+    /// `dx` columns right and `dy` rows down. Cells that end up off the new grid are dropped.
+    pub(crate) fn resized(
+        &self,
+        width: usize,
+        height: usize,
+        dx: isize,
+        dy: isize,
+    ) -> Self {
+        let mut grid = Self::new(width, height);
+        grid.ages = vec![vec![0; grid.words.len()]; self.ages.len()];
+        grid.teams = vec![vec![0; grid.words.len()]; self.teams.len()];
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let state = self.state(x, y);
+                let (to_x, to_y) = (x as isize + dx, y as isize + dy);
+                if state == 0 || to_x < 0 || to_y < 0 || to_x as usize >= width || to_y as usize >= height {
+                    continue;
+                }
+                let (to_x, to_y) = (to_x as usize, to_y as usize);
+                grid.set_state(to_x, to_y, state);
+                if state == 1 {
+                    grid.set_team(to_x, to_y, self.team(x, y));
+                }
+            }
+        }
+        grid
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// The Zobrist hash of the cell states, zero for an all dead grid.
+    pub(crate) fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// How many planes of words there are in all.
+    fn planes(&self) -> usize {
+        1 + self.ages.len() + self.teams.len()
+    }
+
+    /// The words of plane `plane`: the live cells for plane 0, then the bits of the ages from the
+    /// lowest up, then those of the teams.
+    fn plane(
+        &self,
+        plane: usize,
+    ) -> &[u64] {
+        match plane {
+            0 => &self.words,
+            plane if plane <= self.ages.len() => &self.ages[plane - 1],
+            plane => &self.teams[plane - 1 - self.ages.len()],
+        }
+    }
+
+    fn plane_mut(
+        &mut self,
+        plane: usize,
+    ) -> &mut [u64] {
+        match plane {
+            0 => &mut self.words,
+            plane if plane <= self.ages.len() => &mut self.ages[plane - 1],
+            plane => &mut self.teams[plane - 1 - self.ages.len()],
+        }
+    }
+
+    /// The Zobrist hash of bit `bit` of word `word` being set in plane `plane`.
+    fn key(
+        &self,
+        plane: usize,
+        word: usize,
+        bit: u32,
+    ) -> u64 {
+        let stride = self.stride();
+        let idx = (word / stride) * self.width + (word % stride) * WORD_BITS + bit as usize;
+        cell_key(plane, idx)
+    }
+
+    /// The combined keys of the bits set in `bits`, which are the bits of word `word` of plane
+    /// `plane`.
+    fn keys(
+        &self,
+        plane: usize,
+        word: usize,
+        mut bits: u64,
+    ) -> u64 {
+        let mut hash = 0;
+        while bits != 0 {
+            hash ^= self.key(plane, word, bits.trailing_zeros());
+            bits &= bits - 1;
+        }
+        hash
+    }
+
+    fn rehash(&mut self) {
+        let mut hash = 0;
+        for plane in 0..self.planes() {
+            for word in 0..self.words.len() {
+                hash ^= self.keys(plane, word, self.plane(plane)[word]);
+            }
+        }
+        self.hash = hash;
+    }
+
+    /// The combined ages of the dying cells in word `word`, which are nonzero exactly for the dying
+    /// cells.
+    fn dying(
+        &self,
+        word: usize,
+    ) -> u64 {
+        self.ages.iter().fold(0, |dying, plane| dying | plane[word])
+    }
+
+    /// The state of the cell at `(x, y)`: 0 when dead, 1 when alive and from 2 up while dying.
+    pub(crate) fn state(
+        &self,
+        x: usize,
+        y: usize,
+    ) -> usize {
+        let (word, bit) = self.position(x, y);
+        if self.words[word] & bit != 0 {
+            return 1;
+        }
+        let age = self
+            .ages
+            .iter()
+            .enumerate()
+            .filter(|(_, plane)| plane[word] & bit != 0)
+            .fold(0, |age, (idx, _)| age | (1 << idx));
+        if age == 0 {
+            0
+        } else {
+            age + 1
+        }
+    }
+
+    /// The team of the cell at `(x, y)`, which is 0 unless it is alive under a colored rule.
+    pub(crate) fn team(
+        &self,
+        x: usize,
+        y: usize,
+    ) -> usize {
+        let (word, bit) = self.position(x, y);
+        self.teams
+            .iter()
+            .enumerate()
+            .filter(|(_, plane)| plane[word] & bit != 0)
+            .fold(0, |team, (idx, _)| team | (1 << idx))
+    }
+
+    /// Sets bit `bit` of word `word` in plane `plane` to `set`, keeping the hash up to date.
+    fn write_bit(
+        &mut self,
+        plane: usize,
+        word: usize,
+        bit: u64,
+        set: bool,
+    ) {
+        if (self.plane(plane)[word] & bit != 0) != set {
+            self.hash ^= self.key(plane, word, bit.trailing_zeros());
+            self.plane_mut(plane)[word] ^= bit;
+        }
+    }
+
+    /// Sets the cell at `(x, y)` to `state`, which must be below the number of states the grid was
+    /// made for. A live cell keeps its team and any other cell is left on none.
+    pub(crate) fn set_state(
+        &mut self,
+        x: usize,
+        y: usize,
+        state: usize,
+    ) {
+        let (word, bit) = self.position(x, y);
+        let age = state.saturating_sub(1);
+        for plane in 0..=self.ages.len() {
+            let set = match plane {
+                0 => state == 1,
+                plane => age & (1 << (plane - 1)) != 0,
+            };
+            self.write_bit(plane, word, bit, set);
+        }
+        if state != 1 {
+            for plane in self.ages.len() + 1..self.planes() {
+                self.write_bit(plane, word, bit, false);
+            }
+        }
+    }
+
+    /// Puts the live cell at `(x, y)` on `team`, which must be below the number of teams the grid
+    /// was made for.
+    pub(crate) fn set_team(
+        &mut self,
+        x: usize,
+        y: usize,
+        team: usize,
+    ) {
+        debug_assert!(self.get(x, y));
+        let (word, bit) = self.position(x, y);
+        for idx in 0..self.teams.len() {
+            self.write_bit(1 + self.ages.len() + idx, word, bit, team & (1 << idx) != 0);
+        }
+    }
+
+    pub(crate) fn get(
+        &self,
+        x: usize,
+        y: usize,
+    ) -> bool {
+        let (word, bit) = self.position(x, y);
+        self.words[word] & bit != 0
+    }
+
+    pub(crate) fn set(
+        &mut self,
+        x: usize,
+        y: usize,
+        alive: bool,
+    ) {
+        self.set_state(x, y, alive as usize);
+    }
+
+    /// Kills the cell at `(x, y)` if it is alive and brings it to life otherwise, dying or not.
+    pub(crate) fn toggle(
+        &mut self,
+        x: usize,
+        y: usize,
+    ) {
+        let alive = self.get(x, y);
+        self.set(x, y, !alive);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.words.iter_mut().for_each(|word| *word = 0);
+        self.ages.iter_mut().flatten().for_each(|word| *word = 0);
+        self.teams.iter_mut().flatten().for_each(|word| *word = 0);
+        self.hash = 0;
+    }
+
+    /// Overwrites every word with `fill` and then clears the padding bits past `width`. No cell is
+    /// left dying, and every live cell is put on a team drawn from `fill` too.
+    pub(crate) fn fill_with<F: FnMut() -> u64>(
+        &mut self,
+        mut fill: F,
+    ) {
+        let stride = self.stride();
+        let tail_mask = self.tail_mask();
+        for row in self.words.chunks_mut(stride) {
+            row.iter_mut().for_each(|word| *word = fill());
+            row[stride - 1] &= tail_mask;
+        }
+        self.ages.iter_mut().flatten().for_each(|word| *word = 0);
+        let words = &self.words;
+        for plane in self.teams.iter_mut() {
+            plane.iter_mut().zip(words).for_each(|(team, &alive)| *team = fill() & alive);
+        }
+        self.rehash();
+    }
+
+    pub(crate) fn population(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// How many live cells are on `team`.
+    pub(crate) fn team_population(
+        &self,
+        team: usize,
+    ) -> usize {
+        (0..self.words.len())
+            .map(|word| {
+                let on_team = self.teams.iter().enumerate().fold(self.words[word], |cells, (idx, plane)| {
+                    if team & (1 << idx) != 0 {
+                        cells & plane[word]
+                    } else {
+                        cells & !plane[word]
+                    }
+                });
+                on_team.count_ones() as usize
+            })
+            .sum()
+    }
+
+    /// The smallest rectangle holding every live cell, or `None` for an empty grid.
+    pub(crate) fn bounds(&self) -> Option<Bounds> {
+        let stride = self.stride();
+        let mut rows = self.words.chunks(stride).enumerate().filter(|(_, row)| row.iter().any(|&word| word != 0));
+        let (min_y, first) = rows.next()?;
+        let max_y = rows.next_back().map_or(min_y, |(y, _)| y);
+
+        let mut columns = first.to_vec();
+        for row in self.words[(min_y + 1) * stride..(max_y + 1) * stride].chunks(stride) {
+            columns.iter_mut().zip(row).for_each(|(column, &word)| *column |= word);
+        }
+        let min_x = columns.iter().position(|&word| word != 0)?;
+        let max_x = columns.iter().rposition(|&word| word != 0)?;
+
+        Some(Bounds::new(
+            (min_x * WORD_BITS + columns[min_x].trailing_zeros() as usize) as i64,
+            min_y as i64,
+            (max_x * WORD_BITS + WORD_BITS - 1 - columns[max_x].leading_zeros() as usize) as i64,
+            max_y as i64,
+        ))
+    }
+
+    /// Whether every cell is alive, in row major order.
+    pub(crate) fn iter(&self) -> impl ExactSizeIterator<Item = bool> + '_ {
+        let width = self.width;
+        (0..self.len()).map(move |idx| self.get(idx % width, idx / width))
+    }
+
+    /// The state of every cell, in row major order.
+    pub(crate) fn states(&self) -> impl ExactSizeIterator<Item = usize> + '_ {
+        let width = self.width;
+        (0..self.len()).map(move |idx| self.state(idx % width, idx / width))
+    }
+
+    /// The team of every cell, in row major order.
+    pub(crate) fn teams(&self) -> impl ExactSizeIterator<Item = usize> + '_ {
+        let width = self.width;
+        (0..self.len()).map(move |idx| self.team(idx % width, idx / width))
+    }
+
+    /// Advances the whole board one generation under `rule`, returning how many cells were born and
+    /// how many stopped being alive.
+    pub(crate) fn step(
+        &mut self,
+        rule: &Rule,
+        topology: Topology,
+    ) -> (usize, usize) {
+        self.step_with(rule, topology, cfg!(feature = "parallel"))
+    }
+
+    /// Steps like [`BitGrid::step`], spreading the work over the rayon thread pool only when
+    /// `parallel` is set.
+    fn step_with(
+        &mut self,
+        rule: &Rule,
+        topology: Topology,
+        parallel: bool,
+    ) -> (usize, usize) {
+        let mut back = std::mem::take(&mut self.back);
+        back.resize(self.words.len(), 0);
+
+        let neighborhood = rule.neighborhood();
+        if rule.range() == 1 && rule.grid() != Grid::Triangular {
+            self.step_bands(&mut back, parallel, |first_row, next| {
+                self.step_rows(rule, topology, first_row, next)
+            });
+        } else {
+            let mut sums = std::mem::take(&mut self.sums);
+            self.sum_table(&mut sums, neighborhood.reach(rule.range()), topology);
+            let up = neighborhood.rectangles(rule.range());
+            let down = match rule.grid() {
+                Grid::Triangular => up.iter().map(|r| Bounds::new(r.min_x, -r.max_y, r.max_x, -r.min_y)).collect(),
+                _ => up.clone(),
+            };
+            self.step_bands(&mut back, parallel, |first_row, next| {
+                self.count_rows(rule, &sums, [&up, &down], first_row, next)
+            });
+            self.sums = sums;
+        }
+
+        self.back = std::mem::replace(&mut self.words, back);
+
+        let (mut births, mut deaths) = (0, 0);
+        for word in 0..self.words.len() {
+            let (now, before) = (self.words[word], self.back[word]);
+            births += (now & !before).count_ones() as usize;
+            deaths += (before & !now).count_ones() as usize;
+            self.hash ^= self.keys(0, word, now ^ before);
+        }
+        if !self.ages.is_empty() {
+            self.age(rule);
+        }
+        if !self.teams.is_empty() {
+            self.recruit(rule, topology);
+        }
+        (births, deaths)
+    }
+
+    /// Keeps the teams of the cells that survived, drops those of the cells that died and puts every
+    /// newborn cell on the team of its parents, as [`Rule`] describes, using the previous
+    /// generation that `step` left in the back buffer.
+    fn recruit(
+        &mut self,
+        rule: &Rule,
+        topology: Topology,
+    ) {
+        let stride = self.stride();
+        let before = std::mem::take(&mut self.teams);
+        let mut after = before.clone();
+        let offsets: Vec<_> = (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| rule.neighborhood().contains(dx, dy, 1))
+            .collect();
+
+        for word in 0..self.words.len() {
+            let (now, previous) = (self.words[word], self.back[word]);
+            after.iter_mut().zip(&before).for_each(|(plane, old)| plane[word] = old[word] & now & previous);
+
+            let mut born = now & !previous;
+            while born != 0 {
+                let bit = born.trailing_zeros() as usize;
+                born &= born - 1;
+                let (x, y) = ((word % stride) * WORD_BITS + bit, word / stride);
+
+                let mut parents = [0; Rule::MAX_TEAMS];
+                for &(dx, dy) in &offsets {
+                    let cell = topology.resolve(x as isize + dx, y as isize + dy, self.width, self.height);
+                    if let Some((x, y)) = cell {
+                        let (word, bit) = self.position(x, y);
+                        if self.back[word] & bit != 0 {
+                            let team = before.iter().enumerate().filter(|(_, plane)| plane[word] & bit != 0);
+                            parents[team.fold(0, |team, (idx, _)| team | (1 << idx))] += 1;
+                        }
+                    }
+                }
+
+                let parents = &parents[..rule.teams()];
+                let most = parents.iter().copied().max().unwrap_or(0);
+                let mut leaders = (0..parents.len()).filter(|&team| parents[team] == most);
+                let team = match (leaders.next(), leaders.next()) {
+                    (Some(leader), None) => leader,
+                    (first, _) => parents.iter().position(|&count| count == 0).or(first).unwrap_or(0),
+                };
+                after.iter_mut().enumerate().for_each(|(idx, plane)| {
+                    if team & (1 << idx) != 0 {
+                        plane[word] |= 1 << bit;
+                    }
+                });
+            }
+        }
+
+        self.teams = after;
+        for (idx, old) in before.iter().enumerate() {
+            let plane = 1 + self.ages.len() + idx;
+            for (word, &old) in old.iter().enumerate() {
+                self.hash ^= self.keys(plane, word, old ^ self.teams[idx][word]);
+            }
+        }
+    }
+
+    /// Moves every dying cell one state on, killing those past the last state, and starts the cells
+    /// that just stopped being alive dying.
+    fn age(
+        &mut self,
+        rule: &Rule,
+    ) {
+        let mut age = vec![0; self.ages.len()];
+        for word in 0..self.words.len() {
+            age.iter_mut().zip(&self.ages).for_each(|(age, plane)| *age = plane[word]);
+            add(&mut age, self.dying(word));
+            let expired = equals(&age, rule.states() - 1);
+            let started = self.back[word] & !self.words[word];
+
+            for (plane, &age) in age.iter().enumerate() {
+                let mut next = age & !expired;
+                if plane == 0 {
+                    next |= started;
+                }
+                let changed = next ^ self.ages[plane][word];
+                self.ages[plane][word] = next;
+                self.hash ^= self.keys(plane + 1, word, changed);
+            }
+        }
+    }
+
+    /// Computes the next generation of the rows starting at `first_row` into `next`, which holds
+    /// whole rows.
+    fn step_rows(
+        &self,
+        rule: &Rule,
+        topology: Topology,
+        first_row: usize,
+        next: &mut [u64],
+    ) {
+        for (y, next) in next.chunks_mut(self.stride()).enumerate() {
+            self.step_row(rule, topology, first_row + y, next);
+        }
+    }
+
+    /// Splits the board into bands of rows and hands them to `step` on the current rayon thread
+    /// pool, along with the first row of each, or the whole board at once unless `parallel` is set.
+    /// Every row only reads the current generation and writes its own slice of the back buffer, so
+    /// the result is identical to stepping the rows in order.
+    #[cfg(feature = "parallel")]
+    fn step_bands<F: Fn(usize, &mut [u64]) + Sync>(
+        &self,
+        back: &mut [u64],
+        parallel: bool,
+        step: F,
+    ) {
+        use crate::deps::rayon::prelude::*;
+
+        let stride = self.stride();
+        if !parallel || self.words.len() < Self::MIN_PARALLEL_WORDS {
+            return step(0, back);
+        }
+
+        let band_rows = (Self::MIN_BAND_WORDS / stride).max(1);
+        back.par_chunks_mut(band_rows * stride)
+            .enumerate()
+            .for_each(|(band, next)| step(band * band_rows, next));
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn step_bands<F: Fn(usize, &mut [u64])>(
+        &self,
+        back: &mut [u64],
+        _parallel: bool,
+        step: F,
+    ) {
+        step(0, back);
+    }
+
+    /// Fills `sums` with the summed-area table of the live cells of the board padded by `range` on
+    /// every side, with the padding looked up through `topology`. Entry `(x, y)` of the table, which
+    /// is one wider and one taller than the padded board, holds the live cells above and to the left
+    /// of padded cell `(x, y)`. The buffer is kept between steps so that only its size changes.
+    fn sum_table(
+        &self,
+        sums: &mut Vec<u32>,
+        range: usize,
+        topology: Topology,
+    ) {
+        let (width, height) = (self.width, self.height);
+        let columns = width + 2 * range + 1;
+        sums.resize(columns * (height + 2 * range + 1), 0);
+        sums[..columns].iter_mut().for_each(|sum| *sum = 0);
+        for y in 1..=height + 2 * range {
+            sums[y * columns] = 0;
+            let mut row = 0;
+            for x in 1..columns {
+                let (cell_x, cell_y) = (x as isize - 1 - range as isize, y as isize - 1 - range as isize);
+                row += topology
+                    .resolve(cell_x, cell_y, width, height)
+                    .is_some_and(|(x, y)| self.get(x, y)) as u32;
+                sums[y * columns + x] = sums[(y - 1) * columns + x] + row;
+            }
+        }
+    }
+
+    /// Computes the next generation of the rows starting at `first_row` into `next` under a rule
+    /// whose neighborhood is made up of `rectangles`, counting the live cells in each with four
+    /// lookups into the summed-area table `sums`. The first set of rectangles is used for the cells
+    /// at even `x + y` and the second for the others, which only differ on a triangular grid.
+    fn count_rows(
+        &self,
+        rule: &Rule,
+        sums: &[u32],
+        rectangles: [&[Bounds]; 2],
+        first_row: usize,
+        next: &mut [u64],
+    ) {
+        let reach = rule.neighborhood().reach(rule.range());
+        let columns = self.width + 2 * reach + 1;
+        let count = |x: usize, y: usize| -> usize {
+            let rectangles = rectangles[(x + y) % 2];
+            let (x, y) = ((x + reach) as i64, (y + reach) as i64);
+            let sum = |rectangle: &Bounds| {
+                let (west, east) = ((x + rectangle.min_x) as usize, (x + rectangle.max_x) as usize + 1);
+                let (north, south) = ((y + rectangle.min_y) as usize, (y + rectangle.max_y) as usize + 1);
+                (sums[south * columns + east] + sums[north * columns + west])
+                    - (sums[north * columns + east] + sums[south * columns + west])
+            };
+            rectangles.iter().map(sum).sum::<u32>() as usize
+        };
+
+        next.iter_mut().for_each(|word| *word = 0);
+        let stride = self.stride();
+        for (row, next) in next.chunks_mut(stride).enumerate() {
+            let y = first_row + row;
+            for x in 0..self.width {
+                let alive = self.get(x, y);
+                let neighbors = count(x, y) - (alive && !rule.middle()) as usize;
+                let (word, bit) = self.position(x, y);
+                let lives = if alive {
+                    rule.survives(neighbors)
+                } else {
+                    rule.born(neighbors) && self.dying(word) & bit == 0
+                };
+                if lives {
+                    next[word - y * stride] |= bit;
+                }
+            }
+        }
+    }
+
+    /// The offsets of the eight cells around a cell, in the order [`BitGrid::step_row`] adds them.
+    const MOORE_OFFSETS: [(isize, isize); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+    /// Computes the next generation of row `y` into `next`, counting the cells around each that the
+    /// rule's neighborhood of range 1 takes in.
+    fn step_row(
+        &self,
+        rule: &Rule,
+        topology: Topology,
+        y: usize,
+        next: &mut [u64],
+    ) {
+        let stride = self.stride();
+        let north = self.virtual_row(topology, y as isize - 1);
+        let center = self.virtual_row(topology, y as isize);
+        let south = self.virtual_row(topology, y as isize + 1);
+        let neighborhood = rule.neighborhood();
+        let counted = Self::MOORE_OFFSETS.map(|(dx, dy)| neighborhood.contains(dx, dy, 1));
+        let middle = rule.middle() as usize;
+
+        for (idx, next) in next.iter_mut().enumerate() {
+            let mut count = [0u64; 4];
+            let around = [
+                north.west(idx),
+                north.words[idx],
+                north.east(idx),
+                center.west(idx),
+                center.east(idx),
+                south.west(idx),
+                south.words[idx],
+                south.east(idx),
+            ];
+            for (&neighbors, _) in around.iter().zip(&counted).filter(|(_, &counted)| counted) {
+                add(&mut count, neighbors);
+            }
+
+            let alive = center.words[idx];
+            let vacant = !alive & !self.dying(y * stride + idx);
+            let mut word = 0;
+            for neighbors in 0..=8 {
+                let matches = equals(&count, neighbors);
+                if rule.born(neighbors) {
+                    word |= matches & vacant;
+                }
+                if rule.survives(neighbors + middle) {
+                    word |= matches & alive;
+                }
+            }
+            *next = word;
+        }
+        next[stride - 1] &= self.tail_mask();
+    }
+
+    /// The cells seen at row `y`, which may be just off the board, along with the cells just past
+    /// its west and east ends.
+    ///
+    /// Every topology maps a row that is off the board onto a whole board row, possibly reversed,
+    /// or onto nothing at all, so only the two end cells need a per cell lookup.
+    fn virtual_row(
+        &self,
+        topology: Topology,
+        y: isize,
+    ) -> VirtualRow<'_> {
+        let (width, height) = (self.width, self.height);
+        let stride = self.stride();
+        let cell = |x: isize| {
+            topology
+                .resolve(x, y, width, height)
+                .is_some_and(|(x, y)| self.get(x, y))
+        };
+        let (west, east) = (cell(-1), cell(width as isize));
+
+        let words = match topology.resolve(0, y, width, height) {
+            Some((0, row)) => Cow::Borrowed(&self.words[row * stride..(row + 1) * stride]),
+            Some((_, row)) => {
+                let mut reversed = vec![0u64; stride];
+                for x in (0..width).filter(|&x| self.get(width - 1 - x, row)) {
+                    reversed[x / WORD_BITS] |= 1 << (x % WORD_BITS);
+                }
+                Cow::Owned(reversed)
+            }
+            None => Cow::Owned(vec![0u64; stride]),
+        };
+
+        VirtualRow {
+            words,
+            west,
+            east,
+            width,
+        }
+    }
+}
+
+/// A row of words with the two cells that lie just past its ends.
+struct VirtualRow<'a> {
+    words: Cow<'a, [u64]>,
+    west:  bool,
+    east:  bool,
+    width: usize,
+}
+
+impl<'a> VirtualRow<'a> {
+    /// Word `idx` shifted so that each bit holds its western neighbor.
+    fn west(
+        &self,
+        idx: usize,
+    ) -> u64 {
+        let carry = if idx == 0 {
+            self.west as u64
+        } else {
+            self.words[idx - 1] >> (WORD_BITS - 1)
+        };
+        (self.words[idx] << 1) | carry
+    }
+
+    /// Word `idx` shifted so that each bit holds its eastern neighbor.
+    fn east(
+        &self,
+        idx: usize,
+    ) -> u64 {
+        let carry = if idx == self.words.len() - 1 {
+            (self.east as u64) << ((self.width - 1) % WORD_BITS)
+        } else {
+            self.words[idx + 1] << (WORD_BITS - 1)
+        };
+        (self.words[idx] >> 1) | carry
+    }
+}
+
+/// The Zobrist key of bit `plane` of the cell at row major index `idx`. Keys are drawn from
+/// [SplitMix64] on demand rather than stored, so that they cost no memory and stay the same for every
+/// board size.
+///
+/// [SplitMix64]: https://prng.di.unimi.it/splitmix64.c
+fn cell_key(
+    plane: usize,
+    idx: usize,
+) -> u64 {
+    let counter = (idx as u64) ^ ((plane as u64) << 56);
+    let mut z = counter.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Adds a one bit value into each lane of the little endian counter `count`, which has one bit per
+/// word.
+fn add(
+    count: &mut [u64],
+    mut carry: u64,
+) {
+    for bit in count.iter_mut() {
+        let overflow = *bit & carry;
+        *bit ^= carry;
+        carry = overflow;
+    }
+}
+
+/// The lanes of `count` that are equal to `value`.
+fn equals(
+    count: &[u64],
+    value: usize,
+) -> u64 {
+    count.iter().enumerate().fold(!0, |matches, (bit, &lanes)| {
+        if value & (1 << bit) != 0 {
+            matches & lanes
+        } else {
+            matches & !lanes
+        }
+    })
+}
+
+/// The wire form of a [`BitGrid`], which is the grid without its back buffer.
+#[derive(serde::Deserialize)]
+struct BitGridRepr {
+    width:  usize,
+    height: usize,
+    words:  Vec<u64>,
+    #[serde(default)]
+    ages:   Vec<Vec<u64>>,
+    #[serde(default)]
+    teams:  Vec<Vec<u64>>,
+    /// Read only to be skipped, since the hash is recomputed rather than trusted.
+    #[serde(default, rename = "hash")]
+    _hash:  u64,
+}
+
+/// Checks that the planes hold exactly the cells of the board and that each cell is in one state,
+/// since a deserialized grid may come from anywhere and the stepping code indexes by these sizes.
+impl TryFrom<BitGridRepr> for BitGrid {
+    type Error = Error;
+
+    fn try_from(repr: BitGridRepr) -> Result<Self, Self::Error> {
+        let invalid = |reason| Err(Error::InvalidBoard { reason });
+        check_dimensions(repr.width, repr.height)?;
+        let len = Self::stride_of(repr.width) * repr.height;
+
+        if repr.words.len() != len || repr.ages.iter().chain(&repr.teams).any(|plane| plane.len() != len) {
+            return invalid("the cell planes do not match the board size");
+        }
+        if repr.ages.len() > Self::planes_for(Rule::MAX_STATES) || repr.teams.len() > Self::planes_for(Rule::MAX_TEAMS)
+        {
+            return invalid("there are more state or team planes than any rule needs");
+        }
+
+        let mut grid = Self {
+            width:  repr.width,
+            height: repr.height,
+            words:  repr.words,
+            ages:   repr.ages,
+            teams:  repr.teams,
+            hash:   0,
+            back:   Vec::new(),
+            sums:   Vec::new(),
+        };
+        let stride = grid.stride();
+        for word in 0..len {
+            let alive = grid.words[word];
+            let past_end = if word % stride == stride - 1 {
+                !grid.tail_mask()
+            } else {
+                0
+            };
+            if (0..grid.planes()).any(|plane| grid.plane(plane)[word] & past_end != 0) {
+                return invalid("cells are set past the end of a row");
+            }
+            if grid.dying(word) & alive != 0 || grid.teams.iter().any(|plane| plane[word] & !alive != 0) {
+                return invalid("a cell is both alive and dying, or on a team without being alive");
+            }
+        }
+        grid.rehash();
+        Ok(grid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Soup;
+    use serde_json::json;
+
+    /// A `width` by `height` grid for `rule` filled with a reproducible soup, with live cells spread
+    /// over the teams of a colored rule.
+    fn soup(
+        rule: &Rule,
+        width: usize,
+        height: usize,
+        seed: u64,
+    ) -> BitGrid {
+        let pattern = Soup::new(seed, 0.4).generate(width, height).unwrap();
+        let mut grid = BitGrid::new(width, height);
+        grid.set_states(rule.states());
+        grid.set_teams(rule.teams());
+        for (idx, (x, y)) in pattern.alive_cells().enumerate() {
+            grid.set(x, y, true);
+            grid.set_team(x, y, idx % rule.teams());
+        }
+        grid
+    }
+
+    /// The next generation of `grid` under B3/S23, counting the neighbors of one cell at a time.
+    fn naive_step(
+        grid: &BitGrid,
+        topology: Topology,
+    ) -> Vec<bool> {
+        let (width, height) = (grid.width, grid.height);
+        let mut next = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let neighbors = BitGrid::MOORE_OFFSETS
+                    .iter()
+                    .filter_map(|&(dx, dy)| topology.resolve(x as isize + dx, y as isize + dy, width, height))
+                    .filter(|&(x, y)| grid.get(x, y))
+                    .count();
+                next.push(neighbors == 3 || (neighbors == 2 && grid.get(x, y)));
+            }
+        }
+        next
+    }
+
+    #[test]
+    fn steps_match_a_naive_step_on_every_topology() {
+        let rule = Rule::default();
+        let sizes = [(1, 5), (3, 1), (5, 4), (64, 3), (70, 9), (130, 7)];
+        for &topology in &Topology::ALL {
+            for &(width, height) in &sizes {
+                let mut grid = soup(&rule, width, height, (width * height) as u64);
+                for generation in 0..8 {
+                    let expected = naive_step(&grid, topology);
+                    grid.step_with(&rule, topology, false);
+                    let context = format!("{} {}x{} generation {}", topology, width, height, generation);
+                    assert_eq!(grid.iter().collect::<Vec<_>>(), expected, "{}", context);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_summed_area_table_is_reused_across_ranges() {
+        let wide: Rule = "R3,C0,M1,S4..9,B5..7,NM".parse().unwrap();
+        let narrow: Rule = "R2,C0,M1,S3..6,B4..5,NC".parse().unwrap();
+        let mut grid = soup(&wide, 70, 20, 5);
+        grid.step(&wide, Topology::Torus);
+        grid.step(&wide, Topology::Torus);
+
+        for &(rule, topology) in &[(&narrow, Topology::Plane), (&wide, Topology::Mirror), (&narrow, Topology::Torus)] {
+            let mut fresh = grid.clone();
+            grid.step(rule, topology);
+            fresh.step(rule, topology);
+            assert!(grid.words == fresh.words, "{} {}", rule, topology);
+        }
+    }
+
+    #[test]
+    fn deserializing_recomputes_the_hash() {
+        let rule: Rule = "B36/S23/C4".parse().unwrap();
+        let mut grid = soup(&rule, 70, 9, 3);
+        grid.step(&rule, Topology::Torus);
+
+        let mut value = serde_json::to_value(&grid).unwrap();
+        value["hash"] = json!(12345);
+        let read: BitGrid = serde_json::from_value(value).unwrap();
+        assert!(read.words == grid.words && read.ages == grid.ages);
+        assert_eq!(read.hash, grid.hash);
+    }
+
+    #[test]
+    fn deserializing_checks_the_planes() {
+        let rule: Rule = "QuadLife".parse().unwrap();
+        let grid = soup(&rule, 70, 9, 3);
+        let value = serde_json::to_value(&grid).unwrap();
+        assert!(serde_json::from_value::<BitGrid>(value.clone()).is_ok());
+
+        let tampered = |edit: &dyn Fn(&mut serde_json::Value)| {
+            let mut value = value.clone();
+            edit(&mut value);
+            serde_json::from_value::<BitGrid>(value).unwrap_err().to_string()
+        };
+        let stride = BitGrid::stride_of(70);
+        assert!(tampered(&|value| value["width"] = json!(200)).contains("invalid board"));
+        assert!(tampered(&|value| value["height"] = json!(0)).contains("invalid board dimensions"));
+        assert!(tampered(&|value| value["words"].as_array_mut().unwrap().truncate(5)).contains("invalid board"));
+        assert!(tampered(&|value| value["teams"][1] = json!(vec![0u64; 3])).contains("invalid board"));
+        assert!(tampered(&|value| value["teams"] = json!(vec![vec![0u64; stride * 9]; 3])).contains("invalid board"));
+        // Past the end of the first row, on a dead cell, and dying while alive.
+        assert!(tampered(&|value| value["words"][stride - 1] = json!(u64::MAX)).contains("invalid board"));
+        assert!(tampered(&|value| value["teams"][0][0] = json!(u64::MAX)).contains("invalid board"));
+        let mut dying = value.clone();
+        dying["ages"] = json!([dying["words"].clone()]);
+        assert!(serde_json::from_value::<BitGrid>(dying).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn parallel_steps_match_serial_steps() {
+        let rules = ["B3/S23", "B36/S23/C4", "QuadLife", "B2/S34H", "R2,C0,M1,S4..7,B5..7,NM"];
+        // Widths around multiples of 64, with heights that give at least `MIN_PARALLEL_WORDS` words
+        // and are not multiples of the rows in a band.
+        let sizes = [(64, 4500), (100, 2500), (1000, 300), (1027, 250)];
+
+        for rule in &rules {
+            let rule: Rule = rule.parse().unwrap();
+            for &(width, height) in &sizes {
+                let stride = BitGrid::stride_of(width);
+                assert!(stride * height >= BitGrid::MIN_PARALLEL_WORDS);
+                assert_ne!(height % (BitGrid::MIN_BAND_WORDS / stride).max(1), 0);
+
+                for &topology in &[Topology::Torus, Topology::Plane] {
+                    let mut serial = soup(&rule, width, height, width as u64);
+                    let mut parallel = serial.clone();
+                    for generation in 0..3 {
+                        let serial_changes = serial.step_with(&rule, topology, false);
+                        let parallel_changes = parallel.step_with(&rule, topology, true);
+
+                        let context = format!("{} {}x{} {} generation {}", rule, width, height, topology, generation);
+                        assert_eq!(serial_changes, parallel_changes, "{}", context);
+                        assert!(serial.words == parallel.words, "{}", context);
+                        assert!(serial.ages == parallel.ages, "{}", context);
+                        assert!(serial.teams == parallel.teams, "{}", context);
+                        assert_eq!(serial.hash, parallel.hash, "{}", context);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use crate::deps::serde;
+use std::fmt;
+
+/// An inclusive rectangle of cell coordinates, such as the extent of the live cells on a board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Bounds {
+    pub min_x: i64,
+    pub min_y: i64,
+    pub max_x: i64,
+    pub max_y: i64,
+}
+
+impl Bounds {
+    pub fn new(
+        min_x: i64,
+        min_y: i64,
+        max_x: i64,
+        max_y: i64,
+    ) -> Self {
+        debug_assert!(min_x <= max_x && min_y <= max_y);
+        Self {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        }
+    }
+
+    pub fn width(&self) -> u64 {
+        (i128::from(self.max_x) - i128::from(self.min_x) + 1) as u64
+    }
+
+    pub fn height(&self) -> u64 {
+        (i128::from(self.max_y) - i128::from(self.min_y) + 1) as u64
+    }
+
+    pub fn contains(
+        &self,
+        x: i64,
+        y: i64,
+    ) -> bool {
+        self.min_x <= x && x <= self.max_x && self.min_y <= y && y <= self.max_y
+    }
+
+    /// The smallest rectangle covering both `self` and `other`.
+    pub fn union(
+        &self,
+        other: &Bounds,
+    ) -> Bounds {
+        Bounds {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+}
+
+impl fmt::Display for Bounds {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(f, "({}, {})..=({}, {})", self.min_x, self.min_y, self.max_x, self.max_y)
+    }
+}
@@ -0,0 +1,199 @@
+//! A built in catalog of well known Life patterns, looked up by name.
+
+use crate::{
+    Error,
+    Pattern,
+};
+
+/// The name and RLE of every pattern in the catalog. Names are lowercase with words joined by
+/// dashes, as on the [LifeWiki].
+///
+/// [LifeWiki]: https://conwaylife.com/wiki/
+const CATALOG: &[(&str, &str)] = &[
+    // Still lifes.
+    ("block", "2o$2o!"),
+    ("beehive", "b2o$o2bo$b2o!"),
+    ("loaf", "b2o$o2bo$bobo$2bo!"),
+    ("boat", "2o$obo$bo!"),
+    ("tub", "bo$obo$bo!"),
+    // Oscillators.
+    ("blinker", "3o!"),
+    ("toad", "b3o$3o!"),
+    ("beacon", "2o$2o$2b2o$2b2o!"),
+    ("pulsar", "2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!"),
+    ("pentadecathlon", "2bo4bo$2ob4ob2o$2bo4bo!"),
+    // Spaceships.
+    ("glider", "bo$2bo$3o!"),
+    ("lwss", "bo2bo$o$o3bo$4o!"),
+    ("mwss", "3bo$bo3bo$o$o4bo$5o!"),
+    ("hwss", "3b2o$bo4bo$o$o5bo$6o!"),
+    // Methuselahs.
+    ("r-pentomino", "b2o$2o$bo!"),
+    ("acorn", "bo$3bo$2o2b3o!"),
+    ("diehard", "6bo$2o$bo3b3o!"),
+    // Guns.
+    (
+        "gosper-glider-gun",
+        "24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!",
+    ),
+];
+
+impl Pattern {
+    /// The names of the patterns in the catalog, for [`Pattern::named`].
+    pub fn catalog() -> impl ExactSizeIterator<Item = &'static str> {
+        CATALOG.iter().map(|&(name, _)| name)
+    }
+
+    /// The catalog pattern called `name`, matched case insensitively and with spaces or underscores
+    /// standing in for dashes, so that `Gosper glider gun` finds `gosper-glider-gun`.
+    pub fn named(name: &str) -> Result<Self, Error> {
+        let wanted = name.trim().replace(&[' ', '_'][..], "-");
+        let (name, body) = CATALOG
+            .iter()
+            .find(|(candidate, _)| candidate.eq_ignore_ascii_case(&wanted))
+            .ok_or_else(|| Error::UnknownPattern(name.to_string()))?;
+
+        let mut pattern = Pattern::from_rle(body).expect("catalog patterns are valid RLE");
+        pattern.push_comment(format!("N {}", name));
+        Ok(pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Blend,
+        Message,
+        Simulation,
+        Topology,
+        Transform,
+    };
+    use std::collections::BTreeSet;
+
+    type Cells = BTreeSet<(usize, usize)>;
+
+    /// The live cells of rows drawn with `O`, shifted right by `x` and down by `y`.
+    fn cells(
+        rows: &[&str],
+        x: usize,
+        y: usize,
+    ) -> Cells {
+        let mut cells = Cells::new();
+        for (row, line) in rows.iter().enumerate() {
+            for (col, ch) in line.chars().enumerate() {
+                if ch == 'O' {
+                    cells.insert((x + col, y + row));
+                }
+            }
+        }
+        cells
+    }
+
+    fn board(topology: Topology) -> Simulation {
+        Simulation::builder().width(5).height(5).topology(topology).build().unwrap()
+    }
+
+    fn alive(simulation: &Simulation) -> Cells {
+        simulation.to_pattern().alive_cells().collect()
+    }
+
+    #[test]
+    fn every_entry_parses() {
+        assert_eq!(Pattern::catalog().len(), CATALOG.len());
+        for name in Pattern::catalog() {
+            let pattern = Pattern::named(name).unwrap();
+            assert!(pattern.population() > 0, "{}", name);
+            assert_eq!(pattern.comments(), &[format!("N {}", name)][..]);
+        }
+        assert_eq!(Pattern::named("Gosper glider_gun").unwrap(), Pattern::named("gosper-glider-gun").unwrap());
+        assert!(matches!(Pattern::named("spaceship"), Err(Error::UnknownPattern(_))));
+    }
+
+    #[test]
+    fn a_glider_is_placed_with_each_transform() {
+        let expected = [
+            (Transform::Identity, [".O.", "..O", "OOO"]),
+            (Transform::Rotate90, ["O..", "O.O", "OO."]),
+            (Transform::Rotate180, ["OOO", "O..", ".O."]),
+            (Transform::Rotate270, [".OO", "O.O", "..O"]),
+            (Transform::FlipX, [".O.", "O..", "OOO"]),
+            (Transform::FlipY, ["OOO", "..O", ".O."]),
+            (Transform::FlipDiagonal, ["..O", "O.O", ".OO"]),
+            (Transform::FlipAntiDiagonal, ["OO.", "O.O", "O.."]),
+        ];
+        for (transform, rows) in &expected {
+            let mut simulation = board(Topology::Plane);
+            simulation
+                .update(Message::PlacePattern {
+                    name:      "glider".to_string(),
+                    x:         1,
+                    y:         2,
+                    transform: *transform,
+                    blend:     Blend::Or,
+                })
+                .unwrap();
+            assert_eq!(alive(&simulation), cells(rows, 1, 2), "{:?}", transform);
+        }
+    }
+
+    #[test]
+    fn a_glider_is_placed_with_each_blend() {
+        let glider = cells(&[".O.", "..O", "OOO"], 0, 0);
+        let before: Cells = [(0, 0), (1, 0), (4, 4)].iter().copied().collect();
+
+        for &blend in &[Blend::Or, Blend::Xor, Blend::Overwrite] {
+            let mut simulation = board(Topology::Plane);
+            for &(x, y) in &before {
+                simulation.set_cell(x, y, 1).unwrap();
+            }
+            simulation.place_pattern(&Pattern::named("glider").unwrap(), 0, 0, blend);
+
+            let expected: Cells = match blend {
+                Blend::Or => glider.union(&before).copied().collect(),
+                Blend::Xor => glider.symmetric_difference(&before).copied().collect(),
+                Blend::Overwrite => glider.iter().copied().chain(Some((4, 4))).collect(),
+            };
+            assert_eq!(alive(&simulation), expected, "{:?}", blend);
+        }
+    }
+
+    #[test]
+    fn a_glider_is_clipped_at_the_edges() {
+        let glider = Pattern::named("glider").unwrap();
+
+        let mut simulation = board(Topology::Plane);
+        simulation.place_pattern(&glider, 3, 3, Blend::Or);
+        assert_eq!(alive(&simulation), cells(&["O"], 4, 3));
+
+        let mut simulation = board(Topology::Plane);
+        simulation.place_pattern(&glider, -2, -2, Blend::Overwrite);
+        assert_eq!(alive(&simulation), cells(&["O"], 0, 0));
+
+        // A torus wraps the overhang around instead.
+        let mut simulation = board(Topology::Torus);
+        simulation.place_pattern(&glider, 3, 3, Blend::Or);
+        let wrapped: Cells = [(4, 3), (0, 4), (3, 0), (4, 0), (0, 0)].iter().copied().collect();
+        assert_eq!(alive(&simulation), wrapped);
+    }
+
+    #[test]
+    fn place_pattern_reads_from_json() {
+        let json = r#"{"PlacePattern":{"name":"glider","x":3,"y":-1,"transform":"FlipY","blend":"Xor"}}"#;
+        let message: Message = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            message,
+            Message::PlacePattern {
+                name:      "glider".to_string(),
+                x:         3,
+                y:         -1,
+                transform: Transform::FlipY,
+                blend:     Blend::Xor,
+            }
+        );
+
+        let mut simulation = board(Topology::Plane);
+        simulation.update(message).unwrap();
+        assert_eq!(alive(&simulation), cells(&["O"], 4, 1));
+    }
+}
@@ -0,0 +1,412 @@
+//! Splits a board into separate objects and tells what each one is, in the style of the [apgsearch]
+//! census.
+//!
+//! [apgsearch]: https://conwaylife.com/wiki/Apgsearch
+
+use crate::{
+    deps::serde,
+    Error,
+    Simulation,
+    SparseLife,
+    Transform,
+};
+use std::{
+    collections::{
+        BTreeMap,
+        HashSet,
+        VecDeque,
+    },
+    fmt,
+};
+
+/// The symbols of the extended Wechsler format, each standing for a column of five cells with the
+/// top cell in the lowest bit.
+const STRIP_SYMBOLS: &[u8; 32] = b"0123456789abcdefghijklmnopqrstuv";
+
+/// The symbols after a `y`, standing for runs of 4 to 39 empty columns.
+const GAP_SYMBOLS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// What an object turns out to be when run on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ObjectKind {
+    /// Unchanged from one generation to the next.
+    StillLife,
+    /// Back to where it started every `period` generations.
+    Oscillator { period: usize },
+    /// Back to its starting shape every `period` generations, moved by `dx` columns and `dy` rows.
+    Spaceship { period: usize, dx: i64, dy: i64 },
+    /// Did not repeat within the period limit of the census, such as an object that dies, grows
+    /// forever or has yet to settle down.
+    Unknown,
+}
+
+impl ObjectKind {
+    /// The number of generations after which the object repeats, 1 for a still life.
+    pub fn period(&self) -> Option<usize> {
+        match self {
+            ObjectKind::StillLife => Some(1),
+            ObjectKind::Oscillator { period } | ObjectKind::Spaceship { period, .. } => Some(*period),
+            ObjectKind::Unknown => None,
+        }
+    }
+}
+
+impl fmt::Display for ObjectKind {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        match self {
+            ObjectKind::StillLife => f.write_str("still life"),
+            ObjectKind::Oscillator { period } => write!(f, "oscillator with period {}", period),
+            ObjectKind::Spaceship { period, dx, dy } => {
+                write!(f, "spaceship moving ({}, {}) every {} generations", dx, dy, period)
+            }
+            ObjectKind::Unknown => f.write_str("unknown"),
+        }
+    }
+}
+
+/// One group of live cells far enough from every other live cell to evolve on its own.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Object {
+    /// The board coordinates of the live cells of the object.
+    pub cells:   Vec<(usize, usize)>,
+    pub kind:    ObjectKind,
+    /// The [apgcode] naming the object, such as `xs4_33` for a block or `xq4_153` for a glider, or
+    /// `None` when its kind is unknown.
+    ///
+    /// [apgcode]: https://conwaylife.com/wiki/Apgcode
+    pub apgcode: Option<String>,
+}
+
+/// Every object on a board, see [`Simulation::census`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Census {
+    /// The objects in the row major order of their first cell.
+    pub objects: Vec<Object>,
+}
+
+impl Census {
+    /// The longest period looked for by [`Simulation::census`].
+    pub const DEFAULT_MAX_PERIOD: usize = 64;
+
+    /// How many objects there are of each apgcode. Objects of unknown kind are left out, see
+    /// [`Census::unknown`].
+    pub fn counts(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for apgcode in self.objects.iter().filter_map(|object| object.apgcode.as_deref()) {
+            *counts.entry(apgcode).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// How many objects could not be classified.
+    pub fn unknown(&self) -> usize {
+        self.objects.iter().filter(|object| object.kind == ObjectKind::Unknown).count()
+    }
+}
+
+impl Simulation {
+    /// Takes a [`Census`] of the board, looking for periods of up to [`Census::DEFAULT_MAX_PERIOD`].
+    pub fn census(&self) -> Result<Census, Error> {
+        self.census_up_to(Census::DEFAULT_MAX_PERIOD)
+    }
+
+    /// Splits the live cells into objects and runs each one on an empty unbounded plane for up to
+    /// `max_period` generations to find out what it is.
+    ///
+    /// Cells within two cells of each other share a neighbor and so belong to the same object.
+    /// Objects that are further apart may still collide later on, which the census does not look
+    /// ahead for. Teams are ignored, and rules that [`SparseLife`] cannot run fail with
+    /// [`Error::Unsupported`].
+    pub fn census_up_to(
+        &self,
+        max_period: usize,
+    ) -> Result<Census, Error> {
+        let empty = SparseLife::new(self.rule().with_teams(1)?)?;
+        let objects = self
+            .separate()
+            .into_iter()
+            .map(|(cells, plane)| {
+                let (kind, apgcode) = classify(empty.clone(), &plane, max_period);
+                Object { cells, kind, apgcode }
+            })
+            .collect();
+        Ok(Census { objects })
+    }
+
+    /// Groups the live cells into objects, laying each out on the plane so that an object straddling
+    /// an edge of the board stays in one piece.
+    fn separate(&self) -> Vec<Piece> {
+        let (width, height) = (self.width(), self.height());
+        let mut seen = HashSet::new();
+        let mut objects = Vec::new();
+
+        for idx in 0..self.cellules.len() {
+            let (x, y) = (idx % width, idx / width);
+            if !self.cellules.get(x, y) || !seen.insert((x, y)) {
+                continue;
+            }
+
+            let (mut cells, mut plane) = (Vec::new(), Vec::new());
+            let mut queue = VecDeque::new();
+            queue.push_back((x, y, x as i64, y as i64));
+            while let Some((x, y, px, py)) = queue.pop_front() {
+                cells.push((x, y));
+                plane.push((px, py));
+                for dy in -2..=2 {
+                    for dx in -2..=2 {
+                        let neighbor = self.topology.resolve(x as isize + dx, y as isize + dy, width, height);
+                        if let Some((nx, ny)) = neighbor {
+                            if self.cellules.get(nx, ny) && seen.insert((nx, ny)) {
+                                queue.push_back((nx, ny, px + dx as i64, py + dy as i64));
+                            }
+                        }
+                    }
+                }
+            }
+            objects.push((cells, plane));
+        }
+
+        objects
+    }
+}
+
+/// The board cells of an object along with the same cells laid out on the plane.
+type Piece = (Vec<(usize, usize)>, Vec<(i64, i64)>);
+
+/// Runs the cells on their own in an otherwise empty `universe` until they repeat, returning what
+/// they are and their apgcode.
+fn classify(
+    mut universe: SparseLife,
+    cells: &[(i64, i64)],
+    max_period: usize,
+) -> (ObjectKind, Option<String>) {
+    for &(x, y) in cells {
+        universe.set(x, y, true);
+    }
+
+    let (start, origin) = normalize(universe.alive_cells());
+    let mut phases = vec![start];
+    for period in 1..=max_period {
+        universe.step();
+        let (phase, position) = normalize(universe.alive_cells());
+        if phase.is_empty() {
+            break;
+        }
+        if phase == phases[0] {
+            let (dx, dy) = (position.0 - origin.0, position.1 - origin.1);
+            let (kind, prefix) = match (period, dx, dy) {
+                (1, 0, 0) => (ObjectKind::StillLife, format!("xs{}", cells.len())),
+                (period, 0, 0) => (ObjectKind::Oscillator { period }, format!("xp{}", period)),
+                (period, dx, dy) => (ObjectKind::Spaceship { period, dx, dy }, format!("xq{}", period)),
+            };
+            return (kind, Some(format!("{}_{}", prefix, canonical_code(&phases))));
+        }
+        phases.push(phase);
+    }
+
+    (ObjectKind::Unknown, None)
+}
+
+/// The cells moved so that the smallest coordinates are zero, sorted, along with the smallest
+/// coordinates they had.
+fn normalize<I>(cells: I) -> (Vec<(usize, usize)>, (i64, i64))
+where
+    I: IntoIterator<Item = (i64, i64)>,
+{
+    let cells: Vec<_> = cells.into_iter().collect();
+    let min_x = cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+    let min_y = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+
+    let mut normalized: Vec<_> = cells.iter().map(|&(x, y)| ((x - min_x) as usize, (y - min_y) as usize)).collect();
+    normalized.sort_unstable();
+    (normalized, (min_x, min_y))
+}
+
+/// The apgcode body of an object: the shortest extended Wechsler encoding of any phase in any
+/// orientation, ties going to the first in ASCII order.
+fn canonical_code(phases: &[Vec<(usize, usize)>]) -> String {
+    phases
+        .iter()
+        .flat_map(|phase| Transform::ALL.iter().map(move |&transform| wechsler(phase, transform)))
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+        .unwrap_or_default()
+}
+
+/// Encodes normalized cells turned by `transform` in the [extended Wechsler format]: strips of five
+/// rows separated by `z`, each written as one symbol per column with runs of empty columns
+/// shortened to `w`, `x` or `y` and trailing empty columns dropped.
+///
+/// [extended Wechsler format]: https://conwaylife.com/wiki/Apgcode#Extended_Wechsler_format
+fn wechsler(
+    cells: &[(usize, usize)],
+    transform: Transform,
+) -> String {
+    let width = cells.iter().map(|&(x, _)| x + 1).max().unwrap_or(0);
+    let height = cells.iter().map(|&(_, y)| y + 1).max().unwrap_or(0);
+    let (turned_width, turned_height) = if transform.transposes() {
+        (height, width)
+    } else {
+        (width, height)
+    };
+
+    let mut strips = vec![vec![0u8; turned_width]; turned_height.div_ceil(5)];
+    for &(x, y) in cells {
+        let (x, y) = transform.apply(x, y, width, height);
+        strips[y / 5][x] |= 1 << (y % 5);
+    }
+
+    let mut code = String::new();
+    for (idx, strip) in strips.iter().enumerate() {
+        if idx > 0 {
+            code.push('z');
+        }
+        let used = strip.iter().rposition(|&column| column != 0).map_or(0, |last| last + 1);
+        let mut gap = 0;
+        for &column in &strip[..used] {
+            if column == 0 {
+                gap += 1;
+                continue;
+            }
+            push_gap(&mut code, gap);
+            gap = 0;
+            code.push(STRIP_SYMBOLS[column as usize] as char);
+        }
+    }
+    code
+}
+
+fn push_gap(
+    code: &mut String,
+    mut gap: usize,
+) {
+    while gap > 0 {
+        let run = gap.min(39);
+        match run {
+            1 => code.push('0'),
+            2 => code.push('w'),
+            3 => code.push('x'),
+            run => {
+                code.push('y');
+                code.push(GAP_SYMBOLS[run - 4] as char);
+            }
+        }
+        gap -= run;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Topology;
+
+    fn board(
+        width: usize,
+        height: usize,
+        topology: Topology,
+    ) -> Simulation {
+        Simulation::builder()
+            .width(width)
+            .height(height)
+            .topology(topology)
+            .build()
+            .unwrap()
+    }
+
+    /// Brings the `O` cells of `rows` to life with the top left corner at `(x, y)`, wrapping around
+    /// the edges of the board.
+    fn draw(
+        simulation: &mut Simulation,
+        rows: &[&str],
+        x: usize,
+        y: usize,
+    ) {
+        let (width, height) = (simulation.width(), simulation.height());
+        for (dy, row) in rows.iter().enumerate() {
+            for (dx, _) in row.char_indices().filter(|&(_, ch)| ch == 'O') {
+                simulation.set_cell((x + dx) % width, (y + dy) % height, 1).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn known_objects_get_their_apgcodes() {
+        let mut simulation = board(40, 12, Topology::Torus);
+        draw(&mut simulation, &["OO", "OO"], 1, 1);
+        draw(&mut simulation, &["OOO"], 7, 1);
+        draw(&mut simulation, &[".O.", "..O", "OOO"], 14, 1);
+        draw(&mut simulation, &[".OO.", "O..O", ".OO."], 21, 1);
+        draw(&mut simulation, &[".O..O", "O....", "O...O", "OOOO."], 29, 1);
+
+        let census = simulation.census().unwrap();
+        let codes = census
+            .objects
+            .iter()
+            .map(|object| (object.apgcode.as_deref(), object.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(codes, [
+            (Some("xs4_33"), ObjectKind::StillLife),
+            (Some("xp2_7"), ObjectKind::Oscillator { period: 2 }),
+            (Some("xq4_153"), ObjectKind::Spaceship { period: 4, dx: 1, dy: 1 }),
+            (Some("xs6_696"), ObjectKind::StillLife),
+            (Some("xq4_6frc"), ObjectKind::Spaceship { period: 4, dx: -2, dy: 0 }),
+        ]);
+        assert_eq!(census.unknown(), 0);
+        assert_eq!(census.counts().values().sum::<usize>(), 5);
+    }
+
+    #[test]
+    fn objects_across_torus_edges_stay_whole() {
+        let mut simulation = board(12, 10, Topology::Torus);
+        draw(&mut simulation, &["OO", "OO"], 11, 9);
+        draw(&mut simulation, &[".O.", "..O", "OOO"], 4, 8);
+
+        let census = simulation.census().unwrap();
+        assert_eq!(census.objects.len(), 2);
+        assert_eq!(census.counts(), [("xq4_153", 1), ("xs4_33", 1)].iter().copied().collect());
+
+        let block = census.objects.iter().find(|object| object.apgcode.as_deref() == Some("xs4_33"));
+        let mut cells = block.map(|block| block.cells.clone()).unwrap_or_default();
+        cells.sort_unstable();
+        assert_eq!(cells, [(0, 0), (0, 9), (11, 0), (11, 9)]);
+    }
+
+    #[test]
+    fn plane_edges_keep_objects_apart() {
+        let mut simulation = board(12, 10, Topology::Plane);
+        simulation.set_cell(0, 4, 1).unwrap();
+        simulation.set_cell(0, 5, 1).unwrap();
+        simulation.set_cell(11, 4, 1).unwrap();
+        simulation.set_cell(11, 5, 1).unwrap();
+
+        let census = simulation.census().unwrap();
+        assert_eq!(census.objects.len(), 2);
+        assert_eq!(census.unknown(), 2);
+    }
+
+    #[test]
+    fn dying_objects_are_unknown() {
+        let mut simulation = board(12, 10, Topology::Torus);
+        draw(&mut simulation, &["OO"], 1, 1);
+        draw(&mut simulation, &["OO", "OO"], 6, 6);
+
+        let census = simulation.census().unwrap();
+        let domino = &census.objects[0];
+        assert_eq!(domino.cells, [(1, 1), (2, 1)]);
+        assert_eq!((domino.kind, domino.apgcode.as_deref()), (ObjectKind::Unknown, None));
+        assert_eq!(domino.kind.period(), None);
+        assert_eq!(census.unknown(), 1);
+        assert_eq!(census.counts(), [("xs4_33", 1)].iter().copied().collect());
+    }
+
+    #[test]
+    fn period_limit_leaves_slower_objects_unknown() {
+        let mut simulation = board(12, 10, Topology::Torus);
+        draw(&mut simulation, &["OOO"], 4, 4);
+
+        assert_eq!(simulation.census_up_to(1).unwrap().unknown(), 1);
+        assert_eq!(simulation.census_up_to(2).unwrap().counts(), [("xp2_7", 1)].iter().copied().collect());
+    }
+}
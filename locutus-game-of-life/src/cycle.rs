@@ -0,0 +1,230 @@
+use crate::{
+    deps::serde,
+    Error,
+};
+use std::{
+    collections::VecDeque,
+    fmt,
+    str::FromStr,
+};
+
+/// What a board has settled into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Stabilization {
+    /// Every cell has died and none can be born out of nothing.
+    Empty,
+    /// The board no longer changes.
+    Still,
+    /// The board repeats itself every `period` generations.
+    Periodic { period: usize },
+}
+
+impl Stabilization {
+    /// The number of generations after which the board repeats.
+    pub fn period(&self) -> usize {
+        match self {
+            Stabilization::Empty | Stabilization::Still => 1,
+            Stabilization::Periodic { period } => *period,
+        }
+    }
+}
+
+impl fmt::Display for Stabilization {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        match self {
+            Stabilization::Empty => f.write_str("empty"),
+            Stabilization::Still => f.write_str("still"),
+            Stabilization::Periodic { period } => write!(f, "periodic with period {}", period),
+        }
+    }
+}
+
+/// What a [`crate::Simulation`] does on its own once it has stabilized.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum StabilizationPolicy {
+    /// Keep stepping.
+    #[default]
+    Continue,
+    /// Move to [`crate::State::Pausing`], from where the session may be started again.
+    Pause,
+    /// Move to [`crate::State::Ended`].
+    End,
+}
+
+impl StabilizationPolicy {
+    pub const ALL: [StabilizationPolicy; 3] = [
+        StabilizationPolicy::Continue,
+        StabilizationPolicy::Pause,
+        StabilizationPolicy::End,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StabilizationPolicy::Continue => "continue",
+            StabilizationPolicy::Pause => "pause",
+            StabilizationPolicy::End => "end",
+        }
+    }
+}
+
+impl fmt::Display for StabilizationPolicy {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for StabilizationPolicy {
+    type Err = Error;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        StabilizationPolicy::ALL
+            .iter()
+            .copied()
+            .find(|candidate| candidate.as_str().eq_ignore_ascii_case(policy.trim()))
+            .ok_or_else(|| Error::ParseStabilizationPolicy(policy.to_string()))
+    }
+}
+
+/// The hashes of the last `window` generations, used to spot a board that has started repeating.
+///
+/// Only hashes are kept, so two different boards with the same 64 bit hash would be mistaken for a
+/// repeat. Periods longer than the window go unnoticed.
+#[derive(Debug, Clone)]
+pub(crate) struct CycleDetector {
+    window: usize,
+    hashes: VecDeque<u64>,
+}
+
+impl CycleDetector {
+    pub(crate) const DEFAULT_WINDOW: usize = 1 << 10;
+
+    pub(crate) fn new(window: usize) -> Self {
+        Self {
+            window,
+            hashes: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Records the hash of the generation that was just stepped from and looks the hash of the new
+    /// generation up among the recorded ones, returning how many generations ago it was seen.
+    pub(crate) fn record(
+        &mut self,
+        previous: u64,
+        current: u64,
+    ) -> Option<usize> {
+        if self.window == 0 {
+            return None;
+        }
+        if self.hashes.len() == self.window {
+            self.hashes.pop_front();
+        }
+        self.hashes.push_back(previous);
+
+        self.hashes.iter().rev().position(|&hash| hash == current).map(|age| age + 1)
+    }
+
+    /// Forgets every recorded generation, for when the board is changed by anything but a step.
+    pub(crate) fn clear(&mut self) {
+        self.hashes.clear();
+    }
+}
+
+impl std::default::Default for CycleDetector {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Blend,
+        Message,
+        Pattern,
+        Simulation,
+        State,
+    };
+
+    /// A 20 by 20 torus holding the catalog pattern `name`, or nothing, watching `window` generations
+    /// for a repeat.
+    fn simulation(
+        name: Option<&str>,
+        window: usize,
+    ) -> Simulation {
+        let mut simulation = Simulation::builder().width(20).height(20).cycle_window(window).build().unwrap();
+        if let Some(name) = name {
+            simulation.place_pattern(&Pattern::named(name).unwrap(), 2, 2, Blend::Or);
+        }
+        simulation
+    }
+
+    /// Steps `simulation` `generations` times and returns what it has settled into, if anything.
+    fn run(
+        simulation: &mut Simulation,
+        generations: usize,
+    ) -> Option<Stabilization> {
+        for _ in 0..generations {
+            simulation.update(Message::Step).unwrap();
+        }
+        simulation.stabilization()
+    }
+
+    #[test]
+    fn still_lifes_oscillators_and_empty_boards_are_recognized() {
+        let window = CycleDetector::DEFAULT_WINDOW;
+        assert_eq!(run(&mut simulation(Some("block"), window), 1), Some(Stabilization::Still));
+        assert_eq!(run(&mut simulation(Some("beehive"), window), 1), Some(Stabilization::Still));
+        assert_eq!(run(&mut simulation(None, window), 1), Some(Stabilization::Empty));
+
+        let mut blinker = simulation(Some("blinker"), window);
+        assert_eq!(run(&mut blinker, 1), None);
+        assert_eq!(run(&mut blinker, 1), Some(Stabilization::Periodic { period: 2 }));
+        assert_eq!(Stabilization::Periodic { period: 2 }.period(), 2);
+
+        let mut pulsar = simulation(Some("pulsar"), window);
+        assert_eq!(run(&mut pulsar, 3), Some(Stabilization::Periodic { period: 3 }));
+    }
+
+    #[test]
+    fn cycles_longer_than_the_window_go_unnoticed() {
+        assert_eq!(run(&mut simulation(Some("blinker"), 1), 20), None);
+        assert_eq!(run(&mut simulation(Some("pulsar"), 2), 30), None);
+        assert_eq!(run(&mut simulation(Some("pulsar"), 3), 30), Some(Stabilization::Periodic { period: 3 }));
+        assert_eq!(run(&mut simulation(Some("block"), 0), 5), None);
+
+        // A glider never repeats the same board within a window shorter than its trip around the torus.
+        assert_eq!(run(&mut simulation(Some("glider"), 40), 100), None);
+        assert_eq!(
+            run(&mut simulation(Some("glider"), 80), 100),
+            Some(Stabilization::Periodic { period: 80 })
+        );
+    }
+
+    #[test]
+    fn the_policy_decides_what_happens_on_stabilizing() {
+        for &(policy, state) in &[
+            (StabilizationPolicy::Continue, State::Running),
+            (StabilizationPolicy::Pause, State::Pausing),
+            (StabilizationPolicy::End, State::Ended),
+        ] {
+            let mut simulation = simulation(Some("block"), CycleDetector::DEFAULT_WINDOW);
+            simulation.set_on_stabilize(policy);
+            simulation.update(Message::Start).unwrap();
+            simulation.update(Message::Tick).unwrap();
+            assert_eq!(simulation.stabilization(), Some(Stabilization::Still));
+            assert_eq!(simulation.state(), state, "{}", policy);
+        }
+        assert_eq!(StabilizationPolicy::default(), StabilizationPolicy::Continue);
+    }
+}
@@ -0,0 +1,51 @@
+use crate::deps::serde;
+use std::fmt;
+
+/// The tiling the cells of a board stand for, which decides how they are drawn and which of them
+/// are neighbors. Cells are always stored in rows and columns, `(x, y)` being column `x` of row
+/// `y`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Grid {
+    /// Square cells.
+    #[default]
+    Square,
+    /// Hexagonal cells in axial coordinates, as in Golly: the neighbors of `(x, y)` are the cells
+    /// next to it in its row and column and the cells at `(x - 1, y - 1)` and `(x + 1, y + 1)`.
+    /// Drawn true to shape, each row sits half a cell to the left of the row above it, so that the
+    /// board is a parallelogram.
+    Hexagonal,
+    /// Triangular cells, with `(x, y)` pointing up when `x + y` is even and down when it is odd,
+    /// and each triangle sharing its sides with the cells left and right of it in its row and the
+    /// cell above or below it. Wrapping topologies need an even width and height for the triangles
+    /// to line up across the edges.
+    Triangular,
+}
+
+impl Grid {
+    pub const ALL: [Grid; 3] = [Grid::Square, Grid::Hexagonal, Grid::Triangular];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Grid::Square => "square",
+            Grid::Hexagonal => "hexagonal",
+            Grid::Triangular => "triangular",
+        }
+    }
+
+    /// Whether the triangle at `(x, y)` of a [`Grid::Triangular`] board points up.
+    pub fn points_up(
+        x: usize,
+        y: usize,
+    ) -> bool {
+        (x + y).is_multiple_of(2)
+    }
+}
+
+impl fmt::Display for Grid {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,1109 @@
+//! A [HashLife] engine that advances an unbounded plane by huge numbers of generations at once.
+//!
+//! [HashLife]: https://conwaylife.com/wiki/HashLife
+
+use crate::{
+    deps::tracing::info,
+    Bounds,
+    Control,
+    Error,
+    Pattern,
+    Rule,
+    State,
+    UniverseMessage,
+};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+};
+
+type NodeId = u32;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+/// Golly writes leaves as 8x8 blocks of text, so nodes below this level never appear in a file.
+const LEAF_LEVEL: u8 = 3;
+
+/// The largest root whose cell coordinates still fit in an `i64`.
+const MAX_LEVEL: u8 = 63;
+
+/// The largest power of two generations a single jump may take under [`MAX_LEVEL`].
+const MAX_JUMP: u8 = MAX_LEVEL - 3;
+
+const MACROCELL_FORMAT: &str = "macrocell";
+const MACROCELL_HEADER: &str = "[M2]";
+
+/// A canonical quadtree node. Level 0 nodes are the two single cells, and a level `k` node is a
+/// `2^k` square split into `nw`, `ne`, `sw` and `se` quadrants.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    level:      u8,
+    children:   [NodeId; 4],
+    population: u128,
+}
+
+/// An unbounded Life-like universe stored as a hash consed quadtree.
+///
+/// Identical subtrees are shared, and the result of advancing every node is memoized, so repetitive
+/// patterns can be jumped forward `2^n` generations in time roughly proportional to `n`. The root
+/// is always centered on the origin. Memoized nodes accumulate as the universe evolves and are
+/// compacted away once there are more than [`HashLife::node_limit`] of them.
+#[derive(Debug, Clone)]
+pub struct HashLife {
+    state:      State,
+    rule:       Rule,
+    generation: u64,
+    step_log2:  u8,
+    node_limit: usize,
+    comments:   Vec<String>,
+    root:       NodeId,
+    nodes:      Vec<Node>,
+    index:      HashMap<(u8, [NodeId; 4]), NodeId>,
+    results:    HashMap<(NodeId, u8), NodeId>,
+    empty:      Vec<NodeId>,
+}
+
+impl HashLife {
+    pub const DEFAULT_NODE_LIMIT: usize = 1 << 22;
+
+    /// An empty universe running `rule`.
+    ///
+    /// Rules with `B0` turn the whole plane on in one generation and cannot be represented, so
+    /// they are rejected, as are Generations rules whose dying cells and colored rules whose teams the
+    /// quadtree has no room for, and Larger than Life rules, since the leaves are stepped over the
+    /// Moore neighborhood alone.
+    pub fn new(rule: Rule) -> Result<Self, Error> {
+        Self::check_rule(rule)?;
+
+        let leaf = |population| {
+            Node {
+                level: 0,
+                children: [DEAD; 4],
+                population,
+            }
+        };
+        let mut universe = Self {
+            state: State::default(),
+            rule,
+            generation: 0,
+            step_log2: 0,
+            node_limit: Self::DEFAULT_NODE_LIMIT,
+            comments: Vec::new(),
+            root: DEAD,
+            nodes: vec![leaf(0), leaf(1)],
+            index: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+        };
+        universe.root = universe.empty(LEAF_LEVEL);
+
+        Ok(universe)
+    }
+
+    /// Places `pattern` with its top left cell on the origin, running the pattern's rule if it names
+    /// one and Conway's Life otherwise.
+    pub fn from_pattern(pattern: &Pattern) -> Result<Self, Error> {
+        let mut universe = Self::new(pattern.rule().unwrap_or_default())?;
+
+        let extent = pattern.width().max(pattern.height()).max(1);
+        let level = (usize::BITS - (extent - 1).leading_zeros()).max(LEAF_LEVEL as u32 - 1) as u8;
+        let limit = (pattern.width() as u64, pattern.height() as u64);
+        let quadrant = universe.build(level, limit, &mut |x, y| pattern.get(x as usize, y as usize));
+        let empty = universe.empty(level);
+        universe.root = universe.join([empty, empty, empty, quadrant]);
+
+        for comment in pattern.comments() {
+            universe.comments.push(comment.clone());
+        }
+
+        Ok(universe)
+    }
+
+    fn check_rule(rule: Rule) -> Result<(), Error> {
+        if rule.born(0) || rule.states() > 2 || rule.teams() > 1 || !rule.is_life_like() {
+            Err(Error::Unsupported {
+                engine:    "HashLife",
+                operation: format!("rule {}", rule),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// The number of generations the universe has been advanced since it was created or reset.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn population(&self) -> u128 {
+        self.node(self.root).population
+    }
+
+    /// [`Control::Step`] and [`Control::Tick`] advance the universe by `2^step_log2` generations.
+    pub fn step_log2(&self) -> u8 {
+        self.step_log2
+    }
+
+    pub fn set_step_log2(
+        &mut self,
+        step_log2: u8,
+    ) {
+        self.step_log2 = step_log2.min(63);
+    }
+
+    /// The number of canonical nodes above which memoized results are garbage collected.
+    pub fn node_limit(&self) -> usize {
+        self.node_limit
+    }
+
+    pub fn set_node_limit(
+        &mut self,
+        node_limit: usize,
+    ) {
+        self.node_limit = node_limit;
+    }
+
+    /// The number of canonical nodes currently held, live or memoized.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn comments(&self) -> &[String] {
+        &self.comments[..]
+    }
+
+    /// Changes the rule, discarding every memoized result computed under the old one.
+    pub fn set_rule(
+        &mut self,
+        rule: Rule,
+    ) -> Result<(), Error> {
+        Self::check_rule(rule)?;
+        if rule != self.rule {
+            self.rule = rule;
+            self.results.clear();
+        }
+        Ok(())
+    }
+
+    /// Kills every cell and rewinds the generation counter.
+    pub fn clear(&mut self) {
+        self.root = self.empty(LEAF_LEVEL);
+        self.generation = 0;
+        self.collect_garbage();
+    }
+
+    pub fn get(
+        &self,
+        x: i64,
+        y: i64,
+    ) -> bool {
+        let half = self.half_extent(self.root);
+        let (x, y) = (i128::from(x) + half, i128::from(y) + half);
+        let extent = 2 * half;
+        if x < 0 || y < 0 || x >= extent || y >= extent {
+            return false;
+        }
+        self.get_in(self.root, x as u64, y as u64)
+    }
+
+    /// Sets the cell at `(x, y)`, growing the universe to reach it. Cells further than `2^62` from
+    /// the origin are out of reach.
+    pub fn set(
+        &mut self,
+        x: i64,
+        y: i64,
+        alive: bool,
+    ) -> Result<(), Error> {
+        loop {
+            let half = self.half_extent(self.root);
+            let (rx, ry) = (i128::from(x) + half, i128::from(y) + half);
+            if rx >= 0 && ry >= 0 && rx < 2 * half && ry < 2 * half {
+                self.root = self.set_in(self.root, rx as u64, ry as u64, alive);
+                return Ok(());
+            }
+            if self.node(self.root).level >= MAX_LEVEL {
+                return Err(Error::UniverseOverflow);
+            }
+            self.root = self.expand(self.root);
+        }
+    }
+
+    /// The smallest rectangle holding every live cell, or `None` for an empty universe.
+    pub fn bounds(&self) -> Option<Bounds> {
+        let half = self.half_extent(self.root) as i64;
+        self.bounds_in(self.root, -half, -half)
+    }
+
+    /// The live cells inside [`HashLife::bounds`], along with the rule and comments. Cells spread too
+    /// far apart for a [`Pattern`] to hold fail with [`Error::PatternTooLarge`], while
+    /// [`HashLife::to_macrocell`] can write any universe.
+    pub fn to_pattern(&self) -> Result<Pattern, Error> {
+        let mut pattern = match self.bounds() {
+            Some(bounds) => {
+                let mut pattern = Pattern::for_bounds(bounds)?;
+                let half = self.half_extent(self.root) as i64;
+                self.for_each_alive(self.root, -half, -half, &mut |x, y| {
+                    pattern.set((x - bounds.min_x) as usize, (y - bounds.min_y) as usize, true);
+                });
+                pattern
+            }
+            None => Pattern::new(0, 0),
+        };
+        pattern.set_rule(Some(self.rule));
+        for comment in &self.comments {
+            pattern.push_comment(comment.clone());
+        }
+        Ok(pattern)
+    }
+
+    /// Advances the universe by exactly `generations`, one power of two jump per set bit.
+    pub fn advance(
+        &mut self,
+        generations: u64,
+    ) -> Result<(), Error> {
+        for log2 in 0..64u8 {
+            if generations & (1 << log2) == 0 {
+                continue;
+            }
+            if log2 <= MAX_JUMP {
+                self.jump(log2)?;
+            } else {
+                for _ in 0..1u64 << (log2 - MAX_JUMP) {
+                    self.jump(MAX_JUMP)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Advances the universe by `2^log2` generations.
+    fn jump(
+        &mut self,
+        log2: u8,
+    ) -> Result<(), Error> {
+        loop {
+            let root = self.node(self.root);
+            let inner = self.inner_population(self.root);
+            if root.level >= log2 + 3 && inner == root.population {
+                break;
+            }
+            if root.level >= MAX_LEVEL {
+                return Err(Error::UniverseOverflow);
+            }
+            self.root = self.expand(self.root);
+        }
+
+        self.root = self.step(self.root, log2);
+        self.generation = self.generation.wrapping_add(1 << log2);
+        self.shrink();
+
+        if self.nodes.len() > self.node_limit {
+            self.collect_garbage();
+        }
+
+        Ok(())
+    }
+
+    /// Drops every node that is not part of the current universe, along with all memoized results.
+    pub fn collect_garbage(&mut self) {
+        let old = std::mem::take(&mut self.nodes);
+        let before = old.len();
+
+        self.nodes = old[..2].to_vec();
+        self.index.clear();
+        self.results.clear();
+        self.empty.truncate(1);
+
+        let mut remap = HashMap::new();
+        self.root = self.copy_from(&old, &mut remap, self.root);
+        info!("HashLife garbage collected {} of {} nodes", before - self.nodes.len(), before);
+    }
+
+    fn copy_from(
+        &mut self,
+        old: &[Node],
+        remap: &mut HashMap<NodeId, NodeId>,
+        id: NodeId,
+    ) -> NodeId {
+        if id <= ALIVE {
+            return id;
+        }
+        if let Some(&new) = remap.get(&id) {
+            return new;
+        }
+
+        let [nw, ne, sw, se] = old[id as usize].children;
+        let children = [
+            self.copy_from(old, remap, nw),
+            self.copy_from(old, remap, ne),
+            self.copy_from(old, remap, sw),
+            self.copy_from(old, remap, se),
+        ];
+        let new = self.join(children);
+        remap.insert(id, new);
+        new
+    }
+
+    /// Drives the universe with a [`UniverseMessage`].
+    pub fn update(
+        &mut self,
+        msg: UniverseMessage,
+    ) -> Result<(), Error> {
+        use State::*;
+        if self.state.ended() {
+            return Err(Error::GameOver);
+        }
+
+        match msg {
+            UniverseMessage::Control(Control::Start) => {
+                self.state.try_transition(Running)?;
+                info!("Start");
+            }
+            UniverseMessage::Control(Control::Step) => {
+                self.advance(1 << self.step_log2)?;
+            }
+            UniverseMessage::Control(Control::Reset) => {
+                self.clear();
+                info!("Reset");
+            }
+            UniverseMessage::Control(Control::Stop) => {
+                self.state.try_transition(Pausing)?;
+                info!("Stop");
+            }
+            UniverseMessage::SetRule(rule) => {
+                self.set_rule(rule)?;
+                info!("SetRule {}", rule);
+            }
+            UniverseMessage::Control(Control::Tick) => {
+                if self.state.run() {
+                    self.advance(1 << self.step_log2)?;
+                }
+            }
+            UniverseMessage::Control(Control::End) => {
+                self.state.try_transition(Ended)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn node(
+        &self,
+        id: NodeId,
+    ) -> Node {
+        self.nodes[id as usize]
+    }
+
+    fn children(
+        &self,
+        id: NodeId,
+    ) -> [NodeId; 4] {
+        self.nodes[id as usize].children
+    }
+
+    /// Half the side length of `id`, which is also the offset of the origin within the root.
+    fn half_extent(
+        &self,
+        id: NodeId,
+    ) -> i128 {
+        1i128 << (self.node(id).level - 1)
+    }
+
+    /// Returns the canonical node with the given quadrants, creating it if needed.
+    fn join(
+        &mut self,
+        children: [NodeId; 4],
+    ) -> NodeId {
+        let level = self.node(children[0]).level + 1;
+        if let Some(&id) = self.index.get(&(level, children)) {
+            return id;
+        }
+
+        let population = children.iter().map(|&child| self.node(child).population).sum();
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(Node {
+            level,
+            children,
+            population,
+        });
+        self.index.insert((level, children), id);
+        id
+    }
+
+    fn empty(
+        &mut self,
+        level: u8,
+    ) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let below = self.empty[self.empty.len() - 1];
+            let next = self.join([below; 4]);
+            self.empty.push(next);
+        }
+        self.empty[level as usize]
+    }
+
+    /// Wraps `id` in a node twice its size, keeping its center where it was.
+    fn expand(
+        &mut self,
+        id: NodeId,
+    ) -> NodeId {
+        let [nw, ne, sw, se] = self.children(id);
+        let empty = self.empty(self.node(id).level - 1);
+        let children = [
+            self.join([empty, empty, empty, nw]),
+            self.join([empty, empty, ne, empty]),
+            self.join([empty, sw, empty, empty]),
+            self.join([se, empty, empty, empty]),
+        ];
+        self.join(children)
+    }
+
+    /// Replaces the root with its center quadrant for as long as that loses no live cells.
+    fn shrink(&mut self) {
+        while self.node(self.root).level > LEAF_LEVEL {
+            let [nw, ne, sw, se] = self.children(self.root);
+            let quadrants = [self.children(nw)[3], self.children(ne)[2], self.children(sw)[1], self.children(se)[0]];
+            let population: u128 = quadrants.iter().map(|&id| self.node(id).population).sum();
+            if population != self.population() {
+                break;
+            }
+            self.root = self.join(quadrants);
+        }
+    }
+
+    /// The population of the centered square a quarter the side length of `id`.
+    fn inner_population(
+        &self,
+        id: NodeId,
+    ) -> u128 {
+        if self.node(id).level < 3 {
+            return 0;
+        }
+        let [nw, ne, sw, se] = self.children(id);
+        [
+            self.children(self.children(nw)[3])[3],
+            self.children(self.children(ne)[2])[2],
+            self.children(self.children(sw)[1])[1],
+            self.children(self.children(se)[0])[0],
+        ]
+        .iter()
+        .map(|&id| self.node(id).population)
+        .sum()
+    }
+
+    fn center(
+        &mut self,
+        id: NodeId,
+    ) -> NodeId {
+        let [nw, ne, sw, se] = self.children(id);
+        self.join([self.children(nw)[3], self.children(ne)[2], self.children(sw)[1], self.children(se)[0]])
+    }
+
+    /// The node straddling the border between horizontally adjacent `west` and `east`.
+    fn horizontal(
+        &mut self,
+        west: NodeId,
+        east: NodeId,
+    ) -> NodeId {
+        let [_, w_ne, _, w_se] = self.children(west);
+        let [e_nw, _, e_sw, _] = self.children(east);
+        self.join([w_ne, e_nw, w_se, e_sw])
+    }
+
+    /// The node straddling the border between vertically adjacent `north` and `south`.
+    fn vertical(
+        &mut self,
+        north: NodeId,
+        south: NodeId,
+    ) -> NodeId {
+        let [_, _, n_sw, n_se] = self.children(north);
+        let [s_nw, s_ne, _, _] = self.children(south);
+        self.join([n_sw, n_se, s_nw, s_ne])
+    }
+
+    /// The center half of level `k` node `id` advanced by `2^log2` generations, where
+    /// `log2 <= k - 2`.
+    fn step(
+        &mut self,
+        id: NodeId,
+        log2: u8,
+    ) -> NodeId {
+        let node = self.node(id);
+        debug_assert!(node.level >= 2 && log2 + 2 <= node.level);
+
+        if node.population == 0 {
+            return self.empty(node.level - 1);
+        }
+        if let Some(&result) = self.results.get(&(id, log2)) {
+            return result;
+        }
+
+        let result = if node.level == 2 {
+            self.step_base(id)
+        } else {
+            let [nw, ne, sw, se] = node.children;
+            let n01 = self.horizontal(nw, ne);
+            let n10 = self.vertical(nw, sw);
+            let n11 = self.center(id);
+            let n12 = self.vertical(ne, se);
+            let n21 = self.horizontal(sw, se);
+            let nine = [nw, n01, ne, n10, n11, n12, sw, n21, se];
+
+            let full_speed = log2 + 2 == node.level;
+            let mut inner = [DEAD; 9];
+            for (inner, &n) in inner.iter_mut().zip(nine.iter()) {
+                *inner = if full_speed {
+                    self.step(n, log2 - 1)
+                } else {
+                    self.center(n)
+                };
+            }
+
+            let next = if full_speed {
+                log2 - 1
+            } else {
+                log2
+            };
+            let mut quadrants = [DEAD; 4];
+            for (quadrant, &[a, b, c, d]) in quadrants.iter_mut().zip(
+                [[0, 1, 3, 4], [1, 2, 4, 5], [3, 4, 6, 7], [4, 5, 7, 8]].iter(),
+            ) {
+                let joined = self.join([inner[a], inner[b], inner[c], inner[d]]);
+                *quadrant = self.step(joined, next);
+            }
+            self.join(quadrants)
+        };
+
+        self.results.insert((id, log2), result);
+        result
+    }
+
+    /// Advances the center 2x2 of a 4x4 node by one generation by counting neighbors directly.
+    fn step_base(
+        &mut self,
+        id: NodeId,
+    ) -> NodeId {
+        let mut cells = [[false; 4]; 4];
+        for (y, row) in cells.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                *cell = self.get_in(id, x as u64, y as u64);
+            }
+        }
+
+        let mut next = [DEAD; 4];
+        for (idx, next) in next.iter_mut().enumerate() {
+            let (x, y) = (1 + idx % 2, 1 + idx / 2);
+            let neighbors = (y - 1..=y + 1)
+                .flat_map(|ny| (x - 1..=x + 1).map(move |nx| (nx, ny)))
+                .filter(|&(nx, ny)| (nx, ny) != (x, y) && cells[ny][nx])
+                .count();
+            if self.rule.next(cells[y][x], neighbors) {
+                *next = ALIVE;
+            }
+        }
+        self.join(next)
+    }
+
+    /// Builds a level `level` node whose cell at `(x, y)`, relative to its top left, is `alive(x, y)`.
+    /// Cells at or past `limit` are dead without asking.
+    fn build<F: FnMut(u64, u64) -> bool>(
+        &mut self,
+        level: u8,
+        limit: (u64, u64),
+        alive: &mut F,
+    ) -> NodeId {
+        self.build_at(level, 0, 0, limit, alive)
+    }
+
+    fn build_at<F: FnMut(u64, u64) -> bool>(
+        &mut self,
+        level: u8,
+        x: u64,
+        y: u64,
+        limit: (u64, u64),
+        alive: &mut F,
+    ) -> NodeId {
+        if x >= limit.0 || y >= limit.1 {
+            return self.empty(level);
+        }
+        if level == 0 {
+            return if alive(x, y) {
+                ALIVE
+            } else {
+                DEAD
+            };
+        }
+        let half = 1 << (level - 1);
+        let children = [
+            self.build_at(level - 1, x, y, limit, alive),
+            self.build_at(level - 1, x + half, y, limit, alive),
+            self.build_at(level - 1, x, y + half, limit, alive),
+            self.build_at(level - 1, x + half, y + half, limit, alive),
+        ];
+        self.join(children)
+    }
+
+    fn quadrant(
+        level: u8,
+        x: u64,
+        y: u64,
+    ) -> (usize, u64, u64) {
+        let half = 1 << (level - 1);
+        let idx = (y >= half) as usize * 2 + (x >= half) as usize;
+        (idx, x % half, y % half)
+    }
+
+    fn get_in(
+        &self,
+        id: NodeId,
+        x: u64,
+        y: u64,
+    ) -> bool {
+        let node = self.node(id);
+        if node.population == 0 {
+            return false;
+        }
+        if node.level == 0 {
+            return id == ALIVE;
+        }
+        let (idx, x, y) = Self::quadrant(node.level, x, y);
+        self.get_in(node.children[idx], x, y)
+    }
+
+    fn set_in(
+        &mut self,
+        id: NodeId,
+        x: u64,
+        y: u64,
+        alive: bool,
+    ) -> NodeId {
+        let node = self.node(id);
+        if node.level == 0 {
+            return if alive {
+                ALIVE
+            } else {
+                DEAD
+            };
+        }
+        let (idx, cx, cy) = Self::quadrant(node.level, x, y);
+        let mut children = node.children;
+        children[idx] = self.set_in(children[idx], cx, cy, alive);
+        self.join(children)
+    }
+
+    fn bounds_in(
+        &self,
+        id: NodeId,
+        x: i64,
+        y: i64,
+    ) -> Option<Bounds> {
+        let node = self.node(id);
+        if node.population == 0 {
+            return None;
+        }
+        if node.level == 0 {
+            return Some(Bounds::new(x, y, x, y));
+        }
+        let half = 1i64 << (node.level - 1);
+        node.children
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, &child)| self.bounds_in(child, x + half * (idx % 2) as i64, y + half * (idx / 2) as i64))
+            .fold(None, |acc: Option<Bounds>, bounds| Some(acc.map_or(bounds, |acc| acc.union(&bounds))))
+    }
+
+    fn for_each_alive<F: FnMut(i64, i64)>(
+        &self,
+        id: NodeId,
+        x: i64,
+        y: i64,
+        f: &mut F,
+    ) {
+        let node = self.node(id);
+        if node.population == 0 {
+            return;
+        }
+        if node.level == 0 {
+            f(x, y);
+            return;
+        }
+        let half = 1i64 << (node.level - 1);
+        for (idx, &child) in node.children.iter().enumerate() {
+            self.for_each_alive(child, x + half * (idx % 2) as i64, y + half * (idx / 2) as i64, f);
+        }
+    }
+}
+
+impl HashLife {
+    /// Parses a Golly [macrocell] file.
+    ///
+    /// The root node is centered on the origin, as Golly places it. `#R` sets the rule, `#G` the
+    /// generation counter, and any other `#` lines become [`HashLife::comments`].
+    ///
+    /// [macrocell]: https://conwaylife.com/wiki/Macrocell
+    pub fn from_macrocell(macrocell: &str) -> Result<Self, Error> {
+        let parse_error = |line: usize, column: usize, reason: String| {
+            Error::Parse {
+                format: MACROCELL_FORMAT,
+                line,
+                column,
+                reason,
+            }
+        };
+
+        let mut lines = macrocell.lines().enumerate();
+        match lines.next() {
+            Some((_, header)) if header.starts_with(MACROCELL_HEADER) => {}
+            _ => return Err(parse_error(1, 1, format!("expected a `{}` header", MACROCELL_HEADER))),
+        }
+
+        let mut universe = Self::new(Rule::default())?;
+        let mut generation = 0;
+        // Node 0 in a macrocell file is the empty node of whatever level its parent needs.
+        let mut ids: Vec<Option<NodeId>> = vec![None];
+
+        for (idx, line) in lines {
+            let lineno = idx + 1;
+            let line = line.trim_end();
+
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some(rule) = comment.strip_prefix('R') {
+                    let rule = rule.trim().parse().map_err(|err: Error| parse_error(lineno, 3, err.to_string()))?;
+                    universe.set_rule(rule).map_err(|err| parse_error(lineno, 3, err.to_string()))?;
+                } else if let Some(value) = comment.strip_prefix('G') {
+                    generation = value
+                        .trim()
+                        .parse()
+                        .map_err(|_| parse_error(lineno, 3, format!("invalid generation {:?}", value.trim())))?;
+                } else {
+                    universe.comments.push(comment.to_string());
+                }
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+
+            let id = if line.starts_with(|ch: char| ch.is_ascii_digit()) {
+                universe.parse_macrocell_branch(line, lineno, &ids)?
+            } else {
+                universe.parse_macrocell_leaf(line, lineno)?
+            };
+            ids.push(Some(id));
+        }
+
+        if let Some(&Some(root)) = ids.last() {
+            universe.root = root;
+        }
+        while universe.node(universe.root).level < LEAF_LEVEL {
+            universe.root = universe.expand(universe.root);
+        }
+        universe.shrink();
+        universe.generation = generation;
+
+        Ok(universe)
+    }
+
+    /// Parses a `level nw ne sw se` node line.
+    fn parse_macrocell_branch(
+        &mut self,
+        line: &str,
+        lineno: usize,
+        ids: &[Option<NodeId>],
+    ) -> Result<NodeId, Error> {
+        let fields: Vec<_> = line
+            .split_whitespace()
+            .map(|field| {
+                let offset = field.as_ptr() as usize - line.as_ptr() as usize;
+                (field, offset + 1)
+            })
+            .collect();
+        let parse_error = |column: usize, reason: String| {
+            Error::Parse {
+                format: MACROCELL_FORMAT,
+                line: lineno,
+                column,
+                reason,
+            }
+        };
+
+        if fields.len() != 5 {
+            return Err(parse_error(1, format!("expected 5 fields, found {}", fields.len())));
+        }
+        let mut numbers = [0usize; 5];
+        for (number, &(field, column)) in numbers.iter_mut().zip(fields.iter()) {
+            *number = field
+                .parse()
+                .map_err(|_| parse_error(column, format!("invalid number {:?}", field)))?;
+        }
+
+        let level = numbers[0];
+        if level <= LEAF_LEVEL as usize || level > MAX_LEVEL as usize {
+            return Err(parse_error(
+                fields[0].1,
+                format!("node level must be in {}..={}", LEAF_LEVEL + 1, MAX_LEVEL),
+            ));
+        }
+        let level = level as u8;
+
+        let mut children = [DEAD; 4];
+        for ((child, &number), &(_, column)) in children.iter_mut().zip(numbers[1..].iter()).zip(fields[1..].iter()) {
+            *child = match ids.get(number) {
+                Some(Some(id)) if self.node(*id).level + 1 == level => *id,
+                Some(None) => self.empty(level - 1),
+                Some(Some(_)) => return Err(parse_error(column, format!("node {} has the wrong level", number))),
+                None => return Err(parse_error(column, format!("node {} is not defined yet", number))),
+            };
+        }
+
+        Ok(self.join(children))
+    }
+
+    /// Parses an 8x8 leaf written as rows of `.` and `*` separated by `$`.
+    fn parse_macrocell_leaf(
+        &mut self,
+        line: &str,
+        lineno: usize,
+    ) -> Result<NodeId, Error> {
+        let side = 1usize << LEAF_LEVEL;
+        let mut cells = vec![false; side * side];
+        let (mut x, mut y) = (0, 0);
+
+        for (col, ch) in line.chars().enumerate() {
+            match ch {
+                '.' | '*' if x < side && y < side => {
+                    cells[y * side + x] = ch == '*';
+                    x += 1;
+                }
+                '$' if y < side => {
+                    y += 1;
+                    x = 0;
+                }
+                '.' | '*' | '$' => {
+                    return Err(Error::Parse {
+                        format: MACROCELL_FORMAT,
+                        line:   lineno,
+                        column: col + 1,
+                        reason: format!("leaf node exceeds {}x{} cells", side, side),
+                    })
+                }
+                ch => {
+                    return Err(Error::Parse {
+                        format: MACROCELL_FORMAT,
+                        line:   lineno,
+                        column: col + 1,
+                        reason: format!("unexpected character {:?}", ch),
+                    })
+                }
+            }
+        }
+
+        let limit = (side as u64, side as u64);
+        Ok(self.build(LEAF_LEVEL, limit, &mut |x, y| cells[y as usize * side + x as usize]))
+    }
+
+    /// Encodes the universe as a Golly macrocell file.
+    pub fn to_macrocell(&self) -> String {
+        let mut writer = MacrocellWriter {
+            universe: self,
+            lines:    Vec::new(),
+            numbers:  HashMap::new(),
+        };
+
+        let mut macrocell = String::new();
+        let _ = writeln!(macrocell, "{} (locutus)", MACROCELL_HEADER);
+        let _ = writeln!(macrocell, "#R {}", self.rule);
+        if self.generation != 0 {
+            let _ = writeln!(macrocell, "#G {}", self.generation);
+        }
+        for comment in &self.comments {
+            let _ = writeln!(macrocell, "#{}", comment);
+        }
+
+        if writer.write(self.root) == 0 {
+            writer.lines.push("$".to_string());
+        }
+        for line in writer.lines {
+            macrocell.push_str(&line);
+            macrocell.push('\n');
+        }
+        macrocell
+    }
+}
+
+/// Numbers nodes in the order they are first written, so children always precede their parents.
+struct MacrocellWriter<'a> {
+    universe: &'a HashLife,
+    lines:    Vec<String>,
+    numbers:  HashMap<NodeId, usize>,
+}
+
+impl<'a> MacrocellWriter<'a> {
+    fn write(
+        &mut self,
+        id: NodeId,
+    ) -> usize {
+        let node = self.universe.node(id);
+        if node.population == 0 {
+            return 0;
+        }
+        if let Some(&number) = self.numbers.get(&id) {
+            return number;
+        }
+
+        let line = if node.level == LEAF_LEVEL {
+            let side = 1u64 << LEAF_LEVEL;
+            let mut line = String::new();
+            for y in 0..side {
+                let row: String = (0..side)
+                    .map(|x| {
+                        if self.universe.get_in(id, x, y) {
+                            '*'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect();
+                line.push_str(row.trim_end_matches('.'));
+                line.push('$');
+            }
+            line
+        } else {
+            let [nw, ne, sw, se] = node.children;
+            let children = [self.write(nw), self.write(ne), self.write(sw), self.write(se)];
+            format!(
+                "{} {} {} {} {}",
+                node.level, children[0], children[1], children[2], children[3]
+            )
+        };
+
+        self.lines.push(line);
+        let number = self.lines.len();
+        self.numbers.insert(id, number);
+        number
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Blend,
+        Message,
+        Simulation,
+        Topology,
+    };
+
+    /// Margin around the pattern on the bounded board, wide enough that nothing reaches the edge.
+    const MARGIN: usize = 120;
+
+    #[test]
+    fn advance_matches_simulation_on_a_plane() {
+        for &(name, rule) in &[("r-pentomino", "B3/S23"), ("acorn", "B3/S23"), ("glider", "B36/S23")] {
+            let mut pattern = Pattern::named(name).unwrap();
+            pattern.set_rule(Some(rule.parse().unwrap()));
+
+            let mut universe = HashLife::from_pattern(&pattern).unwrap();
+            let mut simulation = Simulation::builder()
+                .width(pattern.width() + 2 * MARGIN)
+                .height(pattern.height() + 2 * MARGIN)
+                .rule(pattern.rule().unwrap())
+                .topology(Topology::Plane)
+                .cycle_window(0)
+                .build()
+                .unwrap();
+            simulation.place_pattern(&pattern, MARGIN as isize, MARGIN as isize, Blend::Or);
+
+            for &generations in &[1, 2, 5, 8, 13, 35, 64] {
+                universe.advance(generations).unwrap();
+                for _ in 0..generations {
+                    simulation.update(Message::Step).unwrap();
+                }
+                assert_eq!(
+                    alive_cells(&universe),
+                    simulation_cells(&simulation),
+                    "{} under {} at generation {}",
+                    name,
+                    rule,
+                    universe.generation()
+                );
+                assert_eq!(universe.population(), simulation.population() as u128);
+            }
+        }
+    }
+
+    #[test]
+    fn macrocell_round_trips() {
+        let mut universe = HashLife::from_pattern(&Pattern::named("gosper-glider-gun").unwrap()).unwrap();
+        universe.comments.push("N Gosper glider gun".to_string());
+        universe.advance(1000).unwrap();
+
+        let macrocell = universe.to_macrocell();
+        let read = HashLife::from_macrocell(&macrocell).unwrap();
+        assert_eq!(read.rule(), universe.rule());
+        assert_eq!(read.generation(), 1000);
+        assert_eq!(read.population(), universe.population());
+        assert_eq!(read.comments(), universe.comments());
+        assert_eq!(alive_cells(&read), alive_cells(&universe));
+        assert_eq!(read.to_macrocell(), macrocell);
+    }
+
+    #[test]
+    fn reads_golly_macrocell_files() {
+        let macrocell = "[M2] (golly 4.2)\n#R B3/S23\n#G 4\n$..*$...*$.***$\n4 1 0 0 0\n";
+        let universe = HashLife::from_macrocell(macrocell).unwrap();
+        assert_eq!(universe.generation(), 4);
+        assert_eq!(alive_cells(&universe), vec![(-6, -7), (-5, -6), (-7, -5), (-6, -5), (-5, -5)]);
+
+        let empty = HashLife::from_macrocell("[M2] (golly 4.2)\n#R B3/S23\n").unwrap();
+        assert_eq!(empty.population(), 0);
+        assert_eq!(HashLife::from_macrocell(&empty.to_macrocell()).unwrap().population(), 0);
+    }
+
+    #[test]
+    fn far_apart_cells_do_not_fit_a_pattern() {
+        let mut universe = HashLife::new(Rule::default()).unwrap();
+        universe.set(-3, 0, true).unwrap();
+        universe.set(1 << 40, 1 << 40, true).unwrap();
+        assert!(matches!(universe.to_pattern(), Err(Error::PatternTooLarge { .. })));
+        assert_eq!(HashLife::from_macrocell(&universe.to_macrocell()).unwrap().population(), 2);
+
+        universe.set(1 << 40, 1 << 40, false).unwrap();
+        assert_eq!(universe.to_pattern().unwrap().alive_cells().collect::<Vec<_>>(), vec![(0, 0)]);
+    }
+
+    /// The live cells of the universe in row major order.
+    fn alive_cells(universe: &HashLife) -> Vec<(i64, i64)> {
+        let bounds = match universe.bounds() {
+            Some(bounds) => bounds,
+            None => return Vec::new(),
+        };
+        let mut cells = Vec::new();
+        for y in bounds.min_y..=bounds.max_y {
+            for x in bounds.min_x..=bounds.max_x {
+                if universe.get(x, y) {
+                    cells.push((x, y));
+                }
+            }
+        }
+        cells
+    }
+
+    /// The live cells of the board in row major order, moved back to where the pattern was placed.
+    fn simulation_cells(simulation: &Simulation) -> Vec<(i64, i64)> {
+        simulation
+            .to_pattern()
+            .alive_cells()
+            .map(|(x, y)| (x as i64 - MARGIN as i64, y as i64 - MARGIN as i64))
+            .collect()
+    }
+}
@@ -0,0 +1,575 @@
+//! The record of steps and edits behind [`Message::StepBack`], [`Message::Seek`] and
+//! [`Message::Undo`].
+//!
+//! Rather than a copy of every generation, the history holds the sequence of events that were applied
+//! to the board along with a full copy of the board, a keyframe, every so many steps. Any recorded
+//! position is rebuilt by loading the closest keyframe before it and replaying the events in between.
+//!
+//! [`Message::StepBack`]: crate::Message::StepBack
+//! [`Message::Seek`]: crate::Message::Seek
+//! [`Message::Undo`]: crate::Message::Undo
+
+use crate::{
+    bitgrid::BitGrid,
+    Error,
+    Rule,
+    Simulation,
+    Topology,
+};
+use std::collections::VecDeque;
+
+/// A change to a [`Simulation`] that can be recorded and replayed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Event {
+    Step,
+    Toggle { x: usize, y: usize, team: usize },
+    Set { x: usize, y: usize, state: usize },
+    Clear,
+    SetRule(Rule),
+    SetTopology(Topology),
+    /// The board was overwritten by something that cannot be replayed, such as a random fill. It is
+    /// always followed by a keyframe, so replays never need to go through it.
+    Replace,
+}
+
+/// Everything needed to continue a simulation from a position in its history.
+#[derive(Debug, Clone)]
+struct Keyframe {
+    position: usize,
+    ticks:    usize,
+    rule:     Rule,
+    topology: Topology,
+    cellules: BitGrid,
+}
+
+/// The events applied to a simulation since its oldest keyframe.
+///
+/// Positions count events from the start of the simulation: position `p` is the state after `p`
+/// events. The cursor is the position the board is currently at, which can be behind the last
+/// recorded event after going back in time. Stepping from there follows the recorded events for as
+/// long as they are steps, while any other change discards the events ahead of the cursor.
+#[derive(Debug, Clone)]
+pub(crate) struct History {
+    interval:     usize,
+    memory_limit: usize,
+    seek_limit:   usize,
+    base:         usize,
+    cursor:       usize,
+    events:       VecDeque<Event>,
+    keyframes:    VecDeque<Keyframe>,
+}
+
+impl History {
+    pub(crate) const DEFAULT_KEYFRAME_INTERVAL: usize = 32;
+    pub(crate) const DEFAULT_MEMORY_LIMIT: usize = 16 << 20;
+    pub(crate) const DEFAULT_SEEK_LIMIT: usize = 1 << 16;
+
+    pub(crate) fn new(
+        interval: usize,
+        memory_limit: usize,
+        seek_limit: usize,
+    ) -> Self {
+        Self {
+            interval: interval.max(1),
+            memory_limit,
+            seek_limit,
+            base: 0,
+            cursor: 0,
+            events: VecDeque::new(),
+            keyframes: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn interval(&self) -> usize {
+        self.interval
+    }
+
+    pub(crate) fn memory_limit(&self) -> usize {
+        self.memory_limit
+    }
+
+    pub(crate) fn seek_limit(&self) -> usize {
+        self.seek_limit
+    }
+
+    fn enabled(&self) -> bool {
+        self.memory_limit > 0
+    }
+
+    /// The bytes taken by the keyframes and events, which is nearly all of the history.
+    pub(crate) fn memory(&self) -> usize {
+        let keyframes: usize = self.keyframes.iter().map(|keyframe| keyframe.cellules.memory()).sum();
+        keyframes + self.events.len() * std::mem::size_of::<Event>()
+    }
+
+    fn event(
+        &self,
+        position: usize,
+    ) -> Event {
+        self.events[position - self.base]
+    }
+
+    /// Appends `event` at the cursor, unless it is the step that was already recorded there.
+    fn push(
+        &mut self,
+        event: Event,
+    ) {
+        if event == Event::Step && self.cursor < self.end() && self.event(self.cursor) == Event::Step {
+            self.cursor += 1;
+            return;
+        }
+        self.truncate(self.cursor);
+        self.events.push_back(event);
+        self.cursor += 1;
+    }
+
+    fn end(&self) -> usize {
+        self.base + self.events.len()
+    }
+
+    /// Forgets every event from `position` on, along with the keyframes that come after it.
+    fn truncate(
+        &mut self,
+        position: usize,
+    ) {
+        self.events.truncate(position - self.base);
+        self.keyframes.retain(|keyframe| keyframe.position <= position);
+    }
+
+    /// The latest keyframe at or before `position`.
+    fn keyframe_before(
+        &self,
+        position: usize,
+    ) -> &Keyframe {
+        self.keyframes
+            .iter()
+            .rev()
+            .find(|keyframe| keyframe.position <= position)
+            .expect("the oldest keyframe is at the start of the history")
+    }
+
+    /// Whether the board at the cursor should be kept as a keyframe: right after it was replaced or
+    /// once enough steps have gone by since the last one.
+    fn keyframe_due(&self) -> bool {
+        let since = self.keyframe_before(self.cursor).position;
+        if since == self.cursor {
+            return false;
+        }
+        self.event(self.cursor - 1) == Event::Replace
+            || (since..self.cursor).filter(|&position| self.event(position) == Event::Step).count() >= self.interval
+    }
+
+    fn push_keyframe(
+        &mut self,
+        keyframe: Keyframe,
+    ) {
+        let idx = self
+            .keyframes
+            .iter()
+            .position(|other| other.position > keyframe.position)
+            .unwrap_or(self.keyframes.len());
+        self.keyframes.insert(idx, keyframe);
+        self.trim();
+    }
+
+    /// Drops the oldest keyframes, and the events before the new oldest one, until the history fits
+    /// in its memory limit. The keyframe the cursor is replayed from is always kept.
+    fn trim(&mut self) {
+        while self.memory() > self.memory_limit
+            && self.keyframes.len() > 1
+            && self.keyframes[1].position <= self.cursor
+        {
+            self.keyframes.pop_front();
+            let oldest = self.keyframes[0].position;
+            self.events.drain(..oldest - self.base);
+            self.base = oldest;
+        }
+    }
+
+    /// The position just before the last step behind the cursor.
+    fn step_back_target(&self) -> Option<usize> {
+        (self.base..self.cursor).rev().find(|&position| self.event(position) == Event::Step)
+    }
+
+    /// The position just before the last edit behind the cursor.
+    fn undo_target(&self) -> Option<usize> {
+        (self.base..self.cursor).rev().find(|&position| self.event(position) != Event::Step)
+    }
+
+    /// The last position at `generation`, after any edits made to it, or the end of the history when
+    /// it stops short of `generation`. `None` when `generation` is older than the history.
+    fn seek_target(
+        &self,
+        generation: usize,
+    ) -> Option<usize> {
+        let mut ticks = self.keyframes.front()?.ticks;
+        if generation < ticks {
+            return None;
+        }
+        for position in self.base..self.end() {
+            if self.event(position) == Event::Step {
+                if ticks == generation {
+                    return Some(position);
+                }
+                ticks += 1;
+            }
+        }
+        Some(self.end())
+    }
+
+    /// The generation at the end of the recorded history, or `None` when nothing is recorded.
+    fn end_ticks(&self) -> Option<usize> {
+        let steps = (self.base..self.end()).filter(|&position| self.event(position) == Event::Step).count();
+        Some(self.keyframes.front()?.ticks + steps)
+    }
+}
+
+impl std::default::Default for History {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_KEYFRAME_INTERVAL, Self::DEFAULT_MEMORY_LIMIT, Self::DEFAULT_SEEK_LIMIT)
+    }
+}
+
+impl Simulation {
+    /// Records `event` in the history, applies it to the board and takes a keyframe if one is due.
+    /// Returns the births and deaths of a step.
+    pub(crate) fn perform(
+        &mut self,
+        event: Event,
+    ) -> (usize, usize) {
+        self.begin_event(event);
+        let changes = self.apply(event);
+        self.end_event();
+        changes
+    }
+
+    /// Records an [`Event::Replace`] while `replace` overwrites the board.
+    pub(crate) fn replace_cellules<F: FnOnce(&mut BitGrid)>(
+        &mut self,
+        replace: F,
+    ) {
+        self.begin_event(Event::Replace);
+        replace(&mut self.cellules);
+        self.end_event();
+    }
+
+    fn begin_event(
+        &mut self,
+        event: Event,
+    ) {
+        if !self.history.enabled() {
+            return;
+        }
+        if self.history.keyframes.is_empty() {
+            let keyframe = self.keyframe(self.history.cursor);
+            self.history.keyframes.push_back(keyframe);
+        }
+        self.history.push(event);
+    }
+
+    fn end_event(&mut self) {
+        if self.history.enabled() && self.history.keyframe_due() {
+            let keyframe = self.keyframe(self.history.cursor);
+            self.history.push_keyframe(keyframe);
+        }
+    }
+
+    fn keyframe(
+        &self,
+        position: usize,
+    ) -> Keyframe {
+        Keyframe {
+            position,
+            ticks: self.ticks,
+            rule: self.rule,
+            topology: self.topology,
+            cellules: self.cellules.clone(),
+        }
+    }
+
+    /// Applies `event` without recording it, returning the births and deaths of a step.
+    fn apply(
+        &mut self,
+        event: Event,
+    ) -> (usize, usize) {
+        match event {
+            Event::Step => {
+                self.ticks += 1;
+                return self.cellules.step(&self.rule, self.topology);
+            }
+            Event::Toggle { x, y, team } => {
+                self.cellules.toggle(x, y);
+                if self.cellules.get(x, y) {
+                    self.cellules.set_team(x, y, team);
+                }
+            }
+            Event::Set { x, y, state } => self.cellules.set_state(x, y, state),
+            Event::Clear => self.cellules.clear(),
+            Event::SetRule(rule) => {
+                if rule.states() != self.rule.states() {
+                    self.cellules.set_states(rule.states());
+                }
+                if rule.teams() != self.rule.teams() {
+                    self.cellules.set_teams(rule.teams());
+                }
+                self.rule = rule;
+                self.grid = rule.grid();
+            }
+            Event::SetTopology(topology) => self.topology = topology,
+            Event::Replace => {}
+        }
+        (0, 0)
+    }
+
+    /// Rebuilds the board at `position`, replaying from the current board when it lies on the way
+    /// there and from the closest keyframe otherwise.
+    fn restore(
+        &mut self,
+        position: usize,
+    ) {
+        let keyframe = self.history.keyframe_before(position);
+        let from = if keyframe.position <= self.history.cursor && self.history.cursor <= position {
+            self.history.cursor
+        } else {
+            self.ticks = keyframe.ticks;
+            self.rule = keyframe.rule;
+            self.grid = keyframe.rule.grid();
+            self.topology = keyframe.topology;
+            self.cellules = keyframe.cellules.clone();
+            keyframe.position
+        };
+
+        let events: Vec<_> = (from..position).map(|position| self.history.event(position)).collect();
+        for event in events {
+            self.apply(event);
+        }
+        self.history.cursor = position;
+        self.board_edited();
+    }
+
+    /// How many steps apart keyframes are taken.
+    pub fn keyframe_interval(&self) -> usize {
+        self.history.interval()
+    }
+
+    /// The most memory, in bytes, the history may take before its oldest part is dropped.
+    pub fn history_limit(&self) -> usize {
+        self.history.memory_limit()
+    }
+
+    /// How many generations past the end of the recorded history a seek may compute.
+    pub fn seek_limit(&self) -> usize {
+        self.history.seek_limit()
+    }
+
+    /// The memory, in bytes, the history currently takes.
+    pub fn history_memory(&self) -> usize {
+        self.history.memory()
+    }
+
+    /// Goes back to the board as it was before the last step, undoing any edits made since.
+    pub fn step_back(&mut self) -> Result<(), Error> {
+        let position = self.history.step_back_target().ok_or(Error::HistoryUnavailable {
+            reason: "no earlier generation is recorded",
+        })?;
+        self.restore(position);
+        Ok(())
+    }
+
+    /// Moves to `generation`, after any edits that were made to it. Generations past the recorded
+    /// history are computed by stepping on from its end, as long as they are no more than
+    /// [`Simulation::seek_limit`] generations past it.
+    pub fn seek(
+        &mut self,
+        generation: usize,
+    ) -> Result<(), Error> {
+        let end = self.history.end_ticks().unwrap_or(self.ticks).max(self.ticks);
+        if generation.saturating_sub(end) > self.history.seek_limit() {
+            return Err(Error::HistoryUnavailable {
+                reason: "the generation is further past the recorded history than the seek limit",
+            });
+        }
+        match self.history.seek_target(generation) {
+            Some(position) => self.restore(position),
+            None if generation >= self.ticks => {}
+            None => {
+                return Err(Error::HistoryUnavailable {
+                    reason: "the generation is older than the recorded history",
+                });
+            }
+        }
+        while self.ticks < generation {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Takes back the last edit, along with every step made since.
+    pub fn undo(&mut self) -> Result<(), Error> {
+        let position = self.history.undo_target().ok_or(Error::HistoryUnavailable {
+            reason: "no edit is recorded",
+        })?;
+        self.restore(position);
+        self.history.truncate(position);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Blend,
+        Message,
+        Pattern,
+    };
+
+    /// A torus with an r-pentomino in the middle, keeping a keyframe every `interval` steps.
+    fn simulation(
+        interval: usize,
+        history_limit: usize,
+    ) -> Simulation {
+        let mut simulation = Simulation::builder()
+            .width(40)
+            .height(40)
+            .keyframe_interval(interval)
+            .history_limit(history_limit)
+            .cycle_window(0)
+            .build()
+            .unwrap();
+        simulation.place_pattern(&Pattern::named("r-pentomino").unwrap(), 18, 18, Blend::Or);
+        simulation
+    }
+
+    /// Steps `generations` times, returning the board before the first step and after each one.
+    fn run(
+        simulation: &mut Simulation,
+        generations: usize,
+    ) -> Vec<Pattern> {
+        let mut boards = vec![simulation.to_pattern()];
+        for _ in 0..generations {
+            simulation.update(Message::Step).unwrap();
+            boards.push(simulation.to_pattern());
+        }
+        boards
+    }
+
+    fn unavailable(result: Result<(), Error>) -> bool {
+        matches!(result, Err(Error::HistoryUnavailable { .. }))
+    }
+
+    #[test]
+    fn step_back_crosses_keyframes() {
+        let mut simulation = simulation(4, History::DEFAULT_MEMORY_LIMIT);
+        let boards = run(&mut simulation, 21);
+
+        for generation in (0..21).rev() {
+            simulation.update(Message::StepBack).unwrap();
+            assert_eq!(simulation.ticks(), generation);
+            assert_eq!(simulation.to_pattern(), boards[generation]);
+        }
+        assert!(unavailable(simulation.step_back()));
+
+        // Stepping again follows the recorded steps rather than discarding them.
+        assert_eq!(run(&mut simulation, 21), boards);
+    }
+
+    #[test]
+    fn seek_replays_and_computes_within_the_limit() {
+        let mut simulation = simulation(4, History::DEFAULT_MEMORY_LIMIT);
+        let boards = run(&mut simulation, 30);
+
+        for &generation in &[3, 17, 0, 30, 9] {
+            simulation.update(Message::Seek(generation)).unwrap();
+            assert_eq!(simulation.ticks(), generation);
+            assert_eq!(simulation.to_pattern(), boards[generation]);
+        }
+
+        simulation.seek(10).unwrap();
+        simulation.seek(40).unwrap();
+        let mut stepped = Simulation::from_pattern(&boards[30]).unwrap();
+        run(&mut stepped, 10);
+        assert_eq!(simulation.to_pattern(), stepped.to_pattern());
+
+        let board = simulation.to_pattern();
+        assert!(unavailable(simulation.seek(usize::MAX)));
+        assert!(unavailable(simulation.seek(40 + History::DEFAULT_SEEK_LIMIT + 1)));
+        assert_eq!(simulation.ticks(), 40);
+        assert_eq!(simulation.to_pattern(), board);
+    }
+
+    #[test]
+    fn seek_limit_counts_from_the_end_of_the_history() {
+        let mut simulation = Simulation::builder().width(8).height(8).seek_limit(5).build().unwrap();
+        run(&mut simulation, 10);
+        simulation.seek(2).unwrap();
+
+        assert!(unavailable(simulation.seek(16)));
+        assert_eq!(simulation.ticks(), 2);
+        simulation.seek(15).unwrap();
+        assert_eq!(simulation.ticks(), 15);
+        assert_eq!(simulation.seek_limit(), 5);
+    }
+
+    #[test]
+    fn undo_takes_back_edits_and_the_steps_since() {
+        let mut simulation = simulation(4, History::DEFAULT_MEMORY_LIMIT);
+        let boards = run(&mut simulation, 6);
+        simulation.update(Message::ToggleCellule(0)).unwrap();
+        let edited = simulation.to_pattern();
+        run(&mut simulation, 11);
+
+        simulation.update(Message::Undo).unwrap();
+        assert_eq!(simulation.ticks(), 6);
+        assert_eq!(simulation.to_pattern(), boards[6]);
+        assert_ne!(simulation.to_pattern(), edited);
+
+        // The undone steps are gone, so stepping on goes from the unedited board.
+        let mut unedited = Simulation::from_pattern(&boards[6]).unwrap();
+        assert_eq!(run(&mut simulation, 3)[3], run(&mut unedited, 3)[3]);
+
+        simulation.undo().unwrap();
+        assert_eq!(simulation.ticks(), 0);
+        assert_eq!(simulation.population(), 0);
+        assert!(unavailable(simulation.undo()));
+    }
+
+    #[test]
+    fn history_messages_read_from_json() {
+        let mut simulation = simulation(4, History::DEFAULT_MEMORY_LIMIT);
+        let boards = run(&mut simulation, 10);
+        simulation.update(Message::ToggleCellule(0)).unwrap();
+
+        let message = |json: &str| serde_json::from_str::<Message>(json).unwrap();
+        assert_eq!(message(r#""Undo""#), Message::Undo);
+        assert_eq!(message(r#""StepBack""#), Message::StepBack);
+        assert_eq!(message(r#"{"Seek":3}"#), Message::Seek(3));
+
+        simulation.update(message(r#""Undo""#)).unwrap();
+        assert_eq!(simulation.to_pattern(), boards[10]);
+        simulation.update(message(r#""StepBack""#)).unwrap();
+        assert_eq!(simulation.to_pattern(), boards[9]);
+        simulation.update(message(r#"{"Seek":3}"#)).unwrap();
+        assert_eq!(simulation.to_pattern(), boards[3]);
+    }
+
+    #[test]
+    fn trimming_keeps_the_history_within_its_limit() {
+        let keyframe = simulation(1, History::DEFAULT_MEMORY_LIMIT).cellules.memory();
+        let limit = 4 * keyframe;
+        let mut simulation = simulation(2, limit);
+        let boards = run(&mut simulation, 50);
+        assert!(simulation.history_memory() <= limit);
+
+        assert!(unavailable(simulation.seek(0)));
+        assert_eq!(simulation.ticks(), 50);
+
+        let mut generation = 50;
+        while simulation.step_back().is_ok() {
+            generation -= 1;
+            assert_eq!(simulation.to_pattern(), boards[generation]);
+        }
+        assert!(generation > 40, "went back to generation {}", generation);
+        assert!(generation < 50, "could not go back at all");
+    }
+}
@@ -12,6 +12,91 @@ use crate::deps::{
 use crate::deps::locutus_actor as actor;
 use std::fmt;
 
+pub use crate::{
+    bounds::Bounds,
+    census::{
+        Census,
+        Object,
+        ObjectKind,
+    },
+    cycle::{
+        Stabilization,
+        StabilizationPolicy,
+    },
+    grid::Grid,
+    hashlife::HashLife,
+    line::{
+        LineAutomaton,
+        LineMessage,
+        LineRule,
+    },
+    neighborhood::Neighborhood,
+    pattern::{
+        Blend,
+        Pattern,
+        Transform,
+    },
+    resize::Anchor,
+    rule::Rule,
+    soup::{
+        Soup,
+        Symmetry,
+    },
+    sparse::SparseLife,
+    stats::{
+        Stats,
+        StatsSeries,
+    },
+    topology::Topology,
+    turmite::{
+        Ant,
+        Heading,
+        Transition,
+        TurmiteMessage,
+        TurmiteRule,
+        Turmites,
+        Turn,
+    },
+    wireworld::{
+        WireCell,
+        Wireworld,
+        WireworldMessage,
+    },
+};
+
+use crate::{
+    bitgrid::BitGrid,
+    cycle::CycleDetector,
+    history::{
+        Event,
+        History,
+    },
+};
+
+mod bitgrid;
+mod bounds;
+mod catalog;
+mod census;
+mod cycle;
+mod grid;
+mod hashlife;
+mod history;
+mod life106;
+mod line;
+mod neighborhood;
+mod pattern;
+mod plaintext;
+mod region;
+mod resize;
+mod rle;
+mod rule;
+mod soup;
+mod sparse;
+mod stats;
+mod topology;
+mod turmite;
+mod wireworld;
+
 pub(crate) mod deps {
     pub use rand;
     pub use serde;
@@ -20,20 +105,9 @@ pub(crate) mod deps {
 
     #[cfg(feature = "actor")]
     pub use locutus_actor;
-}
 
-fn wrap(
-    coord: isize,
-    range: isize,
-) -> usize {
-    let result = if coord < 0 {
-        coord + range
-    } else if coord >= range {
-        coord - range
-    } else {
-        coord
-    };
-    result as usize
+    #[cfg(feature = "parallel")]
+    pub use rayon;
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -46,6 +120,68 @@ pub enum Error {
 
     #[error("invalid state transition from {from:?} -> {to:?}")]
     StateTransition { from: State, to: State },
+
+    #[error("invalid board dimensions {width}x{height}")]
+    InvalidDimensions { width: usize, height: usize },
+
+    #[error("unknown topology {0:?}")]
+    ParseTopology(String),
+
+    #[error("unknown symmetry {0:?}")]
+    ParseSymmetry(String),
+
+    #[error("unknown stabilization policy {0:?}")]
+    ParseStabilizationPolicy(String),
+
+    #[error("invalid rule {rule:?}: {reason}")]
+    ParseRule { rule: String, reason: &'static str },
+
+    #[error("invalid {format} at line {line}, column {column}: {reason}")]
+    Parse {
+        format: &'static str,
+        line:   usize,
+        column: usize,
+        reason: String,
+    },
+
+    #[error("{operation} is not supported by the {engine} engine")]
+    Unsupported { engine: &'static str, operation: String },
+
+    #[error("the universe has grown past the largest supported extent")]
+    UniverseOverflow,
+
+    #[error("invalid soup: {reason}")]
+    InvalidSoup { reason: &'static str },
+
+    #[error("history unavailable: {reason}")]
+    HistoryUnavailable { reason: &'static str },
+
+    #[error("team {team} is not one of the {teams} teams of the rule")]
+    InvalidTeam { team: usize, teams: usize },
+
+    #[error("unknown pattern {0:?}")]
+    UnknownPattern(String),
+
+    #[error("region {region} does not lie on the {width}x{height} board")]
+    RegionOutOfBounds { region: Bounds, width: usize, height: usize },
+
+    #[error("nothing has been copied to the clipboard")]
+    EmptyClipboard,
+
+    #[error("cell ({x}, {y}) does not lie on the {width}x{height} board")]
+    OutOfBounds { x: usize, y: usize, width: usize, height: usize },
+
+    #[error("state {state} is not one of the {states} states of the rule")]
+    InvalidState { state: usize, states: usize },
+
+    #[error("a {width}x{height} pattern has more cells than a pattern can hold")]
+    PatternTooLarge { width: u64, height: u64 },
+
+    #[error("invalid board: {reason}")]
+    InvalidBoard { reason: &'static str },
+
+    #[error("the range {range} neighborhood of the rule does not fit the {width}x{height} board")]
+    RangeTooLarge { range: usize, width: usize, height: usize },
 }
 
 impl Error {
@@ -54,6 +190,25 @@ impl Error {
             Error::Unknown => false,
             Error::GameOver => false,
             Error::StateTransition { .. } => true,
+            Error::InvalidDimensions { .. } => true,
+            Error::ParseTopology(_) => true,
+            Error::ParseSymmetry(_) => true,
+            Error::ParseStabilizationPolicy(_) => true,
+            Error::ParseRule { .. } => true,
+            Error::Parse { .. } => true,
+            Error::Unsupported { .. } => true,
+            Error::UniverseOverflow => false,
+            Error::InvalidSoup { .. } => true,
+            Error::HistoryUnavailable { .. } => true,
+            Error::InvalidTeam { .. } => true,
+            Error::UnknownPattern(_) => true,
+            Error::RegionOutOfBounds { .. } => true,
+            Error::EmptyClipboard => true,
+            Error::OutOfBounds { .. } => true,
+            Error::InvalidState { .. } => true,
+            Error::PatternTooLarge { .. } => true,
+            Error::InvalidBoard { .. } => true,
+            Error::RangeTooLarge { .. } => true,
         }
     }
 
@@ -62,6 +217,25 @@ impl Error {
             Error::Unknown => false,
             Error::GameOver => false,
             Error::StateTransition { from: _, to: _ } => false,
+            Error::InvalidDimensions { .. } => false,
+            Error::ParseTopology(_) => false,
+            Error::ParseSymmetry(_) => false,
+            Error::ParseStabilizationPolicy(_) => false,
+            Error::ParseRule { .. } => false,
+            Error::Parse { .. } => false,
+            Error::Unsupported { .. } => false,
+            Error::UniverseOverflow => false,
+            Error::InvalidSoup { .. } => false,
+            Error::HistoryUnavailable { .. } => false,
+            Error::InvalidTeam { .. } => false,
+            Error::UnknownPattern(_) => false,
+            Error::RegionOutOfBounds { .. } => false,
+            Error::EmptyClipboard => false,
+            Error::OutOfBounds { .. } => false,
+            Error::InvalidState { .. } => false,
+            Error::PatternTooLarge { .. } => false,
+            Error::InvalidBoard { .. } => false,
+            Error::RangeTooLarge { .. } => false,
         }
     }
 }
@@ -180,41 +354,180 @@ impl State {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum Message {
     Random,
+    /// Refills the whole board with a reproducible [`Soup`].
+    RandomSeeded {
+        seed:     u64,
+        density:  f64,
+        symmetry: Symmetry,
+    },
     Start,
     Step,
     Reset,
     Stop,
     ToggleCellule(usize),
+    /// Toggles the cellule at a row major index like [`Message::ToggleCellule`], bringing it to life
+    /// on `team` of a colored [`Rule`].
+    ToggleTeamCellule {
+        idx:  usize,
+        team: usize,
+    },
+    /// Sets the cell in column `x` and row `y` to a state numbered like [`Cellule::state`].
+    SetCell {
+        x:     usize,
+        y:     usize,
+        state: usize,
+    },
+    /// Sets several cells like [`Message::SetCell`] as a single edit. Nothing is changed unless every
+    /// cell lies on the board.
+    SetCells(Vec<CellEdit>),
+    /// Stamps the [`Pattern::named`] catalog pattern `name` onto the board, see
+    /// [`Simulation::place_pattern`].
+    PlacePattern {
+        name:      String,
+        x:         isize,
+        y:         isize,
+        transform: Transform,
+        blend:     Blend,
+    },
+    /// Brings every cell of the region to life.
+    FillRegion(Bounds),
+    /// Kills every cell of the region.
+    ClearRegion(Bounds),
+    /// Kills the live cells of the region and brings the others to life.
+    InvertRegion(Bounds),
+    /// Overwrites the region with a reproducible [`Soup`].
+    RandomRegion {
+        region:   Bounds,
+        seed:     u64,
+        density:  f64,
+        symmetry: Symmetry,
+    },
+    /// Copies the region to the clipboard of the simulation.
+    CopyRegion(Bounds),
+    /// Stamps the clipboard onto the board like [`Message::PlacePattern`].
+    Paste {
+        x:         isize,
+        y:         isize,
+        transform: Transform,
+        blend:     Blend,
+    },
+    /// Grows or crops the board to `width` by `height` around `anchor`, see [`Simulation::resize`].
+    Resize {
+        width:  usize,
+        height: usize,
+        anchor: Anchor,
+    },
+    SetRule(Rule),
+    SetTopology(Topology),
+    /// Goes back to the generation before the current one.
+    StepBack,
+    /// Moves to the given generation, computing it if it was never reached.
+    Seek(usize),
+    /// Takes back the last edit to the board, rule or topology.
+    Undo,
     Tick,
     End,
 }
 
+/// The messages that run any engine, whatever it simulates. Engines other than [`Simulation`] take
+/// their own message enum wrapping these alongside the edits that make sense for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Control {
+    Start,
+    /// Advances a single step, whether or not the engine is running.
+    Step,
+    Reset,
+    Stop,
+    /// Advances a step if the engine is running, sent at the pace of the host.
+    Tick,
+    End,
+}
+
+impl From<Control> for Message {
+    fn from(control: Control) -> Self {
+        match control {
+            Control::Start => Message::Start,
+            Control::Step => Message::Step,
+            Control::Reset => Message::Reset,
+            Control::Stop => Message::Stop,
+            Control::Tick => Message::Tick,
+            Control::End => Message::End,
+        }
+    }
+}
+
+/// The messages of the unbounded [`SparseLife`] and [`HashLife`] universes, which have no board to
+/// edit by index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum UniverseMessage {
+    Control(Control),
+    SetRule(Rule),
+}
+
+impl From<Control> for UniverseMessage {
+    fn from(control: Control) -> Self {
+        UniverseMessage::Control(control)
+    }
+}
+
+/// One cell of a [`Message::SetCells`] batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CellEdit {
+    pub x:     usize,
+    pub y:     usize,
+    pub state: usize,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
 #[repr(u8)]
 pub enum LifeState {
     Alive = 1,
     Dead = 0,
+    /// Past being alive and on its way to dead under a Generations rule.
+    Dying = 2,
 }
 
 #[derive(Copy, Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Cellule {
-    life_state: LifeState,
+    state: usize,
 }
 
 impl Cellule {
     pub fn set_alive(&mut self) {
-        self.life_state = LifeState::Alive;
+        *self = Self::from_state(1);
     }
 
     pub fn set_dead(&mut self) {
-        self.life_state = LifeState::Dead;
+        *self = Self::from_state(0);
     }
 
     pub fn alive(self) -> bool {
-        self.life_state == LifeState::Alive
+        self.life_state() == LifeState::Alive
+    }
+
+    pub fn dying(self) -> bool {
+        self.life_state() == LifeState::Dying
+    }
+
+    pub fn life_state(self) -> LifeState {
+        match self.state {
+            0 => LifeState::Dead,
+            1 => LifeState::Alive,
+            _ => LifeState::Dying,
+        }
+    }
+
+    /// The state number of the cellule: 0 when dead, 1 when alive and from 2 up to one less than
+    /// [`Rule::states`] while dying.
+    pub fn state(self) -> usize {
+        self.state
+    }
+
+    pub(crate) fn from_state(state: usize) -> Self {
+        Self { state }
     }
 
     pub fn count_alive_neighbors(neighbors: &[Cellule]) -> usize {
@@ -234,32 +547,260 @@ impl Cellule {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SimulationBuilder {
+    width:             usize,
+    height:            usize,
+    rule:              Rule,
+    topology:          Topology,
+    cycle_window:      usize,
+    on_stabilize:      StabilizationPolicy,
+    stats_window:      usize,
+    keyframe_interval: usize,
+    history_limit:     usize,
+    seek_limit:        usize,
+}
+
+impl std::default::Default for SimulationBuilder {
+    fn default() -> Self {
+        Self {
+            width:             Simulation::DEFAULT_WIDTH,
+            height:            Simulation::DEFAULT_HEIGHT,
+            rule:              Rule::default(),
+            topology:          Topology::default(),
+            cycle_window:      CycleDetector::DEFAULT_WINDOW,
+            on_stabilize:      StabilizationPolicy::default(),
+            stats_window:      StatsSeries::DEFAULT_CAPACITY,
+            keyframe_interval: History::DEFAULT_KEYFRAME_INTERVAL,
+            history_limit:     History::DEFAULT_MEMORY_LIMIT,
+            seek_limit:        History::DEFAULT_SEEK_LIMIT,
+        }
+    }
+}
+
+impl SimulationBuilder {
+    pub fn width(
+        mut self,
+        width: usize,
+    ) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn height(
+        mut self,
+        height: usize,
+    ) -> Self {
+        self.height = height;
+        self
+    }
+
+    pub fn rule(
+        mut self,
+        rule: Rule,
+    ) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    pub fn topology(
+        mut self,
+        topology: Topology,
+    ) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// How many past generations are remembered to detect a repeating board. Periods longer than
+    /// this are never reported, and zero turns detection off.
+    pub fn cycle_window(
+        mut self,
+        window: usize,
+    ) -> Self {
+        self.cycle_window = window;
+        self
+    }
+
+    pub fn on_stabilize(
+        mut self,
+        policy: StabilizationPolicy,
+    ) -> Self {
+        self.on_stabilize = policy;
+        self
+    }
+
+    /// How many generations of [`Stats`] are kept in [`Simulation::stats_series`].
+    pub fn stats_window(
+        mut self,
+        window: usize,
+    ) -> Self {
+        self.stats_window = window;
+        self
+    }
+
+    /// How many steps apart full copies of the board are kept in the history. Shorter intervals make
+    /// going back faster and the history shorter for the same memory.
+    pub fn keyframe_interval(
+        mut self,
+        interval: usize,
+    ) -> Self {
+        self.keyframe_interval = interval;
+        self
+    }
+
+    /// The most memory, in bytes, the history may take before its oldest part is dropped. Zero
+    /// turns the history off.
+    pub fn history_limit(
+        mut self,
+        bytes: usize,
+    ) -> Self {
+        self.history_limit = bytes;
+        self
+    }
+
+    /// How many generations past the end of the recorded history [`Simulation::seek`] may compute
+    /// before giving up. Zero keeps seeking within the history.
+    pub fn seek_limit(
+        mut self,
+        generations: usize,
+    ) -> Self {
+        self.seek_limit = generations;
+        self
+    }
+
+    /// Validates the dimensions against [`Simulation::MAX_CELLS`] and the range of the rule, and
+    /// allocates an empty board.
+    pub fn build(self) -> Result<Simulation, Error> {
+        let SimulationBuilder {
+            width,
+            height,
+            rule,
+            topology,
+            cycle_window,
+            on_stabilize,
+            stats_window,
+            keyframe_interval,
+            history_limit,
+            seek_limit,
+        } = self;
+        check_dimensions(width, height)?;
+        check_range(&rule, width, height)?;
+
+        let mut cellules = BitGrid::new(width, height);
+        cellules.set_states(rule.states());
+        cellules.set_teams(rule.teams());
+
+        Ok(Simulation {
+            state: State::default(),
+            ticks: 0,
+            cellules,
+            rule,
+            grid: rule.grid(),
+            topology,
+            stabilization: None,
+            on_stabilize,
+            cycles: CycleDetector::new(cycle_window),
+            stats: Stats::default(),
+            stats_series: StatsSeries::new(stats_window),
+            history: History::new(keyframe_interval, history_limit, seek_limit),
+            clipboard: None,
+        })
+    }
+}
+
+/// The number of cells of a `width` by `height` board, failing with [`Error::InvalidDimensions`]
+/// unless there is at least one and no more than [`Simulation::MAX_CELLS`]. Every engine with a
+/// bounded board checks its dimensions here.
+pub(crate) fn check_dimensions(
+    width: usize,
+    height: usize,
+) -> Result<usize, Error> {
+    width
+        .checked_mul(height)
+        .filter(|&len| len > 0 && len <= Simulation::MAX_CELLS)
+        .ok_or(Error::InvalidDimensions { width, height })
+}
+
+/// Fails with [`Error::RangeTooLarge`] when `rule` counts neighbors further away than the width or
+/// height of a `width` by `height` board. Such a neighborhood would take in some cells more than once,
+/// and the summed-area table it is counted through grows with the square of the range however small
+/// the board.
+pub(crate) fn check_range(
+    rule: &Rule,
+    width: usize,
+    height: usize,
+) -> Result<(), Error> {
+    if rule.range() > 1 && rule.range() > width.min(height) {
+        Err(Error::RangeTooLarge {
+            range: rule.range(),
+            width,
+            height,
+        })
+    } else {
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Simulation {
-    state:           State,
-    ticks:           usize,
-    cellules:        Vec<Cellule>,
-    cellules_width:  usize,
-    cellules_height: usize,
+    state:         State,
+    ticks:         usize,
+    cellules:      BitGrid,
+    #[serde(default)]
+    rule:          Rule,
+    /// Always the grid of `rule`, kept alongside so that renderers find it in the serialized state.
+    #[serde(default)]
+    grid:          Grid,
+    #[serde(default)]
+    topology:      Topology,
+    #[serde(default)]
+    stabilization: Option<Stabilization>,
+    #[serde(default)]
+    on_stabilize:  StabilizationPolicy,
+    #[serde(skip)]
+    cycles:        CycleDetector,
+    #[serde(default)]
+    stats:         Stats,
+    #[serde(skip)]
+    stats_series:  StatsSeries,
+    #[serde(skip)]
+    history:       History,
+    #[serde(skip)]
+    clipboard:     Option<Pattern>,
 }
 
 impl Simulation {
-    const HEIGHT: usize = 60;
-    const WIDTH: usize = 80;
+    pub const ALIVE_GLYPH: char = '◼';
+    pub const DEAD_GLYPH: char = '◻';
+    pub const DEFAULT_HEIGHT: usize = 60;
+    pub const DEFAULT_WIDTH: usize = 80;
+    /// The most cells a board may have. Dimensions often come from untrusted input, so larger
+    /// boards are rejected rather than allocated.
+    pub const MAX_CELLS: usize = 1 << 28;
 
     pub fn new() -> Self {
-        Self {
-            state:           State::default(),
-            ticks:           0,
-            cellules:        vec![
-                Cellule {
-                    life_state: LifeState::Dead,
-                };
-                Self::WIDTH * Self::HEIGHT
-            ],
-            cellules_width:  Self::WIDTH,
-            cellules_height: Self::HEIGHT,
-        }
+        Self::with_size(Self::DEFAULT_WIDTH, Self::DEFAULT_HEIGHT)
+    }
+
+    /// Creates an empty board of `width` columns by `height` rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either dimension is zero or the board would have more than [`Simulation::MAX_CELLS`]
+    /// cells. Use [`Simulation::builder`] to validate dimensions that come from untrusted input.
+    pub fn with_size(
+        width: usize,
+        height: usize,
+    ) -> Self {
+        Self::builder()
+            .width(width)
+            .height(height)
+            .build()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn builder() -> SimulationBuilder {
+        SimulationBuilder::default()
     }
 
     pub fn ticks(&self) -> usize {
@@ -270,94 +811,293 @@ impl Simulation {
         self.state
     }
 
-    pub fn cellules(&self) -> &[Cellule] {
-        &self.cellules[..]
+    /// Every cellule in row major order, `width()` cellules to a row.
+    pub fn cellules(&self) -> impl ExactSizeIterator<Item = Cellule> + '_ {
+        self.cellules.states().map(Cellule::from_state)
+    }
+
+    /// The team of every cellule in the order of [`Simulation::cellules`]. Under a colored [`Rule`]
+    /// a live cellule is on a team from 0 up to one less than [`Rule::teams`], and every other
+    /// cellule is on team 0.
+    pub fn cellule_teams(&self) -> impl ExactSizeIterator<Item = usize> + '_ {
+        self.cellules.teams()
+    }
+
+    /// The cellule at row major index `idx`, if it is on the board.
+    pub fn cellule(
+        &self,
+        idx: usize,
+    ) -> Option<Cellule> {
+        let (x, y) = self.position(idx)?;
+        self.cellule_at(x, y)
+    }
+
+    /// The cellule in column `x` and row `y`, or `None` off the board.
+    pub fn cellule_at(
+        &self,
+        x: usize,
+        y: usize,
+    ) -> Option<Cellule> {
+        if x < self.width() && y < self.height() {
+            Some(Cellule::from_state(self.cellules.state(x, y)))
+        } else {
+            None
+        }
+    }
+
+    /// The team of the cellule in column `x` and row `y` like [`Simulation::cellule_teams`], or
+    /// `None` off the board.
+    pub fn team_at(
+        &self,
+        x: usize,
+        y: usize,
+    ) -> Option<usize> {
+        if x < self.width() && y < self.height() {
+            Some(self.cellules.team(x, y))
+        } else {
+            None
+        }
+    }
+
+    /// The row major index of the cellule in column `x` and row `y`, or `None` off the board.
+    pub fn index(
+        &self,
+        x: usize,
+        y: usize,
+    ) -> Option<usize> {
+        if x < self.width() && y < self.height() {
+            Some(y * self.width() + x)
+        } else {
+            None
+        }
+    }
+
+    /// The column and row of the cellule at row major index `idx`, or `None` off the board.
+    pub fn position(
+        &self,
+        idx: usize,
+    ) -> Option<(usize, usize)> {
+        if idx < self.cellules.len() {
+            Some((idx % self.width(), idx / self.width()))
+        } else {
+            None
+        }
+    }
+
+    pub fn population(&self) -> usize {
+        self.cellules.population()
+    }
+
+    /// How many live cellules are on each team of the rule, which is just the population for a rule
+    /// that is not colored.
+    pub fn team_populations(&self) -> Vec<usize> {
+        (0..self.rule.teams()).map(|team| self.cellules.team_population(team)).collect()
     }
 
     pub fn height(&self) -> usize {
-        self.cellules_height
+        self.cellules.height()
     }
 
     pub fn width(&self) -> usize {
-        self.cellules_width
+        self.cellules.width()
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// The tiling the cells stand for, set by the neighborhood of the rule.
+    pub fn grid(&self) -> Grid {
+        self.grid
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// What the board has settled into, once it has been seen to repeat.
+    pub fn stabilization(&self) -> Option<Stabilization> {
+        self.stabilization
+    }
+
+    /// How many past generations are searched for a repeat of the current one.
+    pub fn cycle_window(&self) -> usize {
+        self.cycles.window()
+    }
+
+    pub fn on_stabilize(&self) -> StabilizationPolicy {
+        self.on_stabilize
+    }
+
+    pub fn set_on_stabilize(
+        &mut self,
+        policy: StabilizationPolicy,
+    ) {
+        self.on_stabilize = policy;
+    }
+
+    /// The [`Stats`] of the current generation.
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// The [`Stats`] of the most recent generations, oldest first.
+    pub fn stats_series(&self) -> &StatsSeries {
+        &self.stats_series
+    }
+
+    /// The Zobrist hash of the board. Equal boards have equal hashes whatever their history.
+    pub fn board_hash(&self) -> u64 {
+        self.cellules.hash()
     }
 
     pub fn random_mutate(&mut self) {
-        for cellule in self.cellules.iter_mut() {
-            if thread_rng().gen() {
-                cellule.set_alive();
-            } else {
-                cellule.set_dead();
-            }
-        }
+        let mut rng = thread_rng();
+        self.replace_cellules(|cellules| cellules.fill_with(|| rng.gen()));
+        self.board_edited();
     }
 
     fn reset(&mut self) {
-        for cellule in self.cellules.iter_mut() {
-            cellule.set_dead();
-        }
-    }
-
-    fn step(&mut self) {
-        self.ticks += 1;
-        let mut to_dead = Vec::new();
-        let mut to_live = Vec::new();
-        for row in 0..self.cellules_height {
-            for col in 0..self.cellules_width {
-                let neighbors = self.neighbors(row as isize, col as isize);
-
-                let current_idx = self.row_col_as_idx(row as isize, col as isize);
-                if self.cellules[current_idx].alive() {
-                    if Cellule::alone(&neighbors) || Cellule::overpopulated(&neighbors) {
-                        to_dead.push(current_idx);
-                    }
-                } else if Cellule::can_be_revived(&neighbors) {
-                    to_live.push(current_idx);
-                }
-            }
-        }
-        to_dead.iter().for_each(|idx| self.cellules[*idx].set_dead());
-        to_live.iter().for_each(|idx| self.cellules[*idx].set_alive());
+        self.perform(Event::Clear);
+        self.board_edited();
     }
 
-    fn neighbors(
-        &self,
-        row: isize,
-        col: isize,
-    ) -> [Cellule; 8] {
-        [
-            self.cellules[self.row_col_as_idx(row + 1, col)],
-            self.cellules[self.row_col_as_idx(row + 1, col + 1)],
-            self.cellules[self.row_col_as_idx(row + 1, col - 1)],
-            self.cellules[self.row_col_as_idx(row - 1, col)],
-            self.cellules[self.row_col_as_idx(row - 1, col + 1)],
-            self.cellules[self.row_col_as_idx(row - 1, col - 1)],
-            self.cellules[self.row_col_as_idx(row, col - 1)],
-            self.cellules[self.row_col_as_idx(row, col + 1)],
-        ]
-    }
-
-    fn row_col_as_idx(
+    /// Drops what is known about past generations, for after anything but a step changes the board
+    /// or the way it evolves.
+    fn forget_cycles(&mut self) {
+        self.cycles.clear();
+        self.stabilization = None;
+    }
+
+    /// Brings everything derived from the board up to date after it was changed by anything but a
+    /// step.
+    fn board_edited(&mut self) {
+        self.forget_cycles();
+        self.stats = self.measure(0, 0);
+    }
+
+    fn measure(
         &self,
-        row: isize,
-        col: isize,
-    ) -> usize {
-        let row = wrap(row, self.cellules_height as isize);
-        let col = wrap(col, self.cellules_width as isize);
+        births: usize,
+        deaths: usize,
+    ) -> Stats {
+        Stats {
+            generation: self.ticks as u64,
+            population: self.population(),
+            births,
+            deaths,
+            bounds: self.cellules.bounds(),
+        }
+    }
+
+    fn step(&mut self) -> Result<(), Error> {
+        let previous = self.cellules.hash();
+        let (births, deaths) = self.perform(Event::Step);
+        self.stats = self.measure(births, deaths);
+        self.stats_series.push(self.stats);
+
+        if self.stabilization.is_some() {
+            return Ok(());
+        }
+        let stabilization = if self.stats.population == 0 && !self.rule.born(0) {
+            Some(Stabilization::Empty)
+        } else {
+            self.cycles.record(previous, self.cellules.hash()).map(|period| {
+                match period {
+                    1 => Stabilization::Still,
+                    period => Stabilization::Periodic { period },
+                }
+            })
+        };
+
+        if let Some(stabilization) = stabilization {
+            self.stabilization = Some(stabilization);
+            info!("Stabilized at tick {}: {}", self.ticks, stabilization);
+            match self.on_stabilize {
+                StabilizationPolicy::Continue => {}
+                StabilizationPolicy::Pause => self.state.try_transition(State::Pausing)?,
+                StabilizationPolicy::End => self.state.try_transition(State::Ended)?,
+            }
+        }
 
-        row * self.cellules_width + col
+        Ok(())
     }
 
     fn toggle_cellule(
         &mut self,
         idx: usize,
-    ) {
-        let cellule = self.cellules.get_mut(idx).unwrap();
-        if cellule.life_state == LifeState::Alive {
-            cellule.life_state = LifeState::Dead
+        team: usize,
+    ) -> Result<(), Error> {
+        if team >= self.rule.teams() {
+            return Err(Error::InvalidTeam {
+                team,
+                teams: self.rule.teams(),
+            });
+        }
+        let (x, y) = self.position(idx).ok_or(Error::OutOfBounds {
+            x:      idx % self.width(),
+            y:      idx / self.width(),
+            width:  self.width(),
+            height: self.height(),
+        })?;
+        self.perform(Event::Toggle { x, y, team });
+        self.board_edited();
+        Ok(())
+    }
+
+    /// Sets the cellule in column `x` and row `y` to `state`, numbered like [`Cellule::state`]. A
+    /// live cellule keeps its team and one brought to life joins team 0.
+    pub fn set_cell(
+        &mut self,
+        x: usize,
+        y: usize,
+        state: usize,
+    ) -> Result<(), Error> {
+        self.check_cell(x, y, state)?;
+        self.perform(Event::Set { x, y, state });
+        self.board_edited();
+        Ok(())
+    }
+
+    /// Sets every cellule of `edits` like [`Simulation::set_cell`] as a single edit, changing
+    /// nothing unless all of them are valid.
+    pub fn set_cells(
+        &mut self,
+        edits: &[CellEdit],
+    ) -> Result<(), Error> {
+        for edit in edits {
+            self.check_cell(edit.x, edit.y, edit.state)?;
+        }
+        self.replace_cellules(|cellules| {
+            for edit in edits {
+                cellules.set_state(edit.x, edit.y, edit.state);
+            }
+        });
+        self.board_edited();
+        Ok(())
+    }
+
+    fn check_cell(
+        &self,
+        x: usize,
+        y: usize,
+        state: usize,
+    ) -> Result<(), Error> {
+        if x >= self.width() || y >= self.height() {
+            Err(Error::OutOfBounds {
+                x,
+                y,
+                width: self.width(),
+                height: self.height(),
+            })
+        } else if state >= self.rule.states() {
+            Err(Error::InvalidState {
+                state,
+                states: self.rule.states(),
+            })
         } else {
-            cellule.life_state = LifeState::Alive
-        };
+            Ok(())
+        }
     }
 
     pub fn update(
@@ -374,12 +1114,23 @@ impl Simulation {
                 self.random_mutate();
                 info!("Random");
             }
+            Message::RandomSeeded {
+                seed,
+                density,
+                symmetry,
+            } => {
+                self.fill_soup(&Soup {
+                    symmetry,
+                    ..Soup::new(seed, density)
+                })?;
+                info!("RandomSeeded {} {} {}", seed, density, symmetry);
+            }
             Message::Start => {
                 self.state.try_transition(Running)?;
                 info!("Start");
             }
             Message::Step => {
-                self.step();
+                self.step()?;
             }
             Message::Reset => {
                 self.reset();
@@ -390,11 +1141,98 @@ impl Simulation {
                 info!("Stop");
             }
             Message::ToggleCellule(idx) => {
-                self.toggle_cellule(idx);
+                self.toggle_cellule(idx, 0)?;
+            }
+            Message::ToggleTeamCellule { idx, team } => {
+                self.toggle_cellule(idx, team)?;
+            }
+            Message::SetCell { x, y, state } => {
+                self.set_cell(x, y, state)?;
+                info!("SetCell ({}, {}) to {}", x, y, state);
+            }
+            Message::SetCells(edits) => {
+                self.set_cells(&edits)?;
+                info!("SetCells {}", edits.len());
+            }
+            Message::PlacePattern {
+                name,
+                x,
+                y,
+                transform,
+                blend,
+            } => {
+                let pattern = Pattern::named(&name)?.transformed(transform);
+                self.place_pattern(&pattern, x, y, blend);
+                info!("PlacePattern {} at ({}, {}) {:?} {:?}", name, x, y, transform, blend);
+            }
+            Message::FillRegion(region) => {
+                self.fill_region(region, true)?;
+                info!("FillRegion {}", region);
+            }
+            Message::ClearRegion(region) => {
+                self.fill_region(region, false)?;
+                info!("ClearRegion {}", region);
+            }
+            Message::InvertRegion(region) => {
+                self.invert_region(region)?;
+                info!("InvertRegion {}", region);
+            }
+            Message::RandomRegion {
+                region,
+                seed,
+                density,
+                symmetry,
+            } => {
+                self.fill_soup(&Soup {
+                    symmetry,
+                    region: Some(region),
+                    ..Soup::new(seed, density)
+                })?;
+                info!("RandomRegion {} {} {} {}", region, seed, density, symmetry);
+            }
+            Message::CopyRegion(region) => {
+                self.copy_to_clipboard(region)?;
+                info!("CopyRegion {}", region);
+            }
+            Message::Paste {
+                x,
+                y,
+                transform,
+                blend,
+            } => {
+                self.paste(x, y, transform, blend)?;
+                info!("Paste at ({}, {}) {:?} {:?}", x, y, transform, blend);
+            }
+            Message::Resize { width, height, anchor } => {
+                self.resize(width, height, anchor)?;
+                info!("Resize to {}x{} {:?}", width, height, anchor);
+            }
+            Message::SetRule(rule) => {
+                check_range(&rule, self.width(), self.height())?;
+                self.perform(Event::SetRule(rule));
+                self.forget_cycles();
+                info!("SetRule {}", rule);
+            }
+            Message::SetTopology(topology) => {
+                self.perform(Event::SetTopology(topology));
+                self.forget_cycles();
+                info!("SetTopology {}", topology);
+            }
+            Message::StepBack => {
+                self.step_back()?;
+                info!("StepBack to {}", self.ticks);
+            }
+            Message::Seek(generation) => {
+                self.seek(generation)?;
+                info!("Seek {}", generation);
+            }
+            Message::Undo => {
+                self.undo()?;
+                info!("Undo");
             }
             Message::Tick => {
                 if self.state.run() {
-                    self.step();
+                    self.step()?;
                 }
             }
             Message::End => {
@@ -406,23 +1244,283 @@ impl Simulation {
     }
 }
 
+impl std::default::Default for Simulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl fmt::Display for Simulation {
     fn fmt(
         &self,
         f: &mut fmt::Formatter,
     ) -> fmt::Result {
-        for line in self.cellules.as_slice().chunks(self.width()) {
-            for &cell in line {
-                let symbol = if cell.alive() {
-                    '◼'
-                } else {
-                    '◻'
-                };
-                write!(f, "{}", symbol)?;
+        for (idx, alive) in self.cellules.iter().enumerate() {
+            let symbol = if alive {
+                Self::ALIVE_GLYPH
+            } else {
+                Self::DEAD_GLYPH
+            };
+            write!(f, "{}", symbol)?;
+            if (idx + 1) % self.width() == 0 {
+                writeln!(f)?;
             }
-            write!(f, "\n")?;
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulation() -> Simulation {
+        Simulation::builder().width(6).height(4).build().unwrap()
+    }
+
+    fn out_of_bounds(result: Result<(), Error>) -> bool {
+        matches!(result, Err(Error::OutOfBounds { width: 6, height: 4, .. }))
+    }
+
+    #[test]
+    fn set_cell_rejects_cells_off_the_board() {
+        let mut simulation = simulation();
+        for &(x, y) in &[(6, 0), (0, 4), (6, 4), (usize::MAX, 0)] {
+            assert!(out_of_bounds(simulation.set_cell(x, y, 1)));
+            assert!(out_of_bounds(simulation.update(Message::SetCell { x, y, state: 1 })));
+        }
+        assert_eq!(simulation.population(), 0);
+    }
+
+    #[test]
+    fn set_cells_rejects_cells_off_the_board() {
+        let mut simulation = simulation();
+        for &(x, y) in &[(6, 0), (0, 4)] {
+            let edits = vec![CellEdit { x, y, state: 1 }];
+            assert!(out_of_bounds(simulation.set_cells(&edits)));
+            assert!(out_of_bounds(simulation.update(Message::SetCells(edits))));
+        }
+        assert_eq!(simulation.population(), 0);
+    }
+
+    #[test]
+    fn set_cells_with_a_bad_edit_changes_nothing() {
+        let mut simulation = simulation();
+        simulation.set_cell(0, 0, 1).unwrap();
+        let before = simulation.to_pattern();
+
+        let edits = vec![
+            CellEdit { x: 1, y: 1, state: 1 },
+            CellEdit { x: 2, y: 4, state: 1 },
+            CellEdit { x: 0, y: 0, state: 0 },
+        ];
+        assert!(out_of_bounds(simulation.update(Message::SetCells(edits))));
+        assert_eq!(simulation.to_pattern(), before);
+    }
+
+    #[test]
+    fn larger_than_life_ranges_must_fit_the_board() {
+        let range_too_large = |result: Result<(), Error>| matches!(result, Err(Error::RangeTooLarge { range: 5, .. }));
+        let bosco: Rule = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+        let build = |width, height| Simulation::builder().width(width).height(height).rule(bosco).build().map(drop);
+        assert!(build(5, 5).is_ok());
+        assert!(range_too_large(build(4, 100)));
+        assert!(range_too_large(build(100, 4)));
+        // Range 1 rules run on boards of any size.
+        assert!(Simulation::builder().width(1).height(1).build().is_ok());
+
+        let mut simulation = simulation();
+        assert!(range_too_large(simulation.update(Message::SetRule(bosco))));
+        assert_eq!(simulation.rule(), Rule::default());
+
+        simulation.resize(5, 5, Anchor::TopLeft).unwrap();
+        simulation.update(Message::SetRule(bosco)).unwrap();
+        assert!(range_too_large(simulation.resize(5, 4, Anchor::TopLeft)));
+        assert_eq!((simulation.width(), simulation.height()), (5, 5));
+    }
+
+    /// A 5 by 5 plane running `rule` with the cells of `cells` set to their states.
+    fn generations(
+        rule: &str,
+        cells: &[(usize, usize, usize)],
+    ) -> Simulation {
+        let mut simulation = Simulation::builder()
+            .width(5)
+            .height(5)
+            .rule(rule.parse().unwrap())
+            .topology(Topology::Plane)
+            .cycle_window(0)
+            .build()
+            .unwrap();
+        for &(x, y, state) in cells {
+            simulation.set_cell(x, y, state).unwrap();
+        }
+        simulation
+    }
+
+    fn state_at(
+        simulation: &Simulation,
+        x: usize,
+        y: usize,
+    ) -> usize {
+        simulation.cellule_at(x, y).unwrap().state()
+    }
+
+    #[test]
+    fn generations_rules_parse() {
+        let brians_brain: Rule = "B2/S/C3".parse().unwrap();
+        assert_eq!(brians_brain.states(), 3);
+        assert!(brians_brain.born(2) && !brians_brain.survives(2));
+        for rule in &["B2/S/G3", "b2/s/c3", "/2/3", "S/B2/C3"] {
+            assert_eq!(rule.parse::<Rule>().unwrap(), brians_brain, "{}", rule);
+        }
+        assert_eq!("345/2/4".parse::<Rule>().unwrap(), "B2/S345/C4".parse().unwrap());
+        assert_eq!("B3/S23/C2".parse::<Rule>().unwrap(), Rule::CONWAY);
+        for rule in &["B2/S/C1", "B2/S/C257", "B2/S/Cx", "B2/S/C3/G3"] {
+            assert!(rule.parse::<Rule>().is_err(), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn dying_cells_age_until_dead() {
+        let mut simulation = generations("B/S/C5", &[(2, 2, 1)]);
+        let cellule = simulation.cellule_at(2, 2).unwrap();
+        assert!(cellule.alive() && !cellule.dying());
+        assert_eq!(cellule.life_state(), LifeState::Alive);
+
+        for &state in &[2, 3, 4, 0] {
+            simulation.update(Message::Step).unwrap();
+            let cellule = simulation.cellule_at(2, 2).unwrap();
+            assert_eq!(cellule.state(), state);
+            assert_eq!(cellule.dying(), state > 1);
+            assert!(!cellule.alive());
+        }
+        assert_eq!(simulation.cellule_at(2, 2).unwrap().life_state(), LifeState::Dead);
+    }
+
+    #[test]
+    fn dying_cells_are_not_neighbors() {
+        // The cells between a live and a dying cell see only one live neighbor, too few for B2.
+        let mut simulation = generations("B2/S/C3", &[(1, 2, 1), (3, 2, 2)]);
+        simulation.update(Message::Step).unwrap();
+        assert_eq!(simulation.population(), 0);
+        assert_eq!(state_at(&simulation, 1, 2), 2);
+        assert_eq!(state_at(&simulation, 3, 2), 0);
+
+        // A dying cell with two live neighbors is not born again, while the dead cells beside it are.
+        let mut simulation = generations("B2/S/C3", &[(1, 2, 1), (2, 2, 2), (3, 2, 1)]);
+        simulation.update(Message::Step).unwrap();
+        assert_eq!(state_at(&simulation, 2, 2), 0);
+        assert_eq!(state_at(&simulation, 2, 1), 1);
+        assert_eq!(state_at(&simulation, 2, 3), 1);
+        assert_eq!(simulation.population(), 2);
+    }
+
+    /// A 5 by 5 plane running the colored `rule` with the cells of `cells` alive on their teams.
+    fn colored(
+        rule: &str,
+        cells: &[(usize, usize, usize)],
+    ) -> Simulation {
+        let mut simulation = generations(rule, &[]);
+        for &(x, y, team) in cells {
+            let idx = simulation.index(x, y).unwrap();
+            simulation.update(Message::ToggleTeamCellule { idx, team }).unwrap();
+        }
+        simulation
+    }
+
+    fn teams_at(
+        simulation: &Simulation,
+        cells: &[(usize, usize)],
+    ) -> Vec<usize> {
+        cells.iter().map(|&(x, y)| simulation.team_at(x, y).unwrap()).collect()
+    }
+
+    /// A horizontal blinker across the middle of the board, with its cells on `teams` from left to
+    /// right. After a step it stands upright, with newborn cells above and below its survivor.
+    fn blinker(
+        rule: &str,
+        teams: [usize; 3],
+    ) -> Simulation {
+        let mut simulation = colored(rule, &[(1, 2, teams[0]), (2, 2, teams[1]), (3, 2, teams[2])]);
+        simulation.update(Message::Step).unwrap();
+        simulation
+    }
+
+    const UPRIGHT: [(usize, usize); 3] = [(2, 1), (2, 2), (2, 3)];
+
+    #[test]
+    fn newborns_join_the_majority_team() {
+        let simulation = blinker("Immigration", [1, 1, 0]);
+        assert_eq!(teams_at(&simulation, &UPRIGHT), [1, 1, 1]);
+        assert_eq!(simulation.team_populations(), [0, 3]);
+
+        let simulation = blinker("Immigration", [0, 1, 0]);
+        assert_eq!(teams_at(&simulation, &UPRIGHT), [0, 1, 0]);
+        assert_eq!(simulation.team_populations(), [2, 1]);
+
+        let simulation = blinker("QuadLife", [3, 2, 3]);
+        assert_eq!(teams_at(&simulation, &UPRIGHT), [3, 2, 3]);
+        assert_eq!(simulation.team_populations(), [0, 0, 1, 2]);
+
+        // Dead cells and those off the board have no team.
+        assert_eq!(simulation.team_at(1, 2), Some(0));
+        assert_eq!(simulation.team_at(5, 2), None);
+    }
+
+    #[test]
+    fn newborns_without_a_majority_join_a_missing_team() {
+        // Three parents on three different teams leave team 3 as the first one none of them are on.
+        let simulation = blinker("QuadLife", [0, 1, 2]);
+        assert_eq!(teams_at(&simulation, &UPRIGHT), [3, 1, 3]);
+        assert_eq!(simulation.team_populations(), [0, 1, 0, 2]);
+
+        let simulation = blinker("QuadLife", [2, 0, 3]);
+        assert_eq!(teams_at(&simulation, &UPRIGHT), [1, 0, 1]);
+
+        // Six parents split evenly between both teams leave no team missing, so the first one wins.
+        for &(top, bottom) in &[(1, 0), (0, 1)] {
+            let mut simulation = colored(
+                "B36/S23/T2",
+                &[(1, 1, top), (2, 1, top), (3, 1, top), (1, 3, bottom), (2, 3, bottom), (3, 3, bottom)],
+            );
+            simulation.update(Message::Step).unwrap();
+            assert_eq!(simulation.team_at(2, 2), Some(0));
+            assert_eq!(simulation.team_at(2, 0), Some(top));
+            assert_eq!(simulation.team_at(2, 4), Some(bottom));
+        }
+    }
+
+    #[test]
+    fn cells_join_only_teams_of_the_rule() {
+        let invalid_team = |result: Result<(), Error>, teams| {
+            matches!(result, Err(Error::InvalidTeam { team: 2, teams: rule_teams }) if rule_teams == teams)
+        };
+
+        let mut simulation = colored("Immigration", &[(0, 0, 1)]);
+        assert!(invalid_team(simulation.update(Message::ToggleTeamCellule { idx: 1, team: 2 }), 2));
+        assert_eq!(simulation.team_populations(), [0, 1]);
+
+        let mut simulation = colored("QuadLife", &[(0, 0, 3)]);
+        assert!(simulation.update(Message::ToggleTeamCellule { idx: 1, team: 2 }).is_ok());
+        assert_eq!(simulation.team_populations(), [0, 0, 1, 1]);
+
+        let mut simulation = generations("B3/S23", &[(0, 0, 1), (4, 4, 1)]);
+        assert!(invalid_team(simulation.update(Message::ToggleTeamCellule { idx: 1, team: 2 }), 1));
+        assert_eq!(simulation.team_populations(), [2]);
+    }
+
+    #[test]
+    fn toggle_cellule_rejects_cells_off_the_board() {
+        let mut simulation = simulation();
+        for &idx in &[24, 25, usize::MAX] {
+            assert!(out_of_bounds(simulation.toggle_cellule(idx, 0)));
+            assert!(out_of_bounds(simulation.update(Message::ToggleCellule(idx))));
+        }
+        assert_eq!(simulation.population(), 0);
+
+        simulation.update(Message::ToggleCellule(23)).unwrap();
+        assert!(simulation.cellule_at(5, 3).unwrap().alive());
+    }
+}
@@ -0,0 +1,143 @@
+//! Reading and writing of the [Life 1.06] coordinate list format.
+//!
+//! [Life 1.06]: https://conwaylife.com/wiki/Life_1.06
+
+use crate::{
+    Error,
+    Pattern,
+};
+use std::{
+    convert::TryFrom,
+    fmt::Write,
+};
+
+const FORMAT: &str = "Life 1.06";
+pub(crate) const HEADER: &str = "#Life 1.06";
+
+impl Pattern {
+    /// Parses a pattern in Life 1.06 format.
+    ///
+    /// The file must start with the `#Life 1.06` header, followed by one `x y` pair per live cell.
+    /// Coordinates may be negative; the pattern is translated so that its top left live cell sits on
+    /// the first row and column. Any further `#` lines become [`Pattern::comments`].
+    pub fn from_life106(life: &str) -> Result<Self, Error> {
+        let parse_error = |line: usize, column: usize, reason: String| {
+            Error::Parse {
+                format: FORMAT,
+                line,
+                column,
+                reason,
+            }
+        };
+
+        let mut lines = life.lines().enumerate();
+        match lines.next() {
+            Some((_, header)) if header.trim_end() == HEADER => {}
+            _ => return Err(parse_error(1, 1, format!("expected a `{}` header", HEADER))),
+        }
+
+        let mut comments = Vec::new();
+        let mut coords = Vec::new();
+        for (idx, line) in lines {
+            let lineno = idx + 1;
+            if let Some(comment) = line.trim_start().strip_prefix('#') {
+                comments.push(comment.to_string());
+                continue;
+            }
+
+            let fields: Vec<_> = line
+                .split_whitespace()
+                .map(|field| {
+                    let offset = field.as_ptr() as usize - line.as_ptr() as usize;
+                    (field, line[..offset].chars().count() + 1)
+                })
+                .collect();
+            let coord = |&(field, column): &(&str, usize)| {
+                field
+                    .parse::<i64>()
+                    .map_err(|_| parse_error(lineno, column, format!("invalid coordinate {:?}", field)))
+            };
+
+            match fields.as_slice() {
+                [] => {}
+                [x, y] => coords.push((coord(x)?, coord(y)?)),
+                [_] => {
+                    return Err(parse_error(
+                        lineno,
+                        line.trim_end().chars().count() + 1,
+                        "expected a y coordinate".to_string(),
+                    ));
+                }
+                [_, _, (_, column), ..] => {
+                    return Err(parse_error(lineno, *column, "expected exactly two coordinates".to_string()));
+                }
+            }
+        }
+
+        let min_x = coords.iter().map(|&(x, _)| x).min().unwrap_or(0);
+        let min_y = coords.iter().map(|&(_, y)| y).min().unwrap_or(0);
+        let span = |value: i64, min: i64| value.checked_sub(min).and_then(|span| usize::try_from(span).ok());
+        let mut pattern = coords
+            .into_iter()
+            .map(|(x, y)| span(x, min_x).zip(span(y, min_y)))
+            .collect::<Option<Vec<_>>>()
+            .and_then(Pattern::try_from_alive_cells)
+            .ok_or_else(|| parse_error(1, 1, format!("live cells span more than {} cells", Pattern::MAX_CELLS)))?;
+        for comment in comments {
+            pattern.push_comment(comment);
+        }
+
+        Ok(pattern)
+    }
+
+    /// Encodes the live cells of the pattern as a Life 1.06 coordinate list. The format only records
+    /// live cells, so empty margins, comments and the rule do not survive the round trip.
+    pub fn to_life106(&self) -> String {
+        let mut life = String::new();
+        let _ = writeln!(life, "{}", HEADER);
+        for (x, y) in self.alive_cells() {
+            let _ = writeln!(life, "{} {}", x, y);
+        }
+        life
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn live_cells_round_trip() {
+        let pattern = Pattern::from_alive_cells(vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+
+        let life = pattern.to_life106();
+        assert_eq!(life, "#Life 1.06\n1 0\n2 1\n0 2\n1 2\n2 2\n");
+        assert_eq!(Pattern::from_life106(&life).unwrap(), pattern);
+    }
+
+    #[test]
+    fn negative_coordinates_are_moved_onto_the_pattern() {
+        let pattern = Pattern::from_life106("#Life 1.06\n#D a glider\n0 -1\n1 0\n-1 1\n0 1\n1 1\n").unwrap();
+
+        assert_eq!(pattern, {
+            let mut glider = Pattern::from_alive_cells(vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+            glider.push_comment("D a glider");
+            glider
+        });
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        let position = |life: &str| {
+            match Pattern::from_life106(life) {
+                Err(Error::Parse { line, column, .. }) => Some((line, column)),
+                _ => None,
+            }
+        };
+
+        assert_eq!(position("#Life 1.05\n0 0\n"), Some((1, 1)));
+        assert_eq!(position("#Life 1.06\n0 0\n  1 y\n"), Some((3, 5)));
+        assert_eq!(position("#Life 1.06\n0\n"), Some((2, 2)));
+        assert_eq!(position("#Life 1.06\n0 0 0\n"), Some((2, 5)));
+    }
+}
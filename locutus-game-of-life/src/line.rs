@@ -0,0 +1,546 @@
+//! One dimensional automata: Wolfram's [elementary] rules and [totalistic] rules with more colors,
+//! shown as a space-time diagram in which each tick adds a row below the last one.
+//!
+//! [elementary]: https://mathworld.wolfram.com/ElementaryCellularAutomaton.html
+//! [totalistic]: https://mathworld.wolfram.com/TotalisticCellularAutomaton.html
+
+use crate::{
+    check_dimensions,
+    deps::{
+        rand::{
+            thread_rng,
+            Rng,
+        },
+        serde,
+        tracing::info,
+    },
+    Control,
+    Error,
+    Soup,
+    State,
+    Symmetry,
+    Topology,
+};
+use std::{
+    fmt,
+    str::FromStr,
+};
+
+/// The rule of a [`LineAutomaton`].
+///
+/// Rules are written `W30` or just `30` for an elementary rule and `K3,R1,T777` for a totalistic
+/// one, where `K` is the number of colors, `R` the range and `T` the code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum LineRule {
+    /// One of Wolfram's 256 two color rules over a cell and its two neighbors. Bit `4l + 2c + r`
+    /// of the code is the next color of a cell of color `c` between cells of colors `l` and `r`.
+    Elementary(u8),
+    /// A rule over `colors` colors where the next color of a cell depends only on the sum of the
+    /// colors within `range` cells of it, itself included. Digit `s` of the code written in base
+    /// `colors` is the next color for a sum of `s`.
+    Totalistic { colors: u8, range: u8, code: u64 },
+}
+
+impl Default for LineRule {
+    /// Rule 30.
+    fn default() -> Self {
+        LineRule::Elementary(30)
+    }
+}
+
+impl LineRule {
+    /// The most colors a totalistic rule may have.
+    pub const MAX_COLORS: u8 = 16;
+    /// The furthest a totalistic rule may look to either side of a cell.
+    pub const MAX_RANGE: u8 = 8;
+
+    /// A totalistic rule, checking that there are between 2 and [`Self::MAX_COLORS`] colors, that
+    /// the range is between 1 and [`Self::MAX_RANGE`], and that the code has no more digits than
+    /// there are sums. Combinations whose largest code does not fit in 64 bits are rejected.
+    pub fn totalistic(
+        colors: u8,
+        range: u8,
+        code: u64,
+    ) -> Result<Self, Error> {
+        let rule = LineRule::Totalistic { colors, range, code };
+        let invalid = |reason| {
+            Err(Error::ParseRule {
+                rule: rule.to_string(),
+                reason,
+            })
+        };
+
+        if !(2..=Self::MAX_COLORS).contains(&colors) {
+            return invalid("totalistic rules need between 2 and 16 colors");
+        }
+        if !(1..=Self::MAX_RANGE).contains(&range) {
+            return invalid("totalistic ranges go from 1 to 8");
+        }
+        match u64::from(colors).checked_pow(rule.sums() as u32) {
+            None => invalid("too many colors for the range to fit a 64-bit code"),
+            Some(limit) if code >= limit => invalid("code has more digits than there are sums"),
+            Some(_) => Ok(rule),
+        }
+    }
+
+    pub fn colors(&self) -> u8 {
+        match self {
+            LineRule::Elementary(_) => 2,
+            LineRule::Totalistic { colors, .. } => *colors,
+        }
+    }
+
+    /// How many cells to either side of a cell its next color depends on.
+    pub fn range(&self) -> usize {
+        match self {
+            LineRule::Elementary(_) => 1,
+            LineRule::Totalistic { range, .. } => *range as usize,
+        }
+    }
+
+    /// How many different sums a totalistic rule tells apart.
+    fn sums(&self) -> usize {
+        (2 * self.range() + 1) * (self.colors() as usize - 1) + 1
+    }
+
+    /// The next color of the cell in the middle of `window`, which holds the `2 * range() + 1`
+    /// cells around it from left to right.
+    pub fn next(
+        &self,
+        window: &[u8],
+    ) -> u8 {
+        match *self {
+            LineRule::Elementary(code) => {
+                let pattern = window.iter().fold(0, |pattern, &color| pattern << 1 | (color & 1));
+                (code >> pattern) & 1
+            }
+            LineRule::Totalistic { colors, code, .. } => {
+                let sum = window.iter().map(|&color| color as u32).sum::<u32>();
+                (code / u64::from(colors).pow(sum) % u64::from(colors)) as u8
+            }
+        }
+    }
+}
+
+impl fmt::Display for LineRule {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        match self {
+            LineRule::Elementary(code) => write!(f, "W{}", code),
+            LineRule::Totalistic { colors, range, code } => write!(f, "K{},R{},T{}", colors, range, code),
+        }
+    }
+}
+
+impl FromStr for LineRule {
+    type Err = Error;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason| {
+            Error::ParseRule {
+                rule: rule.to_string(),
+                reason,
+            }
+        };
+        let trimmed = rule.trim();
+
+        let elementary = trimmed.strip_prefix(|c| c == 'W' || c == 'w').unwrap_or(trimmed);
+        if elementary.starts_with(|c: char| c.is_ascii_digit()) {
+            return elementary
+                .parse::<u8>()
+                .map(LineRule::Elementary)
+                .map_err(|_| invalid("expected an elementary rule from W0 to W255"));
+        }
+
+        let (mut colors, mut range, mut code) = (None, 1, None);
+        for part in trimmed.split(',').map(str::trim) {
+            let mut chars = part.chars();
+            let key = chars.next().map(|key| key.to_ascii_uppercase());
+            let value = chars.as_str();
+            match key {
+                Some('K') => colors = Some(value.parse().map_err(|_| invalid("invalid number of colors"))?),
+                Some('R') => range = value.parse().map_err(|_| invalid("invalid range"))?,
+                Some('T') => code = Some(value.parse().map_err(|_| invalid("invalid totalistic code"))?),
+                _ => return Err(invalid("expected K, R and T parts")),
+            }
+        }
+
+        match (colors, code) {
+            (Some(colors), Some(code)) => {
+                LineRule::totalistic(colors, range, code).map_err(|err| {
+                    match err {
+                        Error::ParseRule { reason, .. } => invalid(reason),
+                        err => err,
+                    }
+                })
+            }
+            _ => Err(invalid("totalistic rules need both K and T parts")),
+        }
+    }
+}
+
+/// The messages a [`LineAutomaton`] is driven with.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum LineMessage {
+    Control(Control),
+    Random,
+    /// Replaces the history with a row from a reproducible [`Soup`].
+    RandomSeeded {
+        seed:     u64,
+        density:  f64,
+        symmetry: Symmetry,
+    },
+    /// Moves the cell at a row major index of the current row on to the next color.
+    ToggleCellule(usize),
+    SetTopology(Topology),
+}
+
+impl From<Control> for LineMessage {
+    fn from(control: Control) -> Self {
+        LineMessage::Control(control)
+    }
+}
+
+/// A row of cells run under a [`LineRule`], along with the rows it was in at earlier ticks.
+///
+/// The board is a frame of `height` rows. The first row is the starting one and each tick fills
+/// the row below it, until the frame is full and scrolls up to make room for the next row, so the
+/// frame always shows the latest `height` ticks. Only the current row, the last one filled, takes
+/// part in the next tick. The left and right ends of the row meet as on a [`Topology::Torus`] by
+/// default, and the other topologies treat the row as the only row of their board.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LineAutomaton {
+    state:    State,
+    ticks:    usize,
+    width:    usize,
+    height:   usize,
+    rule:     LineRule,
+    topology: Topology,
+    /// How many rows of the frame are filled.
+    rows:     usize,
+    cells:    Vec<u8>,
+}
+
+impl LineAutomaton {
+    /// A frame of `width` columns by `height` rows, starting from a single cell of color 1 in the
+    /// middle of the first row.
+    pub fn new(
+        width: usize,
+        height: usize,
+        rule: LineRule,
+    ) -> Result<Self, Error> {
+        let len = check_dimensions(width, height)?;
+
+        let mut automaton = Self {
+            state: State::default(),
+            ticks: 0,
+            width,
+            height,
+            rule,
+            topology: Topology::Torus,
+            rows: 1,
+            cells: vec![0; len],
+        };
+        automaton.restart();
+        Ok(automaton)
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn ticks(&self) -> usize {
+        self.ticks
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn rule(&self) -> LineRule {
+        self.rule
+    }
+
+    /// Switches to `rule` from the next tick on. Colors the rule does not have are wrapped into
+    /// its range.
+    pub fn set_rule(
+        &mut self,
+        rule: LineRule,
+    ) {
+        let colors = rule.colors();
+        self.cells.iter_mut().for_each(|color| *color %= colors);
+        self.rule = rule;
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(
+        &mut self,
+        topology: Topology,
+    ) {
+        self.topology = topology;
+    }
+
+    /// The color of every cell of the frame in row major order, `width()` cells to a row. Rows not
+    /// reached yet are all 0.
+    pub fn cells(&self) -> impl ExactSizeIterator<Item = u8> + '_ {
+        self.cells.iter().copied()
+    }
+
+    /// The current row.
+    pub fn row(&self) -> &[u8] {
+        let start = (self.rows - 1) * self.width;
+        &self.cells[start..start + self.width]
+    }
+
+    fn row_mut(&mut self) -> &mut [u8] {
+        let start = (self.rows - 1) * self.width;
+        &mut self.cells[start..start + self.width]
+    }
+
+    /// Sets cell `x` of the current row to `color`, wrapped into the colors of the rule.
+    pub fn set(
+        &mut self,
+        x: usize,
+        color: u8,
+    ) {
+        let colors = self.rule.colors();
+        self.row_mut()[x] = color % colors;
+    }
+
+    /// How many cells of the current row are not color 0.
+    pub fn population(&self) -> usize {
+        self.row().iter().filter(|&&color| color != 0).count()
+    }
+
+    /// Empties the frame and makes `row` the first row, or an empty row when there is none.
+    fn replace_row(
+        &mut self,
+        row: Option<&[u8]>,
+    ) {
+        self.cells.iter_mut().for_each(|color| *color = 0);
+        self.rows = 1;
+        if let Some(row) = row {
+            self.row_mut().copy_from_slice(row);
+        }
+    }
+
+    /// Goes back to a single cell of color 1 in the middle of an otherwise empty frame.
+    pub fn restart(&mut self) {
+        self.replace_row(None);
+        let middle = self.width / 2;
+        self.set(middle, 1);
+    }
+
+    /// Advances the row one tick, scrolling the frame up when it is full.
+    pub fn step(&mut self) {
+        let range = self.rule.range() as isize;
+        let row = self.row();
+        let mut window = Vec::with_capacity(2 * range as usize + 1);
+        let next = (0..self.width)
+            .map(|x| {
+                window.clear();
+                window.extend((-range..=range).map(|dx| {
+                    self.topology
+                        .resolve(x as isize + dx, 0, self.width, 1)
+                        .map(|(x, _)| row[x])
+                        .unwrap_or(0)
+                }));
+                self.rule.next(&window)
+            })
+            .collect::<Vec<_>>();
+
+        if self.rows == self.height {
+            self.cells.rotate_left(self.width);
+        } else {
+            self.rows += 1;
+        }
+        self.row_mut().copy_from_slice(&next);
+        self.ticks += 1;
+    }
+
+    /// Drives the automaton with a [`LineMessage`]. Toggling a cell of the current row moves it on
+    /// to the next color, while cells of earlier rows cannot be changed. Random fills replace the
+    /// history with a single random row, in which a seeded soup colors its live cells 1. Resetting
+    /// goes back to the starting row.
+    pub fn update(
+        &mut self,
+        msg: LineMessage,
+    ) -> Result<(), Error> {
+        use State::*;
+        if self.state.ended() {
+            return Err(Error::GameOver);
+        }
+
+        match msg {
+            LineMessage::Random => {
+                let colors = self.rule.colors();
+                let mut rng = thread_rng();
+                let row = (0..self.width).map(|_| rng.gen_range(0, colors)).collect::<Vec<_>>();
+                self.replace_row(Some(&row));
+                info!("Random");
+            }
+            LineMessage::RandomSeeded {
+                seed,
+                density,
+                symmetry,
+            } => {
+                let soup = Soup {
+                    symmetry,
+                    ..Soup::new(seed, density)
+                };
+                let pattern = soup.generate(self.width, 1)?;
+                let row = (0..self.width).map(|x| pattern.get(x, 0) as u8).collect::<Vec<_>>();
+                self.replace_row(Some(&row));
+                info!("RandomSeeded {} {} {}", seed, density, symmetry);
+            }
+            LineMessage::Control(Control::Start) => {
+                self.state.try_transition(Running)?;
+                info!("Start");
+            }
+            LineMessage::Control(Control::Step) => {
+                self.step();
+            }
+            LineMessage::Control(Control::Reset) => {
+                self.restart();
+                info!("Reset");
+            }
+            LineMessage::Control(Control::Stop) => {
+                self.state.try_transition(Pausing)?;
+                info!("Stop");
+            }
+            LineMessage::ToggleCellule(idx) => {
+                let start = (self.rows - 1) * self.width;
+                if (start..start + self.width).contains(&idx) {
+                    let colors = self.rule.colors();
+                    self.cells[idx] = (self.cells[idx] + 1) % colors;
+                }
+            }
+            LineMessage::SetTopology(topology) => {
+                self.topology = topology;
+                info!("SetTopology {}", topology);
+            }
+            LineMessage::Control(Control::Tick) => {
+                if self.state.run() {
+                    self.step();
+                }
+            }
+            LineMessage::Control(Control::End) => {
+                self.state.try_transition(Ended)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The frame as one string per row, `.` for color 0 and the color digit otherwise.
+    fn frame(automaton: &LineAutomaton) -> Vec<String> {
+        let cells = automaton.cells().collect::<Vec<_>>();
+        cells
+            .chunks(automaton.width())
+            .map(|row| {
+                row.iter()
+                    .map(|&color| {
+                        match color {
+                            0 => '.',
+                            color => std::char::from_digit(color as u32, 16).unwrap_or('?'),
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn run(
+        rule: LineRule,
+        rows: &[&str],
+    ) {
+        let mut automaton = LineAutomaton::new(rows[0].len(), rows.len(), rule).unwrap();
+        for _ in 1..rows.len() {
+            automaton.step();
+        }
+        assert_eq!(frame(&automaton), rows, "{}", rule);
+    }
+
+    #[test]
+    fn rule_30_from_single_cell() {
+        run(LineRule::Elementary(30), &[
+            ".......1.......",
+            "......111......",
+            ".....11..1.....",
+            "....11.1111....",
+            "...11..1...1...",
+            "..11.1111.111..",
+            ".11..1....1..1.",
+            "11.1111..111111",
+        ]);
+    }
+
+    #[test]
+    fn rule_110_from_single_cell() {
+        run(LineRule::Elementary(110), &[
+            ".......1.......",
+            "......11.......",
+            ".....111.......",
+            "....11.1.......",
+            "...11111.......",
+            "..11...1.......",
+            ".111..11.......",
+            "11.1.111.......",
+        ]);
+    }
+
+    #[test]
+    fn totalistic_code_is_read_in_base_colors() {
+        let rule = "K3,R1,T777".parse::<LineRule>().unwrap();
+        assert_eq!(rule, LineRule::totalistic(3, 1, 777).unwrap());
+        assert_eq!(rule.to_string(), "K3,R1,T777");
+        assert_eq!((rule.colors(), rule.range()), (3, 1));
+
+        // 777 is 1001210 in base 3, so sums 0 to 6 lead to colors 0, 1, 2, 1, 0, 0 and 1.
+        let windows: [[u8; 3]; 7] = [[0, 0, 0], [1, 0, 0], [0, 1, 1], [1, 1, 1], [1, 2, 1], [2, 1, 2], [2, 2, 2]];
+        let next = windows.iter().map(|window| rule.next(window)).collect::<Vec<_>>();
+        assert_eq!(next, [0, 1, 2, 1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn totalistic_rules_are_checked() {
+        assert!(LineRule::totalistic(3, 1, 2186).is_ok());
+        assert!(LineRule::totalistic(3, 1, 2187).is_err());
+        assert!(LineRule::totalistic(1, 1, 0).is_err());
+        assert!(LineRule::totalistic(17, 1, 0).is_err());
+        assert!(LineRule::totalistic(2, 0, 0).is_err());
+        assert!(LineRule::totalistic(16, 8, 0).is_err());
+        assert_eq!("w30".parse::<LineRule>().unwrap(), LineRule::Elementary(30));
+        assert!("256".parse::<LineRule>().is_err());
+        assert!("K3,R1".parse::<LineRule>().is_err());
+    }
+
+    #[test]
+    fn history_keeps_fixed_height() {
+        let mut tall = LineAutomaton::new(15, 8, LineRule::Elementary(30)).unwrap();
+        let mut short = LineAutomaton::new(15, 3, LineRule::Elementary(30)).unwrap();
+        for _ in 0..7 {
+            tall.step();
+            short.step();
+            assert_eq!(short.cells().len(), 15 * 3);
+        }
+
+        assert_eq!(short.ticks(), 7);
+        assert_eq!(short.row(), tall.row());
+        assert_eq!(frame(&short), frame(&tall)[5..]);
+    }
+}
@@ -0,0 +1,310 @@
+use crate::{
+    deps::serde,
+    Bounds,
+    Grid,
+};
+
+/// The shape of the cells around a cell that a [`crate::Rule`] counts, out to some range.
+///
+/// Shapes are named by the letter that follows `N` in a Larger than Life rulestring. Whether the
+/// cell itself is counted is up to the rule, so no shape contains its center. Most shapes are laid
+/// over a [`Grid::Square`] board, while the hexagonal and triangular ones make the board a
+/// [`Grid::Hexagonal`] or [`Grid::Triangular`] one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Neighborhood {
+    /// The square of cells within the range in both directions, `NM`.
+    #[default]
+    Moore,
+    /// The diamond of cells within the range in Manhattan distance, `NN`.
+    VonNeumann,
+    /// The disc of cells whose centers lie within the range plus one half, `NC`.
+    Circular,
+    /// The row and column through the center, `N+`.
+    Cross,
+    /// The two diagonals through the center, `NX`.
+    Saltire,
+    /// The row, column and both diagonals through the center, `N*`.
+    Star,
+    /// The two rows and two columns next to the center, `N#`.
+    Hash,
+    /// The hexagon of cells within the range in steps between neighboring hexagons, `NH`.
+    Hexagonal,
+    /// The twelve triangles sharing a side or a corner with the center, `NL`. Only range 1 exists.
+    Triangular,
+}
+
+impl Neighborhood {
+    pub const ALL: [Neighborhood; 9] = [
+        Neighborhood::Moore,
+        Neighborhood::VonNeumann,
+        Neighborhood::Circular,
+        Neighborhood::Cross,
+        Neighborhood::Saltire,
+        Neighborhood::Star,
+        Neighborhood::Hash,
+        Neighborhood::Hexagonal,
+        Neighborhood::Triangular,
+    ];
+
+    /// The letter naming the shape in a rulestring.
+    pub fn code(&self) -> char {
+        match self {
+            Neighborhood::Moore => 'M',
+            Neighborhood::VonNeumann => 'N',
+            Neighborhood::Circular => 'C',
+            Neighborhood::Cross => '+',
+            Neighborhood::Saltire => 'X',
+            Neighborhood::Star => '*',
+            Neighborhood::Hash => '#',
+            Neighborhood::Hexagonal => 'H',
+            Neighborhood::Triangular => 'L',
+        }
+    }
+
+    /// The tiling the shape is laid over.
+    pub fn grid(&self) -> Grid {
+        match self {
+            Neighborhood::Hexagonal => Grid::Hexagonal,
+            Neighborhood::Triangular => Grid::Triangular,
+            _ => Grid::Square,
+        }
+    }
+
+    /// How many columns or rows away from the center the shape reaches at `range`.
+    pub(crate) fn reach(
+        &self,
+        range: usize,
+    ) -> usize {
+        match self {
+            Neighborhood::Triangular => 2 * range,
+            _ => range,
+        }
+    }
+
+    pub fn from_code(code: char) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|neighborhood| neighborhood.code() == code.to_ascii_uppercase())
+    }
+
+    /// Whether the cell at offset `(dx, dy)` from the center is a neighbor at `range`. Triangular
+    /// offsets are those of a center pointing up, and are mirrored top to bottom for one pointing
+    /// down.
+    pub fn contains(
+        &self,
+        dx: isize,
+        dy: isize,
+        range: usize,
+    ) -> bool {
+        let (x, y) = (dx.unsigned_abs(), dy.unsigned_abs());
+        if (x, y) == (0, 0) || y > range {
+            return false;
+        }
+        match self {
+            Neighborhood::Moore => x <= range,
+            Neighborhood::VonNeumann => x + y <= range,
+            Neighborhood::Circular => x * x + y * y <= range * range + range,
+            Neighborhood::Cross => x <= range && (x == 0 || y == 0),
+            Neighborhood::Saltire => x == y,
+            Neighborhood::Star => x <= range && (x == 0 || y == 0 || x == y),
+            Neighborhood::Hash => x <= range && (x == 1 || y == 1),
+            Neighborhood::Hexagonal if (dx < 0) == (dy < 0) || dx == 0 || dy == 0 => x.max(y) <= range,
+            Neighborhood::Hexagonal => x + y <= range,
+            Neighborhood::Triangular => range == 1 && x <= if dy < 0 { 1 } else { 2 },
+        }
+    }
+
+    /// How many neighbors a cell has at `range`.
+    pub fn size(
+        &self,
+        range: usize,
+    ) -> usize {
+        let reach = self.reach(range) as isize;
+        (-reach..=reach)
+            .flat_map(|dy| (-reach..=reach).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| self.contains(dx, dy, range))
+            .count()
+    }
+
+    /// Splits the shape at `range`, along with its center, into rectangles of offsets, so that the
+    /// cells in it can be counted with one summed-area table lookup per rectangle.
+    ///
+    /// Each row of the shape is cut into runs of neighbors, and runs that repeat on the rows below
+    /// are merged into one rectangle. The Moore neighborhood is a single rectangle while the others
+    /// take a few per row at most.
+    pub(crate) fn rectangles(
+        &self,
+        range: usize,
+    ) -> Vec<Bounds> {
+        let reach = self.reach(range) as i64;
+        let runs = |dy: i64| {
+            let member = |dx: i64| (dx, dy) == (0, 0) || self.contains(dx as isize, dy as isize, range);
+            let mut runs = Vec::new();
+            let mut dx = -reach;
+            while dx <= reach {
+                if member(dx) {
+                    let start = dx;
+                    while dx < reach && member(dx + 1) {
+                        dx += 1;
+                    }
+                    runs.push((start, dx));
+                }
+                dx += 1;
+            }
+            runs
+        };
+
+        let mut rectangles: Vec<Bounds> = Vec::new();
+        let mut open = Vec::new();
+        for dy in -(range as i64)..=range as i64 {
+            let row = runs(dy);
+            if row != open {
+                open = row;
+                rectangles.extend(open.iter().map(|&(min_x, max_x)| Bounds::new(min_x, dy, max_x, dy)));
+            } else {
+                let count = rectangles.len();
+                rectangles[count - open.len()..].iter_mut().for_each(|rectangle| rectangle.max_y = dy);
+            }
+        }
+        rectangles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Message,
+        Rule,
+        Simulation,
+        Topology,
+    };
+    use std::collections::BTreeSet;
+
+    type Cells = BTreeSet<(usize, usize)>;
+
+    /// A `size` by `size` board running `rule` over `topology` with the cells of `alive` alive.
+    fn board(
+        rule: &str,
+        topology: Topology,
+        size: usize,
+        alive: &Cells,
+    ) -> Simulation {
+        let rule: Rule = rule.parse().unwrap();
+        let mut simulation = Simulation::builder()
+            .width(size)
+            .height(size)
+            .rule(rule)
+            .topology(topology)
+            .cycle_window(0)
+            .build()
+            .unwrap();
+        for &(x, y) in alive {
+            simulation.set_cell(x, y, 1).unwrap();
+        }
+        simulation
+    }
+
+    fn step(simulation: &mut Simulation) -> Cells {
+        simulation.update(Message::Step).unwrap();
+        simulation.to_pattern().alive_cells().collect()
+    }
+
+    /// The cells at `offsets` from `(x, y)`.
+    fn around(
+        x: usize,
+        y: usize,
+        offsets: &[(isize, isize)],
+    ) -> Cells {
+        offsets
+            .iter()
+            .map(|&(dx, dy)| ((x as isize + dx) as usize, (y as isize + dy) as usize))
+            .collect()
+    }
+
+    #[test]
+    fn neighborhoods_have_the_expected_sizes() {
+        assert_eq!(Neighborhood::Moore.size(1), 8);
+        assert_eq!(Neighborhood::VonNeumann.size(1), 4);
+        assert_eq!(Neighborhood::Hexagonal.size(1), 6);
+        assert_eq!(Neighborhood::Triangular.size(1), 12);
+        assert_eq!(Neighborhood::Moore.size(2), 24);
+        assert_eq!(Neighborhood::Hexagonal.size(2), 18);
+    }
+
+    #[test]
+    fn hexagonal_cells_have_six_neighbors() {
+        // Under B1 everything next to a lone cell is born, which shows which cells it neighbors.
+        let lone: Cells = [(3, 3)].iter().copied().collect();
+        let mut simulation = board("B1/SH", Topology::Plane, 7, &lone);
+        let expected = around(3, 3, &[(-1, -1), (0, -1), (-1, 0), (1, 0), (0, 1), (1, 1)]);
+        assert_eq!(step(&mut simulation), expected);
+
+        // A cell surrounded on all six sides is born under B6 and nothing else is.
+        let full: Cells = (0..6).flat_map(|y| (0..6).map(move |x| (x, y))).filter(|&cell| cell != (2, 3)).collect();
+        assert_eq!(step(&mut board("B6/SH", Topology::Torus, 6, &full)), around(2, 3, &[(0, 0)]));
+    }
+
+    #[test]
+    fn triangular_neighbors_depend_on_which_way_a_cell_points() {
+        assert!(Grid::points_up(4, 4) && Grid::points_up(3, 5) && !Grid::points_up(4, 5) && !Grid::points_up(0, 1));
+
+        // Pointing up, a triangle has three neighbors in the row above, four beside it and five in the
+        // row below, and pointing down the other way round.
+        let up = around(4, 4, &[
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (-2, 0),
+            (-1, 0),
+            (1, 0),
+            (2, 0),
+            (-2, 1),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+            (2, 1),
+        ]);
+        let down = around(5, 4, &[
+            (-2, -1),
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (2, -1),
+            (-2, 0),
+            (-1, 0),
+            (1, 0),
+            (2, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+        ]);
+        for &((x, y), ref expected) in &[((4, 4), up), ((5, 4), down)] {
+            let lone: Cells = [(x, y)].iter().copied().collect();
+            let mut simulation = board("B1/SL", Topology::Plane, 10, &lone);
+            assert_eq!(&step(&mut simulation), expected, "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn triangular_counts_past_nine_are_written_in_hexadecimal() {
+        let rule: Rule = "B4a/S2bcL".parse().unwrap();
+        assert!(rule.born(4) && rule.born(10) && !rule.born(11));
+        assert!(rule.survives(2) && rule.survives(11) && rule.survives(12) && !rule.survives(10));
+        assert_eq!(rule.to_string(), "B4a/S2bcL");
+        assert_eq!("B4A/S2BCL".parse::<Rule>().unwrap(), rule);
+        assert!("B3a/S23".parse::<Rule>().is_err());
+        assert!("B3d/SL".parse::<Rule>().is_err());
+
+        // On a full torus every cell has twelve live neighbors, so only the one dead cell is born
+        // under Bc, and nothing is under Bb.
+        for &(x, y) in &[(2, 2), (3, 2)] {
+            let full: Cells = (0..6).flat_map(|y| (0..6).map(move |x| (x, y))).filter(|&cell| cell != (x, y)).collect();
+            assert_eq!(step(&mut board("Bc/SL", Topology::Torus, 6, &full)), around(x, y, &[(0, 0)]));
+            assert!(step(&mut board("Bb/SL", Topology::Torus, 6, &full)).is_empty());
+            // Under Sc only the cells not next to the dead one survive.
+            assert_eq!(step(&mut board("B/ScL", Topology::Torus, 6, &full)).len(), 36 - 1 - 12);
+        }
+    }
+}
@@ -0,0 +1,496 @@
+use crate::{
+    deps::serde,
+    life106,
+    Bounds,
+    Error,
+    Rule,
+    Simulation,
+};
+use std::{
+    convert::TryFrom,
+    str::FromStr,
+};
+
+/// One of the eight ways of turning or mirroring a pattern that keep it on the square grid.
+/// Rotations are clockwise as seen on screen, with rows running down.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Transform {
+    #[default]
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Mirrored left to right.
+    FlipX,
+    /// Mirrored top to bottom.
+    FlipY,
+    /// Mirrored across the diagonal from the top left corner, swapping rows with columns.
+    FlipDiagonal,
+    /// Mirrored across the diagonal from the top right corner.
+    FlipAntiDiagonal,
+}
+
+impl Transform {
+    pub const ALL: [Transform; 8] = [
+        Transform::Identity,
+        Transform::Rotate90,
+        Transform::Rotate180,
+        Transform::Rotate270,
+        Transform::FlipX,
+        Transform::FlipY,
+        Transform::FlipDiagonal,
+        Transform::FlipAntiDiagonal,
+    ];
+
+    /// Whether the transform swaps rows with columns.
+    pub(crate) fn transposes(&self) -> bool {
+        matches!(
+            self,
+            Transform::Rotate90 | Transform::Rotate270 | Transform::FlipDiagonal | Transform::FlipAntiDiagonal
+        )
+    }
+
+    /// Where `(x, y)` of a `width` by `height` pattern ends up.
+    pub(crate) fn apply(
+        &self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> (usize, usize) {
+        let (far_x, far_y) = (width - 1 - x, height - 1 - y);
+        match self {
+            Transform::Identity => (x, y),
+            Transform::Rotate90 => (far_y, x),
+            Transform::Rotate180 => (far_x, far_y),
+            Transform::Rotate270 => (y, far_x),
+            Transform::FlipX => (far_x, y),
+            Transform::FlipY => (x, far_y),
+            Transform::FlipDiagonal => (y, x),
+            Transform::FlipAntiDiagonal => (far_y, far_x),
+        }
+    }
+}
+
+/// How the cells of a placed pattern combine with the board already under it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Blend {
+    /// Live pattern cells come alive and the rest of the board is left as it was.
+    #[default]
+    Or,
+    /// Live pattern cells toggle the cells under them.
+    Xor,
+    /// The whole rectangle of the pattern replaces the board under it, dead cells included.
+    Overwrite,
+}
+
+/// A rectangular block of cells detached from any running simulation, as read from or written to a
+/// pattern file.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Pattern {
+    width:    usize,
+    height:   usize,
+    cells:    Vec<bool>,
+    rule:     Option<Rule>,
+    comments: Vec<String>,
+}
+
+impl Pattern {
+    /// The most cells a pattern may span. Pattern files are untrusted input, so the readers reject
+    /// larger bounds rather than running out of memory.
+    pub const MAX_CELLS: usize = 1 << 28;
+
+    /// An all dead pattern of `width` columns by `height` rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern would span more than [`Pattern::MAX_CELLS`] cells. Use
+    /// [`Pattern::try_new`] for dimensions that come from untrusted input.
+    pub fn new(
+        width: usize,
+        height: usize,
+    ) -> Self {
+        Self::try_new(width, height)
+            .unwrap_or_else(|| panic!("a {}x{} pattern has more than {} cells", width, height, Self::MAX_CELLS))
+    }
+
+    /// An all dead pattern like [`Pattern::new`], or `None` when it would span more than
+    /// [`Pattern::MAX_CELLS`] cells.
+    pub fn try_new(
+        width: usize,
+        height: usize,
+    ) -> Option<Self> {
+        if !Self::fits(width, height) {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            cells: vec![false; width * height],
+            rule: None,
+            comments: Vec::new(),
+        })
+    }
+
+    /// An all dead pattern spanning `bounds`, failing with [`Error::PatternTooLarge`] when it would
+    /// have more than [`Pattern::MAX_CELLS`] cells.
+    pub(crate) fn for_bounds(bounds: Bounds) -> Result<Self, Error> {
+        let (width, height) = (bounds.width(), bounds.height());
+        usize::try_from(width)
+            .ok()
+            .zip(usize::try_from(height).ok())
+            .and_then(|(width, height)| Self::try_new(width, height))
+            .ok_or(Error::PatternTooLarge { width, height })
+    }
+
+    /// Builds the smallest pattern that contains every `(x, y)` live cell.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cells span more than [`Pattern::MAX_CELLS`] cells.
+    pub fn from_alive_cells<I>(cells: I) -> Self
+    where
+        I: IntoIterator<Item = (usize, usize)>,
+    {
+        Self::try_from_alive_cells(cells)
+            .unwrap_or_else(|| panic!("live cells span more than {} cells", Self::MAX_CELLS))
+    }
+
+    /// Builds the smallest pattern that contains every `(x, y)` live cell like
+    /// [`Pattern::from_alive_cells`], or `None` when they span more than [`Pattern::MAX_CELLS`] cells.
+    pub fn try_from_alive_cells<I>(cells: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = (usize, usize)>,
+    {
+        let cells: Vec<_> = cells.into_iter().collect();
+        let width = cells.iter().map(|&(x, _)| x.saturating_add(1)).max().unwrap_or(0);
+        let height = cells.iter().map(|&(_, y)| y.saturating_add(1)).max().unwrap_or(0);
+
+        let mut pattern = Self::try_new(width, height)?;
+        for (x, y) in cells {
+            pattern.set(x, y, true);
+        }
+        Some(pattern)
+    }
+
+    /// Whether a `width` by `height` pattern stays within [`Pattern::MAX_CELLS`].
+    pub(crate) fn fits(
+        width: usize,
+        height: usize,
+    ) -> bool {
+        matches!(width.checked_mul(height), Some(len) if len <= Self::MAX_CELLS)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn rule(&self) -> Option<Rule> {
+        self.rule
+    }
+
+    pub fn set_rule(
+        &mut self,
+        rule: Option<Rule>,
+    ) {
+        self.rule = rule;
+    }
+
+    /// Comment lines carried by the pattern file, without the leading comment marker.
+    pub fn comments(&self) -> &[String] {
+        &self.comments[..]
+    }
+
+    pub fn push_comment<S: Into<String>>(
+        &mut self,
+        comment: S,
+    ) {
+        self.comments.push(comment.into());
+    }
+
+    /// Whether the cell at column `x` and row `y` is alive. Cells outside the pattern are dead.
+    pub fn get(
+        &self,
+        x: usize,
+        y: usize,
+    ) -> bool {
+        x < self.width && y < self.height && self.cells[y * self.width + x]
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` lies outside the pattern.
+    pub fn set(
+        &mut self,
+        x: usize,
+        y: usize,
+        alive: bool,
+    ) {
+        assert!(
+            x < self.width && y < self.height,
+            "({}, {}) is outside of a {}x{} pattern",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        self.cells[y * self.width + x] = alive;
+    }
+
+    /// The `(x, y)` coordinates of every live cell in row major order.
+    pub fn alive_cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, &alive)| alive)
+            .map(move |(idx, _)| (idx % width, idx / width))
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|&&alive| alive).count()
+    }
+
+    /// A copy of the pattern turned or mirrored by `transform`, keeping its rule and comments.
+    pub fn transformed(
+        &self,
+        transform: Transform,
+    ) -> Self {
+        let (width, height) = if transform.transposes() {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+
+        let mut pattern = Pattern::new(width, height);
+        for (x, y) in self.alive_cells() {
+            let (x, y) = transform.apply(x, y, self.width, self.height);
+            pattern.set(x, y, true);
+        }
+        pattern.rule = self.rule;
+        pattern.comments = self.comments.clone();
+        pattern
+    }
+
+    pub(crate) fn with_cells(
+        width: usize,
+        height: usize,
+        cells: Vec<bool>,
+    ) -> Self {
+        debug_assert_eq!(cells.len(), width * height);
+        Self {
+            width,
+            height,
+            cells,
+            rule: None,
+            comments: Vec::new(),
+        }
+    }
+}
+
+/// Reads a pattern in any supported format, picking the format from the content: Life 1.06 when the
+/// input starts with its header, the [`Simulation`] `Display` glyphs when it contains them, plaintext
+/// when it starts with a `!` comment or holds only `.` and `O` rows, and RLE otherwise.
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let first_line = pattern.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+
+        if first_line.starts_with(life106::HEADER) {
+            Pattern::from_life106(pattern)
+        } else if pattern.contains(&[Simulation::ALIVE_GLYPH, Simulation::DEAD_GLYPH][..]) {
+            Pattern::from_glyphs(pattern)
+        } else if first_line.starts_with('!') || first_line.chars().all(|ch| matches!(ch, '.' | 'O' | '*')) {
+            Pattern::from_plaintext(pattern)
+        } else {
+            Pattern::from_rle(pattern)
+        }
+    }
+}
+
+impl Pattern {
+    /// Parses the glyph grid written by the [`Simulation`] `Display` implementation, one row per
+    /// line. Surrounding whitespace and blank lines are ignored so dumps copied out of logs load as is.
+    pub fn from_glyphs(glyphs: &str) -> Result<Self, Error> {
+        let parse_error = |line: usize, column: usize, reason: String| {
+            Error::Parse {
+                format: "glyph",
+                line,
+                column,
+                reason,
+            }
+        };
+
+        let mut width = None;
+        let mut cells = Vec::new();
+        for (idx, line) in glyphs.lines().enumerate() {
+            let row = line.trim();
+            if row.is_empty() {
+                continue;
+            }
+            let indent = line.chars().take_while(|ch| ch.is_whitespace()).count();
+
+            let mut row_width = 0;
+            for (col, ch) in row.chars().enumerate() {
+                match ch {
+                    Simulation::ALIVE_GLYPH => cells.push(true),
+                    Simulation::DEAD_GLYPH => cells.push(false),
+                    ch => {
+                        return Err(parse_error(
+                            idx + 1,
+                            indent + col + 1,
+                            format!("unexpected character {:?}", ch),
+                        ))
+                    }
+                }
+                row_width += 1;
+            }
+
+            match width {
+                None => width = Some(row_width),
+                Some(width) if width != row_width => {
+                    return Err(parse_error(
+                        idx + 1,
+                        indent + 1,
+                        format!("row has {} cells, expected {}", row_width, width),
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+
+        let width = width.unwrap_or(0);
+        let height = cells.len().checked_div(width).unwrap_or(0);
+        Ok(Pattern::with_cells(width, height, cells))
+    }
+}
+
+impl Simulation {
+    /// Creates a board the size of `pattern`, running the pattern's rule if it names one.
+    pub fn from_pattern(pattern: &Pattern) -> Result<Self, Error> {
+        let mut simulation = Simulation::builder()
+            .width(pattern.width())
+            .height(pattern.height())
+            .rule(pattern.rule().unwrap_or_default())
+            .build()?;
+
+        for (x, y) in pattern.alive_cells() {
+            simulation.cellules.set(x, y, true);
+        }
+        simulation.board_edited();
+
+        Ok(simulation)
+    }
+
+    /// Stamps `pattern` onto the board with its top left corner at `(x, y)`, leaving the rule alone.
+    /// Parts of the pattern hanging off the board wrap, reflect or vanish as the topology dictates.
+    pub fn place_pattern(
+        &mut self,
+        pattern: &Pattern,
+        x: isize,
+        y: isize,
+        blend: Blend,
+    ) {
+        let (width, height, topology) = (self.width(), self.height(), self.topology);
+        self.replace_cellules(|cellules| {
+            for row in 0..pattern.height() {
+                for col in 0..pattern.width() {
+                    let alive = pattern.get(col, row);
+                    if !alive && blend != Blend::Overwrite {
+                        continue;
+                    }
+                    let (px, py) = (x + col as isize, y + row as isize);
+                    if let Some((px, py)) = topology.resolve(px, py, width, height) {
+                        match blend {
+                            Blend::Or | Blend::Overwrite => cellules.set(px, py, alive),
+                            Blend::Xor => cellules.toggle(px, py),
+                        }
+                    }
+                }
+            }
+        });
+        self.board_edited();
+    }
+
+    /// Snapshots the whole board and its rule.
+    pub fn to_pattern(&self) -> Pattern {
+        let cells = self.cellules.iter().collect();
+        let mut pattern = Pattern::with_cells(self.width(), self.height(), cells);
+        pattern.set_rule(Some(self.rule()));
+        pattern
+    }
+}
+
+/// Parses any format accepted by [`Pattern`]'s `FromStr` into a board the size of the pattern.
+impl FromStr for Simulation {
+    type Err = Error;
+
+    fn from_str(simulation: &str) -> Result<Self, Self::Err> {
+        Simulation::from_pattern(&simulation.parse()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glider() -> Pattern {
+        Pattern::from_alive_cells(vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)])
+    }
+
+    #[test]
+    fn glyphs_round_trip() {
+        let mut simulation = Simulation::builder().width(5).height(4).build().unwrap();
+        simulation.place_pattern(&glider(), 1, 1, Blend::Or);
+
+        let glyphs = simulation.to_string();
+        let pattern = Pattern::from_glyphs(&glyphs).unwrap();
+        assert_eq!((pattern.width(), pattern.height()), (5, 4));
+        let alive = simulation.to_pattern().alive_cells().collect::<Vec<_>>();
+        assert_eq!(pattern.alive_cells().collect::<Vec<_>>(), alive);
+
+        let indented = glyphs.lines().map(|line| format!("    {}\n", line)).collect::<String>();
+        assert_eq!(Pattern::from_glyphs(&format!("\n{}\n", indented)).unwrap(), pattern);
+    }
+
+    #[test]
+    fn ragged_glyph_rows_are_rejected() {
+        let position = |glyphs: &str| {
+            match Pattern::from_glyphs(glyphs) {
+                Err(Error::Parse { line, column, .. }) => Some((line, column)),
+                _ => None,
+            }
+        };
+
+        assert_eq!(position("◻◼◻\n  ◻◼\n"), Some((2, 3)));
+        assert_eq!(position("◻◼◻\n◻O◻\n"), Some((2, 2)));
+    }
+
+    #[test]
+    fn from_str_detects_the_format() {
+        let formats = [
+            ("#Life 1.06\n1 0\n2 1\n0 2\n1 2\n2 2\n", "Life 1.06"),
+            ("◻◼◻\n◻◻◼\n◼◼◼\n", "glyphs"),
+            ("!Name: Glider\n.O.\n..O\nOOO\n", "plaintext"),
+            (".O.\n..O\nOOO\n", "plaintext without comments"),
+            ("#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n", "RLE"),
+            ("bo$2bo$3o!", "RLE without a header"),
+        ];
+
+        let alive = glider().alive_cells().collect::<Vec<_>>();
+        for &(text, format) in &formats {
+            let pattern = text.parse::<Pattern>().unwrap_or_else(|err| panic!("{}: {}", format, err));
+            assert_eq!(pattern.alive_cells().collect::<Vec<_>>(), alive, "{}", format);
+        }
+
+        assert_eq!(formats[0].0.parse::<Pattern>().unwrap(), Pattern::from_life106(formats[0].0).unwrap());
+        assert_eq!(formats[2].0.parse::<Pattern>().unwrap().comments(), ["Name: Glider"]);
+        assert_eq!(formats[4].0.parse::<Pattern>().unwrap().comments(), ["N Glider"]);
+        assert_eq!(formats[4].0.parse::<Pattern>().unwrap().rule(), Some(Rule::default()));
+    }
+}
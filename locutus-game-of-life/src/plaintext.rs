@@ -0,0 +1,136 @@
+//! Reading and writing of the LifeWiki [plaintext] `.cells` format.
+//!
+//! [plaintext]: https://conwaylife.com/wiki/Plaintext
+
+use crate::{
+    Error,
+    Pattern,
+};
+use std::fmt::Write;
+
+const FORMAT: &str = "plaintext";
+
+impl Pattern {
+    /// Parses a pattern in plaintext format.
+    ///
+    /// Lines starting with `!` become [`Pattern::comments`]. Every other line is a row where `.` is a
+    /// dead cell and `O` (or `*`) a live one. Rows may omit trailing dead cells, and an empty line is an
+    /// all dead row.
+    pub fn from_plaintext(cells: &str) -> Result<Self, Error> {
+        let mut comments = Vec::new();
+        let mut rows = Vec::new();
+
+        for (idx, line) in cells.lines().enumerate() {
+            if let Some(comment) = line.strip_prefix('!') {
+                comments.push(comment.to_string());
+                continue;
+            }
+
+            let row = line
+                .trim_end()
+                .chars()
+                .enumerate()
+                .map(|(col, ch)| {
+                    match ch {
+                        '.' => Ok(false),
+                        'O' | '*' => Ok(true),
+                        ch => {
+                            Err(Error::Parse {
+                                format: FORMAT,
+                                line:   idx + 1,
+                                column: col + 1,
+                                reason: format!("unexpected character {:?}, expected `.` or `O`", ch),
+                            })
+                        }
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            rows.push(row);
+        }
+
+        while let Some(true) = rows.last().map(Vec::is_empty) {
+            rows.pop();
+        }
+
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut pattern = Pattern::try_new(width, rows.len()).ok_or_else(|| {
+            Error::Parse {
+                format: FORMAT,
+                line:   1,
+                column: 1,
+                reason: format!("a {}x{} pattern has more than {} cells", width, rows.len(), Pattern::MAX_CELLS),
+            }
+        })?;
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &alive) in row.iter().enumerate() {
+                pattern.set(x, y, alive);
+            }
+        }
+        for comment in comments {
+            pattern.push_comment(comment);
+        }
+
+        Ok(pattern)
+    }
+
+    /// Encodes the pattern in plaintext format. Rows are written in full so that the bounds survive a
+    /// round trip through [`Pattern::from_plaintext`]. The rule is not part of the format and is
+    /// dropped.
+    pub fn to_plaintext(&self) -> String {
+        let mut cells = String::new();
+
+        for comment in self.comments() {
+            let _ = writeln!(cells, "!{}", comment);
+        }
+        for y in 0..self.height() {
+            cells.extend((0..self.width()).map(|x| {
+                if self.get(x, y) {
+                    'O'
+                } else {
+                    '.'
+                }
+            }));
+            cells.push('\n');
+        }
+
+        cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_round_trip() {
+        let mut pattern = Pattern::new(5, 4);
+        for &(x, y) in &[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            pattern.set(x, y, true);
+        }
+        pattern.push_comment("Name: Glider");
+        pattern.push_comment("");
+
+        let cells = pattern.to_plaintext();
+        assert_eq!(cells, "!Name: Glider\n!\n.O...\n..O..\nOOO..\n.....\n");
+        assert_eq!(Pattern::from_plaintext(&cells).unwrap(), pattern);
+    }
+
+    #[test]
+    fn short_rows_and_stars_are_read() {
+        let pattern = Pattern::from_plaintext("!Name: Beehive\n.**\n*..*\n\n.OO\n\n").unwrap();
+
+        assert_eq!((pattern.width(), pattern.height()), (4, 4));
+        assert_eq!(pattern.alive_cells().collect::<Vec<_>>(), [(1, 0), (2, 0), (0, 1), (3, 1), (1, 3), (2, 3)]);
+        assert_eq!(pattern.comments(), ["Name: Beehive"]);
+        assert_eq!(pattern.rule(), None);
+    }
+
+    #[test]
+    fn unexpected_characters_are_rejected() {
+        let position = match Pattern::from_plaintext("!x\n.O\n.Ox") {
+            Err(Error::Parse { line, column, .. }) => Some((line, column)),
+            _ => None,
+        };
+        assert_eq!(position, Some((3, 3)));
+    }
+}
@@ -0,0 +1,271 @@
+//! Editing rectangular regions of a board, and the clipboard they can be copied to and pasted from.
+
+use crate::{
+    bitgrid::BitGrid,
+    Blend,
+    Bounds,
+    Error,
+    Pattern,
+    Simulation,
+    Transform,
+};
+
+impl Simulation {
+    /// Brings every cell of `region` to life when `alive` is set and kills every one otherwise.
+    pub fn fill_region(
+        &mut self,
+        region: Bounds,
+        alive: bool,
+    ) -> Result<(), Error> {
+        self.edit_region(region, |cellules, x, y| cellules.set(x, y, alive))
+    }
+
+    /// Kills the live cells of `region` and brings the others to life, dying or not.
+    pub fn invert_region(
+        &mut self,
+        region: Bounds,
+    ) -> Result<(), Error> {
+        self.edit_region(region, |cellules, x, y| cellules.toggle(x, y))
+    }
+
+    /// The live cells of `region` as a pattern of its size, running the board's rule.
+    pub fn copy_region(
+        &self,
+        region: Bounds,
+    ) -> Result<Pattern, Error> {
+        self.check_region(region)?;
+        let (left, top) = (region.min_x as usize, region.min_y as usize);
+        let mut pattern = Pattern::new(region.width() as usize, region.height() as usize);
+        for y in 0..pattern.height() {
+            for x in 0..pattern.width() {
+                pattern.set(x, y, self.cellules.get(left + x, top + y));
+            }
+        }
+        pattern.set_rule(Some(self.rule));
+        Ok(pattern)
+    }
+
+    /// The pattern last copied with [`crate::Message::CopyRegion`], if any.
+    pub fn clipboard(&self) -> Option<&Pattern> {
+        self.clipboard.as_ref()
+    }
+
+    /// Copies `region` to the clipboard, replacing what was there.
+    pub fn copy_to_clipboard(
+        &mut self,
+        region: Bounds,
+    ) -> Result<(), Error> {
+        self.clipboard = Some(self.copy_region(region)?);
+        Ok(())
+    }
+
+    /// Places the clipboard like [`Simulation::place_pattern`], turned or mirrored by `transform`.
+    pub fn paste(
+        &mut self,
+        x: isize,
+        y: isize,
+        transform: Transform,
+        blend: Blend,
+    ) -> Result<(), Error> {
+        let pattern = self.clipboard.as_ref().ok_or(Error::EmptyClipboard)?.transformed(transform);
+        self.place_pattern(&pattern, x, y, blend);
+        Ok(())
+    }
+
+    /// Fails with [`Error::RegionOutOfBounds`] unless `region` is a well formed rectangle that lies
+    /// entirely on the board.
+    pub(crate) fn check_region(
+        &self,
+        region: Bounds,
+    ) -> Result<(), Error> {
+        let (width, height) = (self.width() as i64, self.height() as i64);
+        let fits = 0 <= region.min_x
+            && region.min_x <= region.max_x
+            && region.max_x < width
+            && 0 <= region.min_y
+            && region.min_y <= region.max_y
+            && region.max_y < height;
+        if fits {
+            Ok(())
+        } else {
+            Err(Error::RegionOutOfBounds {
+                region,
+                width: self.width(),
+                height: self.height(),
+            })
+        }
+    }
+
+    /// Applies `edit` to every cell of `region` as a single undoable change.
+    fn edit_region<F>(
+        &mut self,
+        region: Bounds,
+        mut edit: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(&mut BitGrid, usize, usize),
+    {
+        self.check_region(region)?;
+        self.replace_cellules(|cellules| {
+            for y in region.min_y as usize..=region.max_y as usize {
+                for x in region.min_x as usize..=region.max_x as usize {
+                    edit(cellules, x, y);
+                }
+            }
+        });
+        self.board_edited();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Message,
+        Topology,
+    };
+
+    /// A 6 by 4 plane with a glider in its top left corner.
+    fn simulation() -> Simulation {
+        let mut simulation = Simulation::builder().width(6).height(4).topology(Topology::Plane).build().unwrap();
+        simulation.place_pattern(&Pattern::named("glider").unwrap(), 0, 0, Blend::Or);
+        simulation
+    }
+
+    fn region(
+        min_x: i64,
+        min_y: i64,
+        max_x: i64,
+        max_y: i64,
+    ) -> Bounds {
+        Bounds {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        }
+    }
+
+    fn out_of_bounds(result: Result<(), Error>) -> bool {
+        matches!(result, Err(Error::RegionOutOfBounds { width: 6, height: 4, .. }))
+    }
+
+    #[test]
+    fn check_region_accepts_only_regions_on_the_board() {
+        let simulation = simulation();
+        assert!(simulation.check_region(region(0, 0, 5, 3)).is_ok());
+        assert!(simulation.check_region(region(5, 3, 5, 3)).is_ok());
+        assert!(simulation.check_region(region(2, 3, 5, 3)).is_ok());
+
+        // Inverted.
+        assert!(out_of_bounds(simulation.check_region(region(3, 0, 2, 3))));
+        assert!(out_of_bounds(simulation.check_region(region(0, 2, 5, 1))));
+        // Out of range.
+        assert!(out_of_bounds(simulation.check_region(region(0, 0, 6, 3))));
+        assert!(out_of_bounds(simulation.check_region(region(0, 0, 5, 4))));
+        assert!(out_of_bounds(simulation.check_region(region(-1, 0, 5, 3))));
+        assert!(out_of_bounds(simulation.check_region(region(0, i64::MIN, 5, 3))));
+        assert!(out_of_bounds(simulation.check_region(region(0, 0, i64::MAX, 3))));
+    }
+
+    #[test]
+    fn region_out_of_bounds_is_recoverable() {
+        let mut simulation = simulation();
+        let before = simulation.to_pattern();
+        let bad = region(4, 2, 6, 3);
+        for message in &[
+            Message::FillRegion(bad),
+            Message::ClearRegion(bad),
+            Message::InvertRegion(bad),
+            Message::CopyRegion(bad),
+        ] {
+            let err = simulation.update(message.clone()).unwrap_err();
+            assert!(err.may_recover());
+            assert!(out_of_bounds(Err(err)));
+        }
+        assert_eq!(simulation.to_pattern(), before);
+        assert!(simulation.clipboard().is_none());
+    }
+
+    #[test]
+    fn edits_reach_the_last_row_and_column() {
+        let mut simulation = simulation();
+        simulation.update(Message::FillRegion(region(4, 2, 5, 3))).unwrap();
+        for &(x, y) in &[(4, 2), (5, 2), (4, 3), (5, 3)] {
+            assert!(simulation.cellule_at(x, y).unwrap().alive(), "({}, {})", x, y);
+        }
+        assert_eq!(simulation.population(), 9);
+
+        simulation.update(Message::InvertRegion(region(5, 0, 5, 3))).unwrap();
+        assert_eq!(simulation.population(), 9);
+        simulation.update(Message::ClearRegion(region(0, 0, 5, 3))).unwrap();
+        assert_eq!(simulation.population(), 0);
+    }
+
+    #[test]
+    fn copy_then_paste_round_trips() {
+        let mut simulation = simulation();
+        let glider = region(0, 0, 2, 2);
+        simulation.update(Message::CopyRegion(glider)).unwrap();
+        assert_eq!(simulation.clipboard().unwrap().population(), 5);
+
+        simulation.update(Message::ClearRegion(glider)).unwrap();
+        assert_eq!(simulation.population(), 0);
+        simulation
+            .update(Message::Paste {
+                x:         3,
+                y:         1,
+                transform: Transform::Identity,
+                blend:     Blend::Or,
+            })
+            .unwrap();
+        let moved = simulation.copy_region(region(3, 1, 5, 3)).unwrap();
+        assert_eq!(&moved, simulation.clipboard().unwrap());
+        assert_eq!(simulation.population(), 5);
+    }
+
+    #[test]
+    fn paste_clips_at_the_edge() {
+        let mut simulation = simulation();
+        simulation.copy_to_clipboard(region(0, 0, 2, 2)).unwrap();
+        simulation.fill_region(region(0, 0, 5, 3), false).unwrap();
+
+        // Only the live cell of the glider's top row lands on the board.
+        simulation.paste(4, 2, Transform::Identity, Blend::Or).unwrap();
+        let alive: Vec<_> = simulation.to_pattern().alive_cells().collect();
+        assert_eq!(alive, vec![(5, 2)]);
+
+        simulation.fill_region(region(0, 0, 5, 3), false).unwrap();
+        simulation.paste(-1, -1, Transform::Identity, Blend::Or).unwrap();
+        let alive: Vec<_> = simulation.to_pattern().alive_cells().collect();
+        assert_eq!(alive, vec![(1, 0), (0, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn paste_needs_a_clipboard() {
+        let mut simulation = simulation();
+        assert!(matches!(
+            simulation.paste(0, 0, Transform::Identity, Blend::Or),
+            Err(Error::EmptyClipboard)
+        ));
+    }
+
+    #[test]
+    fn region_messages_read_from_json() {
+        let json = r#"{"FillRegion":{"min_x":4,"min_y":2,"max_x":5,"max_y":3}}"#;
+        let message: Message = serde_json::from_str(json).unwrap();
+        assert_eq!(message, Message::FillRegion(region(4, 2, 5, 3)));
+
+        let json = r#"{"Paste":{"x":-1,"y":0,"transform":"Rotate90","blend":"Overwrite"}}"#;
+        assert_eq!(
+            serde_json::from_str::<Message>(json).unwrap(),
+            Message::Paste {
+                x:         -1,
+                y:         0,
+                transform: Transform::Rotate90,
+                blend:     Blend::Overwrite,
+            }
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Growing and cropping a board while keeping its cells in place.
+
+use crate::{
+    check_dimensions,
+    check_range,
+    deps::serde,
+    Error,
+    Simulation,
+};
+
+/// The point of a board that stays put when it is resized, so that growing a board anchored
+/// at [`Anchor::Center`] adds room evenly on every side.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    pub const ALL: [Anchor; 9] = [
+        Anchor::TopLeft,
+        Anchor::Top,
+        Anchor::TopRight,
+        Anchor::Left,
+        Anchor::Center,
+        Anchor::Right,
+        Anchor::BottomLeft,
+        Anchor::Bottom,
+        Anchor::BottomRight,
+    ];
+
+    /// How far the cells move right and down when a `from` sized board becomes a `to` sized one.
+    fn offset(
+        &self,
+        from: (usize, usize),
+        to: (usize, usize),
+    ) -> (isize, isize) {
+        let grown = |from: usize, to: usize| to as isize - from as isize;
+        let (width, height) = (grown(from.0, to.0), grown(from.1, to.1));
+
+        let dx = match self {
+            Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => 0,
+            Anchor::Top | Anchor::Center | Anchor::Bottom => width / 2,
+            Anchor::TopRight | Anchor::Right | Anchor::BottomRight => width,
+        };
+        let dy = match self {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => 0,
+            Anchor::Left | Anchor::Center | Anchor::Right => height / 2,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => height,
+        };
+        (dx, dy)
+    }
+}
+
+impl Simulation {
+    /// Makes the board `width` columns by `height` rows, adding dead cells or cropping around
+    /// `anchor`. The generation count and rule carry over, and the resize can be undone like any
+    /// other edit. A board may not shrink below the range of a Larger than Life rule.
+    pub fn resize(
+        &mut self,
+        width: usize,
+        height: usize,
+        anchor: Anchor,
+    ) -> Result<(), Error> {
+        check_dimensions(width, height)?;
+        check_range(&self.rule, width, height)?;
+
+        let (dx, dy) = anchor.offset((self.width(), self.height()), (width, height));
+        self.replace_cellules(|cellules| *cellules = cellules.resized(width, height, dx, dy));
+        self.board_edited();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        LineAutomaton,
+        LineRule,
+        Message,
+        TurmiteRule,
+        Turmites,
+        Wireworld,
+    };
+
+    /// A 4 by 4 board with its corners and two cells of the diagonal alive.
+    fn simulation() -> Simulation {
+        let mut simulation = Simulation::with_size(4, 4);
+        for &(x, y) in &[(0, 0), (3, 0), (0, 3), (3, 3), (1, 1), (2, 2)] {
+            simulation.set_cell(x, y, 1).unwrap();
+        }
+        simulation
+    }
+
+    fn alive(simulation: &Simulation) -> Vec<(usize, usize)> {
+        let mut cells: Vec<_> = simulation.to_pattern().alive_cells().collect();
+        cells.sort_unstable();
+        cells
+    }
+
+    #[test]
+    fn growing_keeps_the_cells_at_the_anchor() {
+        let expected = [
+            (Anchor::TopLeft, (0, 0)),
+            (Anchor::Top, (1, 0)),
+            (Anchor::TopRight, (3, 0)),
+            (Anchor::Left, (0, 1)),
+            (Anchor::Center, (1, 1)),
+            (Anchor::Right, (3, 1)),
+            (Anchor::BottomLeft, (0, 2)),
+            (Anchor::Bottom, (1, 2)),
+            (Anchor::BottomRight, (3, 2)),
+        ];
+        for &(anchor, (dx, dy)) in &expected {
+            let mut simulation = simulation();
+            let mut moved: Vec<_> = alive(&simulation).into_iter().map(|(x, y)| (x + dx, y + dy)).collect();
+            moved.sort_unstable();
+
+            simulation.resize(7, 6, anchor).unwrap();
+            assert_eq!((simulation.width(), simulation.height()), (7, 6));
+            assert_eq!(alive(&simulation), moved, "{:?}", anchor);
+        }
+    }
+
+    #[test]
+    fn shrinking_keeps_the_cells_at_the_anchor() {
+        let expected: [(Anchor, &[(usize, usize)]); 9] = [
+            (Anchor::TopLeft, &[(0, 0), (1, 1)]),
+            (Anchor::Top, &[(0, 1)]),
+            (Anchor::TopRight, &[(1, 0)]),
+            (Anchor::Left, &[(1, 0)]),
+            (Anchor::Center, &[(0, 0), (1, 1)]),
+            (Anchor::Right, &[(0, 1)]),
+            (Anchor::BottomLeft, &[(0, 1)]),
+            (Anchor::Bottom, &[(1, 0)]),
+            (Anchor::BottomRight, &[(0, 0), (1, 1)]),
+        ];
+        for &(anchor, cells) in &expected {
+            let mut simulation = simulation();
+            simulation.resize(2, 2, anchor).unwrap();
+            assert_eq!((simulation.width(), simulation.height()), (2, 2));
+            assert_eq!(alive(&simulation), cells, "{:?}", anchor);
+        }
+    }
+
+    #[test]
+    fn resize_reads_from_json_and_can_be_undone() {
+        let json = r#"{"Resize":{"width":2,"height":2,"anchor":"Center"}}"#;
+        let message: Message = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            message,
+            Message::Resize {
+                width:  2,
+                height: 2,
+                anchor: Anchor::Center,
+            }
+        );
+
+        let mut simulation = simulation();
+        let before = simulation.to_pattern();
+        simulation.update(message).unwrap();
+        assert_eq!(simulation.population(), 2);
+        simulation.update(Message::Undo).unwrap();
+        assert_eq!(simulation.to_pattern(), before);
+    }
+
+    #[test]
+    fn boards_above_the_cell_limit_are_rejected() {
+        let side = 1 << 14;
+        let mut simulation = Simulation::with_size(8, 8);
+        let too_big = Simulation::MAX_CELLS + 1;
+        for &(width, height) in &[(too_big, 1), (side, 2 * side), (usize::MAX, 2), (0, 8)] {
+            let err = simulation.resize(width, height, Anchor::Center).unwrap_err();
+            assert!(matches!(err, Error::InvalidDimensions { .. }), "{:?}", err);
+            let err = Simulation::builder().width(width).height(height).build().unwrap_err();
+            assert!(matches!(err, Error::InvalidDimensions { .. }), "{:?}", err);
+            assert!(matches!(Wireworld::new(width, height), Err(Error::InvalidDimensions { .. })));
+            assert!(matches!(
+                LineAutomaton::new(width, height, LineRule::default()),
+                Err(Error::InvalidDimensions { .. })
+            ));
+            assert!(matches!(
+                Turmites::new(width, height, TurmiteRule::default()),
+                Err(Error::InvalidDimensions { .. })
+            ));
+        }
+        assert_eq!((simulation.width(), simulation.height()), (8, 8));
+    }
+}
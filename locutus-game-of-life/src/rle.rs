@@ -0,0 +1,425 @@
+//! Reading and writing of the [Run Length Encoded] pattern format.
+//!
+//! [Run Length Encoded]: https://conwaylife.com/wiki/Run_Length_Encoded
+
+use crate::{
+    Error,
+    Pattern,
+    Rule,
+    Simulation,
+};
+use std::fmt::Write;
+
+const FORMAT: &str = "RLE";
+const MAX_LINE_LENGTH: usize = 70;
+const COMMENT_KINDS: &[char] = &['C', 'c', 'N', 'O', 'P', 'R', 'r'];
+
+fn parse_error<S: Into<String>>(
+    line: usize,
+    column: usize,
+    reason: S,
+) -> Error {
+    Error::Parse {
+        format: FORMAT,
+        line,
+        column,
+        reason: reason.into(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Header {
+    width:  usize,
+    height: usize,
+    rule:   Option<Rule>,
+}
+
+impl Header {
+    /// Parses an `x = m, y = n, rule = abc` line. Unknown keys are ignored so that files written by
+    /// other tools still load.
+    fn parse(
+        line: &str,
+        lineno: usize,
+    ) -> Result<Self, Error> {
+        let mut width = None;
+        let mut height = None;
+        let mut rule = None;
+
+        let mut offset = 0;
+        let mut rest = line;
+        loop {
+            let (mut field, remainder) = match rest.find(',') {
+                Some(comma) => (&rest[..comma], Some(&rest[comma + 1..])),
+                None => (rest, None),
+            };
+            let column = offset + 1;
+
+            let eq = field
+                .find('=')
+                .ok_or_else(|| parse_error(lineno, column, format!("expected `key = value`, found {:?}", field)))?;
+            let key = field[..eq].trim();
+            // Larger than Life rules are themselves written with commas, so the rule takes the rest of
+            // the line.
+            if key == "rule" {
+                field = rest;
+            }
+            offset += field.chars().count() + 1;
+            let value = field[eq + 1..].trim_start();
+            let value_column = column + field.chars().count() - value.chars().count();
+            let value = value.trim_end();
+
+            let parse_dimension = |value: &str| {
+                value
+                    .parse::<usize>()
+                    .map_err(|_| parse_error(lineno, value_column, format!("invalid {} dimension {:?}", key, value)))
+            };
+
+            match key {
+                "x" => width = Some(parse_dimension(value)?),
+                "y" => height = Some(parse_dimension(value)?),
+                "rule" => {
+                    rule = Some(
+                        value
+                            .parse::<Rule>()
+                            .map_err(|err| parse_error(lineno, value_column, err.to_string()))?,
+                    )
+                }
+                _ => {}
+            }
+
+            match remainder {
+                Some(remainder) if key != "rule" => rest = remainder,
+                _ => break,
+            }
+        }
+
+        match (width, height) {
+            (Some(width), Some(height)) if !Pattern::fits(width, height) => {
+                Err(parse_error(
+                    lineno,
+                    1,
+                    format!("a {}x{} pattern has more than {} cells", width, height, Pattern::MAX_CELLS),
+                ))
+            }
+            (Some(width), Some(height)) => Ok(Header { width, height, rule }),
+            _ => Err(parse_error(lineno, 1, "header must contain both `x` and `y`")),
+        }
+    }
+}
+
+impl Pattern {
+    /// Parses a pattern in RLE format.
+    ///
+    /// Leading `#` lines become [`Pattern::comments`], the optional `x = m, y = n, rule = abc`
+    /// header sets the pattern bounds and rule, and the body runs until `!` or the end of input.
+    /// When the header is missing the bounds are those of the live cells.
+    pub fn from_rle(rle: &str) -> Result<Self, Error> {
+        let mut comments = Vec::new();
+        let mut header = None;
+        let mut runs = Vec::new();
+        let mut in_body = false;
+
+        let (mut x, mut y) = (0usize, 0usize);
+        let (mut right, mut bottom) = (0usize, 0usize);
+        let mut run: Option<(usize, usize, usize)> = None;
+
+        'lines: for (idx, line) in rle.lines().enumerate() {
+            let lineno = idx + 1;
+
+            if !in_body {
+                let trimmed = line.trim();
+                if let Some(comment) = line.trim_start().strip_prefix('#') {
+                    comments.push(comment.to_string());
+                    continue;
+                } else if trimmed.is_empty() {
+                    continue;
+                } else if trimmed.starts_with('x') && header.is_none() {
+                    header = Some(Header::parse(line, lineno)?);
+                    continue;
+                }
+                in_body = true;
+            }
+
+            for (col, ch) in line.chars().enumerate() {
+                let column = col + 1;
+                let count = run.map(|(count, _, _)| count).unwrap_or(1);
+
+                match ch {
+                    '0'..='9' => {
+                        let digit = ch.to_digit(10).unwrap_or_default() as usize;
+                        let (count, line, column) = run.unwrap_or((0, lineno, column));
+                        let count = count
+                            .checked_mul(10)
+                            .and_then(|count| count.checked_add(digit))
+                            .ok_or_else(|| parse_error(line, column, "run count is too large"))?;
+                        run = Some((count, line, column));
+                        continue;
+                    }
+                    'b' | '.' => {
+                        x = x.saturating_add(count);
+                    }
+                    'o' => {
+                        let end = x
+                            .checked_add(count)
+                            .ok_or_else(|| parse_error(lineno, column, "run extends past the end of the row"))?;
+                        if let Some(Header { width, height, .. }) = header {
+                            if end > width || y >= height {
+                                return Err(parse_error(
+                                    lineno,
+                                    column,
+                                    format!("live cell lies outside the x = {}, y = {} bounds", width, height),
+                                ));
+                            }
+                        }
+                        right = right.max(end);
+                        bottom = bottom.max(y.saturating_add(1));
+                        if !Pattern::fits(right, bottom) {
+                            return Err(parse_error(
+                                lineno,
+                                column,
+                                format!("live cells span more than {} cells", Pattern::MAX_CELLS),
+                            ));
+                        }
+                        runs.push((x..end, y));
+                        x = end;
+                    }
+                    '$' => {
+                        y = y.saturating_add(count);
+                        x = 0;
+                    }
+                    '!' => {
+                        run = None;
+                        break 'lines;
+                    }
+                    ch if ch.is_whitespace() => continue,
+                    ch if ch.is_ascii_alphabetic() => {
+                        return Err(parse_error(
+                            lineno,
+                            column,
+                            format!("unsupported cell state {:?}, only `b` and `o` are allowed", ch),
+                        ));
+                    }
+                    ch => return Err(parse_error(lineno, column, format!("unexpected character {:?}", ch))),
+                }
+
+                run = None;
+            }
+        }
+
+        if let Some((_, line, column)) = run {
+            return Err(parse_error(line, column, "run count is not followed by a cell or `$`"));
+        }
+
+        // Without a header the bounds are those of the live cells, which were checked to fit as they
+        // were read.
+        let (width, height, rule) = match header {
+            Some(Header { width, height, rule }) => (width, height, rule),
+            None => (right, bottom, None),
+        };
+        let mut pattern = Pattern::new(width, height);
+        pattern.set_rule(rule);
+        for (columns, y) in runs {
+            for x in columns {
+                pattern.set(x, y, true);
+            }
+        }
+        for comment in comments {
+            pattern.push_comment(comment);
+        }
+
+        Ok(pattern)
+    }
+
+    /// Encodes the pattern as RLE. Reading the output back with [`Pattern::from_rle`] yields the same
+    /// cells, bounds and rule.
+    ///
+    /// Comments that are empty, start with whitespace or start with a comment type such as `C` or `N`
+    /// followed by a space are written as they are, so they read back unchanged. Any other comment,
+    /// such as `found in a soup` or one read from a `#found in a soup` line, is written as a `#C`
+    /// comment and reads back with a `C ` in front.
+    pub fn to_rle(&self) -> String {
+        let mut rle = String::new();
+
+        for comment in self.comments() {
+            let mut chars = comment.chars();
+            let typed = match chars.next() {
+                None => true,
+                Some(first) if first.is_whitespace() => true,
+                Some(kind) => COMMENT_KINDS.contains(&kind) && matches!(chars.next(), None | Some(' ')),
+            };
+            if typed {
+                let _ = writeln!(rle, "#{}", comment);
+            } else {
+                let _ = writeln!(rle, "#C {}", comment);
+            }
+        }
+
+        let _ = write!(rle, "x = {}, y = {}", self.width(), self.height());
+        if let Some(rule) = self.rule() {
+            let _ = write!(rle, ", rule = {}", rule);
+        }
+        rle.push('\n');
+
+        let mut writer = LineWriter::new(rle);
+        let mut pending_rows = 0;
+        for y in 0..self.height() {
+            let row: Vec<bool> = (0..self.width()).map(|x| self.get(x, y)).collect();
+            let end = row.iter().rposition(|&alive| alive).map_or(0, |idx| idx + 1);
+
+            if end > 0 {
+                if pending_rows > 0 {
+                    writer.push_run(pending_rows, '$');
+                    pending_rows = 0;
+                }
+
+                let mut start = 0;
+                while start < end {
+                    let alive = row[start];
+                    let len = row[start..end].iter().take_while(|&&cell| cell == alive).count();
+                    writer.push_run(len, if alive { 'o' } else { 'b' });
+                    start += len;
+                }
+            }
+            pending_rows += 1;
+        }
+        writer.push_run(1, '!');
+
+        writer.finish()
+    }
+}
+
+/// Accumulates run tokens into lines no longer than [`MAX_LINE_LENGTH`], never splitting a token.
+struct LineWriter {
+    output:   String,
+    line_len: usize,
+}
+
+impl LineWriter {
+    fn new(output: String) -> Self {
+        Self {
+            output,
+            line_len: 0,
+        }
+    }
+
+    fn push_run(
+        &mut self,
+        count: usize,
+        tag: char,
+    ) {
+        let token = if count == 1 {
+            tag.to_string()
+        } else {
+            format!("{}{}", count, tag)
+        };
+
+        if self.line_len + token.len() > MAX_LINE_LENGTH {
+            self.output.push('\n');
+            self.line_len = 0;
+        }
+        self.line_len += token.len();
+        self.output.push_str(&token);
+    }
+
+    fn finish(mut self) -> String {
+        self.output.push('\n');
+        self.output
+    }
+}
+
+impl Simulation {
+    /// Creates a board sized and ruled by the RLE header. See [`Pattern::from_rle`].
+    pub fn from_rle(rle: &str) -> Result<Self, Error> {
+        Simulation::from_pattern(&Pattern::from_rle(rle)?)
+    }
+
+    pub fn to_rle(&self) -> String {
+        self.to_pattern().to_rle()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_round_trip() {
+        let mut pattern = Pattern::new(150, 4);
+        for x in (0..40).chain(45..150) {
+            pattern.set(x, 0, true);
+        }
+        pattern.set(3, 3, true);
+        pattern.set(149, 3, true);
+        pattern.set_rule(Some("B36/S23".parse().unwrap()));
+        pattern.push_comment("N HighLife sampler");
+        pattern.push_comment("C two rows of runs");
+
+        let rle = pattern.to_rle();
+        assert!(rle.contains("40o5b105o"), "{}", rle);
+        assert!(rle.lines().all(|line| line.len() <= MAX_LINE_LENGTH || line.starts_with('#')), "{}", rle);
+        assert_eq!(Pattern::from_rle(&rle).unwrap(), pattern);
+    }
+
+    #[test]
+    fn free_form_comments_are_written_as_c_comments() {
+        let mut pattern = Pattern::new(1, 1);
+        pattern.push_comment("found in a soup");
+
+        let read = Pattern::from_rle(&pattern.to_rle()).unwrap();
+        assert_eq!(read.comments(), ["C found in a soup"]);
+        assert_eq!(Pattern::from_rle(&read.to_rle()).unwrap(), read);
+    }
+
+    #[test]
+    fn comments_read_from_rle_round_trip() {
+        let rle = "# foo\n#C  two spaces\n#N Glider  \n#\n  #\tindented\n#r 23/3\nx = 1, y = 1\no!\n";
+        let pattern = Pattern::from_rle(rle).unwrap();
+        assert_eq!(pattern.comments(), [" foo", "C  two spaces", "N Glider  ", "", "\tindented", "r 23/3"]);
+
+        let written = pattern.to_rle();
+        assert!(written.starts_with("# foo\n#C  two spaces\n#N Glider  \n#\n#\tindented\n#r 23/3\n"), "{}", written);
+        assert_eq!(Pattern::from_rle(&written).unwrap(), pattern);
+
+        let untyped = Pattern::from_rle("#found in a soup\no!").unwrap();
+        assert_eq!(Pattern::from_rle(&untyped.to_rle()).unwrap().comments(), ["C found in a soup"]);
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_offending_character() {
+        assert_eq!(parse_position(Pattern::from_rle("x = 3, y = 3\nbo$2o\n3q!")), (3, 2));
+        assert_eq!(parse_position(Pattern::from_rle("#C comment\nx = 3, y = 2\nbo$o%!")), (3, 5));
+        assert_eq!(parse_position(Pattern::from_rle("x = 2, y = 2\n3o!")), (2, 2));
+        assert_eq!(parse_position(Pattern::from_rle("x = 2, y = 2\no$\n  12")), (3, 3));
+        assert_eq!(parse_position(Pattern::from_rle("x = 2, y = two\no!")), (1, 12));
+        assert_eq!(parse_position(Pattern::from_rle("x = 2, y = 2, rule = B3/Q\no!")), (1, 22));
+    }
+
+    #[test]
+    fn larger_than_life_rule_round_trips() {
+        let rule: Rule = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+        let mut pattern = Pattern::from_alive_cells(vec![(0, 0), (1, 1), (2, 2)]);
+        pattern.set_rule(Some(rule));
+
+        let rle = pattern.to_rle();
+        assert!(rle.contains("rule = R5,C0,M1,S34..58,B34..45,NM"), "{}", rle);
+        assert_eq!(Pattern::from_rle(&rle).unwrap(), pattern);
+    }
+
+    fn parse_position(result: Result<Pattern, Error>) -> (usize, usize) {
+        match result {
+            Err(Error::Parse { line, column, .. }) => (line, column),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn oversized_patterns_are_rejected() {
+        assert_eq!(parse_position(Pattern::from_rle("x = 100000000000, y = 100000000000\n!")), (1, 1));
+        assert_eq!(parse_position(Pattern::from_rle("x = 100000, y = 100000\n!")), (1, 1));
+        assert_eq!(parse_position(Pattern::from_rle("o$100000000o$100000000$o!")), (1, 24));
+        assert_eq!(parse_position(Pattern::from_life106("#Life 1.06\n0 0\n100000 100000\n")), (1, 1));
+
+        let plaintext = format!("O{}\n{}O", "\n".repeat(1 << 14), ".".repeat(1 << 15));
+        assert_eq!(parse_position(Pattern::from_plaintext(&plaintext)), (1, 1));
+    }
+}
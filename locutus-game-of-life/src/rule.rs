@@ -0,0 +1,712 @@
+use crate::{
+    deps::serde,
+    Error,
+    Grid,
+    Neighborhood,
+};
+use std::{
+    convert::TryFrom,
+    fmt,
+    ops::RangeInclusive,
+    str::FromStr,
+};
+
+/// A Life-like outer totalistic rule over the Moore neighborhood, written in B/S notation.
+///
+/// Bit `n` of `birth` is set when a dead cell with `n` live neighbors is born, and bit `n` of
+/// `survival` is set when a live cell with `n` live neighbors survives.
+///
+/// Rules of the [Generations] family, written `B2/S/C3`, have more than two `states`. A live cell
+/// that does not survive then starts dying instead of dying at once: it moves through the states
+/// from 2 up to `states - 1`, one per generation, and only becomes dead after the last. Dying cells
+/// neither count as live neighbors nor can be born.
+///
+/// As in Golly, a `V`, `H` or `L` at the end of a rulestring, as in `B2/S34H`, counts the von
+/// Neumann, hexagonal or triangular neighbors instead of the Moore ones. The twelve triangular
+/// neighbors allow counts past 9, which are written as the hexadecimal digits `a`, `b` and `c`, as in
+/// `B4a/S2bcL`.
+///
+/// Colored rules give every live cell a team, written as a `T` part as in `B3/S23/T2`. A cell
+/// keeps its team while it survives, and a newborn cell joins the team most of its live neighbors
+/// are on. When no team has a majority it joins the first team none of them are on, and the first
+/// of the tied teams when every team is among them. Conway's Life with two teams is [Immigration]
+/// and with four [QuadLife], which are written by those names.
+///
+/// [Larger than Life] rules, written `R5,C0,M1,S34..58,B34..45,NM`, count the live cells of a
+/// [`Neighborhood`] of any range instead, optionally along with the cell itself, and are born or
+/// survive when that count falls within a range. Those of range 1 over a neighborhood with at most
+/// eight cells that B/S notation can name are kept as B/S rules.
+///
+/// [Generations]: https://conwaylife.com/wiki/Generations
+/// [Immigration]: https://conwaylife.com/wiki/Immigration
+/// [QuadLife]: https://conwaylife.com/wiki/QuadLife
+/// [Larger than Life]: https://conwaylife.com/wiki/Larger_than_Life
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rule {
+    birth:        Counts,
+    survival:     Counts,
+    states:       u16,
+    range:        u16,
+    neighborhood: Neighborhood,
+    middle:       bool,
+    teams:        u8,
+}
+
+/// The neighbor counts that lead to a birth or to survival.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Counts {
+    /// Bit `n` is set when `n` is among the counts.
+    Mask(u16),
+    /// Every count from `min` to `max`.
+    Range { min: u32, max: u32 },
+}
+
+impl Counts {
+    fn contains(
+        &self,
+        count: usize,
+    ) -> bool {
+        match *self {
+            Counts::Mask(mask) => count < u16::BITS as usize && mask & (1 << count) != 0,
+            Counts::Range { min, max } => min as usize <= count && count <= max as usize,
+        }
+    }
+}
+
+impl Rule {
+    /// Conway's Game of Life.
+    pub const CONWAY: Rule = Rule::from_masks(1 << 3, (1 << 2) | (1 << 3));
+    pub const DAY_AND_NIGHT: Rule = Rule::from_masks(
+        (1 << 3) | (1 << 6) | (1 << 7) | (1 << 8),
+        (1 << 3) | (1 << 4) | (1 << 6) | (1 << 7) | (1 << 8),
+    );
+    pub const HIGHLIFE: Rule = Rule::from_masks((1 << 3) | (1 << 6), (1 << 2) | (1 << 3));
+    pub const SEEDS: Rule = Rule::from_masks(1 << 2, 0);
+    /// Brian's Brain, `B2/S/C3`.
+    pub const BRIANS_BRAIN: Rule = Rule {
+        states: 3,
+        ..Rule::SEEDS
+    };
+    /// Star Wars, `B2/S345/C4`.
+    pub const STAR_WARS: Rule = Rule {
+        states: 4,
+        ..Rule::from_masks(1 << 2, (1 << 3) | (1 << 4) | (1 << 5))
+    };
+    /// Conway's Life with two teams.
+    pub const IMMIGRATION: Rule = Rule {
+        teams: 2,
+        ..Rule::CONWAY
+    };
+    /// Conway's Life with four teams.
+    pub const QUADLIFE: Rule = Rule {
+        teams: 4,
+        ..Rule::CONWAY
+    };
+    /// The most states a Generations rule may have.
+    pub const MAX_STATES: usize = 256;
+    /// The widest range a Larger than Life rule may have.
+    pub const MAX_RANGE: usize = 500;
+    /// The most teams a colored rule may have.
+    pub const MAX_TEAMS: usize = 4;
+    const MAX_NEIGHBORS: usize = 8;
+    /// The letters ending a B/S rulestring over another neighborhood than the Moore one.
+    const SUFFIXES: [(char, Neighborhood); 3] = [
+        ('V', Neighborhood::VonNeumann),
+        ('H', Neighborhood::Hexagonal),
+        ('L', Neighborhood::Triangular),
+    ];
+
+    const fn from_masks(
+        birth: u16,
+        survival: u16,
+    ) -> Self {
+        Self {
+            birth:        Counts::Mask(birth),
+            survival:     Counts::Mask(survival),
+            states:       2,
+            range:        1,
+            neighborhood: Neighborhood::Moore,
+            middle:       false,
+            teams:        1,
+        }
+    }
+
+    /// Builds a rule from the neighbor counts that cause a birth and the counts that let a live
+    /// cell survive. Counts above 8 are rejected.
+    pub fn new(
+        birth: &[usize],
+        survival: &[usize],
+    ) -> Result<Self, Error> {
+        let mask = |counts: &[usize]| {
+            counts.iter().try_fold(0u16, |mask, &count| {
+                if count <= Self::MAX_NEIGHBORS {
+                    Ok(mask | (1 << count))
+                } else {
+                    Err(Error::ParseRule {
+                        rule:   format!("{:?}/{:?}", birth, survival),
+                        reason: "neighbor counts must be in 0..=8",
+                    })
+                }
+            })
+        };
+
+        Ok(Self::from_masks(mask(birth)?, mask(survival)?))
+    }
+
+    /// Builds a Larger than Life rule counting the live cells of `neighborhood` out to `range`, and
+    /// the cell itself too when `middle` is set. Ranges outside `1..=500` are rejected, as are
+    /// triangular neighborhoods past range 1.
+    pub fn larger_than_life(
+        range: usize,
+        neighborhood: Neighborhood,
+        middle: bool,
+        birth: RangeInclusive<usize>,
+        survival: RangeInclusive<usize>,
+    ) -> Result<Self, Error> {
+        if !(1..=Self::MAX_RANGE).contains(&range) {
+            return Err(Error::ParseRule {
+                rule:   format!("R{},N{}", range, neighborhood.code()),
+                reason: "the range must be in 1..=500",
+            });
+        }
+        if range > 1 && neighborhood == Neighborhood::Triangular {
+            return Err(Error::ParseRule {
+                rule:   format!("R{},N{}", range, neighborhood.code()),
+                reason: "triangular neighborhoods only have range 1",
+            });
+        }
+
+        let named = neighborhood == Neighborhood::Moore
+            || Self::SUFFIXES.iter().any(|&(_, suffixed)| suffixed == neighborhood);
+        if range == 1 && named && neighborhood.size(1) <= Self::MAX_NEIGHBORS {
+            let mask = |counts: &RangeInclusive<usize>, offset: usize| {
+                (0..=neighborhood.size(1))
+                    .filter(|&count| counts.contains(&(count + offset)))
+                    .fold(0u16, |mask, count| mask | (1 << count))
+            };
+            return Ok(Self {
+                neighborhood,
+                ..Self::from_masks(mask(&birth, 0), mask(&survival, middle as usize))
+            });
+        }
+
+        let counts = |counts: RangeInclusive<usize>| {
+            let clamp = |count: usize| count.min(u32::MAX as usize) as u32;
+            Counts::Range {
+                min: clamp(*counts.start()),
+                max: clamp(*counts.end()),
+            }
+        };
+        Ok(Self {
+            birth: counts(birth),
+            survival: counts(survival),
+            states: 2,
+            range: range as u16,
+            neighborhood,
+            middle,
+            teams: 1,
+        })
+    }
+
+    /// The same birth and survival conditions with `states` cell states, making a Generations rule
+    /// when there are more than two. Counts outside `2..=256` are rejected.
+    pub fn with_states(
+        self,
+        states: usize,
+    ) -> Result<Self, Error> {
+        if (2..=Self::MAX_STATES).contains(&states) {
+            Ok(Self {
+                states: states as u16,
+                ..self
+            })
+        } else {
+            Err(Error::ParseRule {
+                rule:   format!("{}/C{}", Self { states: 2, ..self }, states),
+                reason: "the number of states must be in 2..=256",
+            })
+        }
+    }
+
+    /// The same birth and survival conditions with `teams` teams of live cells, making a colored rule
+    /// when there are more than one. Only two state rules of range 1 over a square or hexagonal
+    /// grid can be colored, and counts outside `1..=4` are rejected.
+    pub fn with_teams(
+        self,
+        teams: usize,
+    ) -> Result<Self, Error> {
+        let invalid = |reason| {
+            Err(Error::ParseRule {
+                rule: format!("{}/T{}", Self { teams: 1, ..self }, teams),
+                reason,
+            })
+        };
+        if !(1..=Self::MAX_TEAMS).contains(&teams) {
+            invalid("the number of teams must be in 1..=4")
+        } else if teams > 1 && (self.states > 2 || self.range > 1 || self.grid() == Grid::Triangular) {
+            invalid("only two state rules of range 1 over square or hexagonal cells can have teams")
+        } else {
+            Ok(Self {
+                teams: teams as u8,
+                ..self
+            })
+        }
+    }
+
+    /// How many states a cell can be in: 2 for Life-like rules, more for Generations rules.
+    pub fn states(&self) -> usize {
+        self.states as usize
+    }
+
+    /// How far the neighborhood reaches from a cell, 1 for Life-like rules.
+    pub fn range(&self) -> usize {
+        self.range as usize
+    }
+
+    pub fn neighborhood(&self) -> Neighborhood {
+        self.neighborhood
+    }
+
+    /// The tiling the rule's neighborhood is laid over.
+    pub fn grid(&self) -> Grid {
+        self.neighborhood.grid()
+    }
+
+    /// How many teams live cells belong to: 1 unless the rule is colored.
+    pub fn teams(&self) -> usize {
+        self.teams as usize
+    }
+
+    /// Whether a live cell counts itself among its live neighbors.
+    pub fn middle(&self) -> bool {
+        self.middle
+    }
+
+    /// Whether the rule only looks at the eight Moore neighbors, so that it can be written in B/S
+    /// notation.
+    pub fn is_life_like(&self) -> bool {
+        self.range == 1 && self.neighborhood == Neighborhood::Moore
+    }
+
+    pub fn born(
+        &self,
+        alive_neighbors: usize,
+    ) -> bool {
+        self.birth.contains(alive_neighbors)
+    }
+
+    /// Whether a live cell with `alive_neighbors` survives, where the count includes the cell itself
+    /// when [`Rule::middle`] is set.
+    pub fn survives(
+        &self,
+        alive_neighbors: usize,
+    ) -> bool {
+        self.survival.contains(alive_neighbors)
+    }
+
+    /// The next life state of a cell given its current state and live neighbor count.
+    pub fn next(
+        &self,
+        alive: bool,
+        alive_neighbors: usize,
+    ) -> bool {
+        if alive {
+            self.survives(alive_neighbors)
+        } else {
+            self.born(alive_neighbors)
+        }
+    }
+
+    /// The next state of a cell, where 0 is dead, 1 alive and anything higher dying, given its
+    /// current state and live neighbor count.
+    pub fn next_state(
+        &self,
+        state: usize,
+        alive_neighbors: usize,
+    ) -> usize {
+        match state {
+            0 if self.born(alive_neighbors) => 1,
+            0 => 0,
+            1 if self.survives(alive_neighbors) => 1,
+            state => (state + 1) % self.states(),
+        }
+    }
+
+    fn parse_counts(
+        rule: &str,
+        digits: &str,
+        neighbors: usize,
+    ) -> Result<u16, Error> {
+        digits.chars().try_fold(0u16, |mask, ch| {
+            match ch.to_digit(16) {
+                Some(count) if mask & (1 << count) != 0 => {
+                    Err(Error::ParseRule {
+                        rule:   rule.to_string(),
+                        reason: "each neighbor count may only be given once",
+                    })
+                }
+                Some(count) if count as usize <= neighbors => Ok(mask | (1 << count)),
+                _ => {
+                    Err(Error::ParseRule {
+                        rule:   rule.to_string(),
+                        reason: "neighbor counts must be digits no greater than the number of neighbors",
+                    })
+                }
+            }
+        })
+    }
+
+    /// Parses a `R5,C0,M1,S34..58,B34..45,NM` Larger than Life rulestring. Only the range, survival
+    /// and birth are required: the number of states defaults to 2, the middle to 0 and the
+    /// neighborhood to Moore. Counts are written `min..max`, `min-max` or as a single number.
+    fn parse_larger_than_life(rule: &str) -> Result<Self, Error> {
+        let invalid = |reason| {
+            Error::ParseRule {
+                rule: rule.to_string(),
+                reason,
+            }
+        };
+        let number = |value: &str| value.trim().parse::<usize>().map_err(|_| invalid("expected a number"));
+        let counts = |value: &str| {
+            match value.split_once("..").or_else(|| value.split_once('-')) {
+                Some((min, max)) => {
+                    let (min, max) = (number(min)?, number(max)?);
+                    if min > max {
+                        return Err(invalid("a count range must not end below its start"));
+                    }
+                    Ok(min..=max)
+                }
+                None => number(value).map(|count| count..=count),
+            }
+        };
+
+        let (mut range, mut states, mut middle, mut survival, mut birth, mut neighborhood) =
+            (None, None, None, None, None, None);
+        for part in rule.trim().split(',') {
+            let mut chars = part.trim().chars();
+            let slot = match chars.next().map(|ch| ch.to_ascii_lowercase()) {
+                Some('r') => &mut range,
+                Some('c') => &mut states,
+                Some('m') => &mut middle,
+                Some('s') => &mut survival,
+                Some('b') => &mut birth,
+                Some('n') => &mut neighborhood,
+                _ => return Err(invalid("expected an 'R', 'C', 'M', 'S', 'B' or 'N' part")),
+            };
+            if slot.replace(chars.as_str()).is_some() {
+                return Err(invalid("the same part appears twice"));
+            }
+        }
+
+        let neighborhood = match neighborhood {
+            Some(code) => {
+                let mut chars = code.chars();
+                match (chars.next().and_then(Neighborhood::from_code), chars.next()) {
+                    (Some(neighborhood), None) => neighborhood,
+                    _ => return Err(invalid("unknown neighborhood")),
+                }
+            }
+            None => Neighborhood::Moore,
+        };
+        let middle = match middle.map(str::trim) {
+            None | Some("0") => false,
+            Some("1") => true,
+            Some(_) => return Err(invalid("the middle must be 0 or 1")),
+        };
+        let (range, survival, birth) = match (range, survival, birth) {
+            (Some(range), Some(survival), Some(birth)) => (number(range)?, counts(survival)?, counts(birth)?),
+            _ => return Err(invalid("expected an 'R' part, an 'S' part and a 'B' part")),
+        };
+
+        let parsed = Self::larger_than_life(range, neighborhood, middle, birth, survival).map_err(|err| {
+            match err {
+                Error::ParseRule { reason, .. } => invalid(reason),
+                err => err,
+            }
+        })?;
+        match states.map(number).transpose()? {
+            Some(states) if states > 2 => {
+                parsed.with_states(states).map_err(|_| invalid("the number of states must be in 2..=256"))
+            }
+            _ => Ok(parsed),
+        }
+    }
+}
+
+impl std::default::Default for Rule {
+    fn default() -> Self {
+        Rule::CONWAY
+    }
+}
+
+/// Parses `B3/S23` style rulestrings, case insensitively and with the parts in any order, as well as
+/// the older `23/3` survival/birth form. A third part gives the number of states of a Generations
+/// rule, as in `B2/S/C3` (or `G3`) and the older `345/2/4`, or the number of teams of a colored rule,
+/// as in `B3/S23/T2`, and a `V`, `H` or `L` at the very end picks the neighborhood. `Immigration`
+/// and `QuadLife` are accepted by name. Larger than Life rules are written with comma separated
+/// parts, as in `R5,C0,M1,S34..58,B34..45,NM`.
+impl FromStr for Rule {
+    type Err = Error;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        if rule.contains(',') {
+            return Self::parse_larger_than_life(rule);
+        }
+        for named in &[Self::IMMIGRATION, Self::QUADLIFE] {
+            if named.to_string().eq_ignore_ascii_case(rule.trim()) {
+                return Ok(*named);
+            }
+        }
+        let invalid = |reason| {
+            Error::ParseRule {
+                rule: rule.to_string(),
+                reason,
+            }
+        };
+
+        let mut rest = rule.trim();
+        let mut neighborhood = Neighborhood::Moore;
+        for &(suffix, suffixed) in &Self::SUFFIXES {
+            if let Some(stripped) = rest.strip_suffix(|ch: char| ch.to_ascii_uppercase() == suffix) {
+                rest = stripped;
+                neighborhood = suffixed;
+                break;
+            }
+        }
+
+        let parts: Vec<_> = rest.split('/').collect();
+        if parts.len() != 2 && parts.len() != 3 {
+            return Err(invalid("expected two or three parts separated by '/'"));
+        }
+
+        let (birth, survival, states, teams) = if parts.iter().all(|part| part.starts_with(char::is_alphabetic)) {
+            let (mut birth, mut survival, mut states, mut teams) = (None, None, None, None);
+            for part in &parts {
+                let mut chars = part.chars();
+                let slot = match chars.next().map(|ch| ch.to_ascii_lowercase()) {
+                    Some('b') => &mut birth,
+                    Some('s') => &mut survival,
+                    Some('c') | Some('g') => &mut states,
+                    Some('t') => &mut teams,
+                    _ => return Err(invalid("expected a 'B', 'S', 'C' or 'T' part")),
+                };
+                if slot.replace(chars.as_str()).is_some() {
+                    return Err(invalid("the same part appears twice"));
+                }
+            }
+            match (birth, survival) {
+                (Some(birth), Some(survival)) => (birth, survival, states, teams),
+                _ => return Err(invalid("expected a 'B' part and an 'S' part")),
+            }
+        } else if parts.iter().all(|part| !part.starts_with(char::is_alphabetic)) {
+            (parts[1], parts[0], parts.get(2).copied(), None)
+        } else {
+            return Err(invalid("expected a 'B' part and an 'S' part"));
+        };
+
+        let neighbors = neighborhood.size(1);
+        let parsed = Self {
+            neighborhood,
+            ..Self::from_masks(
+                Self::parse_counts(rule, birth, neighbors)?,
+                Self::parse_counts(rule, survival, neighbors)?,
+            )
+        };
+        let parsed = match states {
+            Some(states) => {
+                let states = states
+                    .parse()
+                    .map_err(|_| invalid("the number of states must be a number"))?;
+                parsed.with_states(states).map_err(|_| invalid("the number of states must be in 2..=256"))?
+            }
+            None => parsed,
+        };
+        match teams {
+            Some(teams) => {
+                let teams = teams.parse().map_err(|_| invalid("the number of teams must be a number"))?;
+                parsed.with_teams(teams).map_err(|err| {
+                    match err {
+                        Error::ParseRule { reason, .. } => invalid(reason),
+                        err => err,
+                    }
+                })
+            }
+            None => Ok(parsed),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        if let Counts::Range { .. } = self.birth {
+            let counts = |counts: &Counts| {
+                match *counts {
+                    Counts::Range { min, max } => format!("{}..{}", min, max),
+                    Counts::Mask(_) => unreachable!("birth and survival are written the same way"),
+                }
+            };
+            return write!(
+                f,
+                "R{},C{},M{},S{},B{},N{}",
+                self.range,
+                if self.states > 2 { self.states } else { 0 },
+                self.middle as u8,
+                counts(&self.survival),
+                counts(&self.birth),
+                self.neighborhood.code(),
+            );
+        }
+
+        if *self == Self::IMMIGRATION {
+            return f.write_str("Immigration");
+        } else if *self == Self::QUADLIFE {
+            return f.write_str("QuadLife");
+        }
+
+        let neighbors = self.neighborhood.size(1);
+        write!(f, "B")?;
+        for count in (0..=neighbors).filter(|&n| self.born(n)) {
+            write!(f, "{:x}", count)?;
+        }
+        write!(f, "/S")?;
+        for count in (0..=neighbors).filter(|&n| self.survives(n)) {
+            write!(f, "{:x}", count)?;
+        }
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        if self.teams > 1 {
+            write!(f, "/T{}", self.teams)?;
+        }
+        match Self::SUFFIXES.iter().find(|&&(_, suffixed)| suffixed == self.neighborhood) {
+            Some((suffix, _)) => write!(f, "{}", suffix),
+            None => Ok(()),
+        }
+    }
+}
+
+impl TryFrom<String> for Rule {
+    type Error = Error;
+
+    fn try_from(rule: String) -> Result<Self, Self::Error> {
+        rule.parse()
+    }
+}
+
+impl From<Rule> for String {
+    fn from(rule: Rule) -> Self {
+        rule.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid(rule: &str) -> bool {
+        matches!(rule.parse::<Rule>(), Err(Error::ParseRule { .. }))
+    }
+
+    #[test]
+    fn life_like_rules_parse() {
+        let conway: Rule = "B3/S23".parse().unwrap();
+        assert_eq!(conway, Rule::CONWAY);
+        assert!(conway.born(3) && !conway.born(2) && !conway.born(6));
+        assert!(conway.survives(2) && conway.survives(3) && !conway.survives(4));
+
+        for rule in &["23/3", "S23/B3", "b3/s23", " B3/S23 "] {
+            assert_eq!(rule.parse::<Rule>().unwrap(), conway, "{}", rule);
+        }
+
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        assert!(highlife.born(3) && highlife.born(6) && !highlife.born(4));
+        assert!(highlife.survives(2) && highlife.survives(3) && !highlife.survives(6));
+        assert_eq!("S23/B63".parse::<Rule>().unwrap(), highlife);
+        assert_eq!(Rule::new(&[3, 6], &[2, 3]).unwrap(), highlife);
+    }
+
+    #[test]
+    fn life_like_rules_display_round_trip() {
+        for rule in &["B3/S23", "B36/S23", "B/S", "B012345678/S012345678", "B2/S/C3", "B36/S23/T2", "B2/S34H"] {
+            let parsed: Rule = rule.parse().unwrap();
+            assert_eq!(parsed.to_string(), *rule);
+            assert_eq!(parsed.to_string().parse::<Rule>().unwrap(), parsed);
+        }
+        assert_eq!("23/3".parse::<Rule>().unwrap().to_string(), "B3/S23");
+        assert_eq!("B3/S23/T2".parse::<Rule>().unwrap().to_string(), "Immigration");
+        assert_eq!("S23/B63".parse::<Rule>().unwrap().to_string(), "B36/S23");
+    }
+
+    #[test]
+    fn rules_serialize_as_strings() {
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        assert_eq!(serde_json::to_string(&highlife).unwrap(), r#""B36/S23""#);
+        assert_eq!(serde_json::from_str::<Rule>(r#""S23/B36""#).unwrap(), highlife);
+        assert!(serde_json::from_str::<Rule>(r#""B9/S23""#).is_err());
+        assert!(serde_json::from_str::<Rule>("36").is_err());
+    }
+
+    #[test]
+    fn bad_life_like_rules_are_rejected() {
+        for rule in &[
+            "B9/S23", "B3/S239", "", " ", "B3", "B3/S23/C2/X", "B33/S23", "B3/S232", "332/3", "B3/B3", "B3/23",
+        ] {
+            assert!(invalid(rule), "{:?}", rule);
+        }
+        assert!(Rule::new(&[9], &[2, 3]).is_err());
+    }
+
+    #[test]
+    fn larger_than_life_rules_parse() {
+        let bosco: Rule = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+        assert_eq!(bosco.range(), 5);
+        assert_eq!(bosco.states(), 2);
+        assert!(bosco.middle());
+        assert_eq!(bosco.neighborhood(), Neighborhood::Moore);
+        assert!(!bosco.born(33) && bosco.born(34) && bosco.born(45) && !bosco.born(46));
+        assert!(!bosco.survives(33) && bosco.survives(34) && bosco.survives(58) && !bosco.survives(59));
+
+        assert_eq!("R5,C0,M1,S34-58,B34-45,NM".parse::<Rule>().unwrap(), bosco);
+        assert_eq!("r5, s34..58, b34..45, m1".parse::<Rule>().unwrap(), bosco);
+
+        let generations: Rule = "R2,C3,S4..7,B5,NN".parse().unwrap();
+        assert_eq!(generations.states(), 3);
+        assert_eq!(generations.neighborhood(), Neighborhood::VonNeumann);
+        assert!(generations.born(5) && !generations.born(6));
+    }
+
+    #[test]
+    fn larger_than_life_rules_display_round_trip() {
+        for rule in &["R5,C0,M1,S34..58,B34..45,NM", "R2,C3,M0,S4..7,B5..5,NN", "R500,C0,M0,S1..1,B1..2,NH"] {
+            let parsed: Rule = rule.parse().unwrap();
+            assert_eq!(parsed.to_string(), *rule);
+            assert_eq!(parsed.to_string().parse::<Rule>().unwrap(), parsed);
+        }
+        assert_eq!("R5,M1,S34-58,B34-45".parse::<Rule>().unwrap().to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+    }
+
+    #[test]
+    fn bad_larger_than_life_rules_are_rejected() {
+        for rule in &[
+            "R5,C0,M1,S58..34,B34..45,NM",
+            "R5,S34..,B34..45",
+            "R5,S..58,B34..45",
+            "R5,S34..58..60,B34..45",
+            "R5,S-4,B34..45",
+            "R5,Sx,B34..45",
+            "R5,S34..58",
+            "R5,S34..58,B34..45,M2",
+            "R5,S34..58,B34..45,NQ",
+            "R5,R6,S34..58,B34..45",
+            "R0,S1,B1",
+            "R501,S1,B1",
+            "R-1,S1,B1",
+            "R2,S1,B1,N3",
+        ] {
+            assert!(invalid(rule), "{}", rule);
+        }
+    }
+}
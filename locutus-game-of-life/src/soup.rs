@@ -0,0 +1,312 @@
+//! Reproducible random starting patterns, or soups, in the style of [apgsearch].
+//!
+//! [apgsearch]: https://conwaylife.com/wiki/Apgsearch
+
+use crate::{
+    deps::serde,
+    Bounds,
+    Error,
+    Pattern,
+    Simulation,
+};
+use std::{
+    fmt,
+    str::FromStr,
+};
+
+/// The symmetry imposed on a soup, named after the apgsearch symmetry families.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Symmetry {
+    /// No symmetry at all.
+    #[default]
+    C1,
+    /// Unchanged by a half turn.
+    C2,
+    /// Unchanged by a quarter turn. Needs a square region.
+    C4,
+    /// Mirrored left to right.
+    D2,
+    /// Mirrored left to right and top to bottom.
+    D4,
+    /// Unchanged by every rotation and reflection of the square. Needs a square region.
+    D8,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 6] = [
+        Symmetry::C1,
+        Symmetry::C2,
+        Symmetry::C4,
+        Symmetry::D2,
+        Symmetry::D4,
+        Symmetry::D8,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Symmetry::C1 => "C1",
+            Symmetry::C2 => "C2",
+            Symmetry::C4 => "C4",
+            Symmetry::D2 => "D2",
+            Symmetry::D4 => "D4",
+            Symmetry::D8 => "D8",
+        }
+    }
+
+    /// Whether the symmetry swaps rows with columns and so only fits a square.
+    fn needs_square(&self) -> bool {
+        matches!(self, Symmetry::C4 | Symmetry::D8)
+    }
+
+    /// Every cell that `(x, y)` of a `width` by `height` region is mapped to, itself included.
+    fn orbit(
+        &self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> Vec<(usize, usize)> {
+        let (far_x, far_y) = (width - 1 - x, height - 1 - y);
+        match self {
+            Symmetry::C1 => vec![(x, y)],
+            Symmetry::C2 => vec![(x, y), (far_x, far_y)],
+            Symmetry::C4 => vec![(x, y), (far_y, x), (far_x, far_y), (y, far_x)],
+            Symmetry::D2 => vec![(x, y), (far_x, y)],
+            Symmetry::D4 => vec![(x, y), (far_x, y), (x, far_y), (far_x, far_y)],
+            Symmetry::D8 => {
+                vec![
+                    (x, y),
+                    (far_y, x),
+                    (far_x, far_y),
+                    (y, far_x),
+                    (far_x, y),
+                    (x, far_y),
+                    (y, x),
+                    (far_y, far_x),
+                ]
+            }
+        }
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = Error;
+
+    fn from_str(symmetry: &str) -> Result<Self, Self::Err> {
+        Symmetry::ALL
+            .iter()
+            .copied()
+            .find(|candidate| candidate.as_str().eq_ignore_ascii_case(symmetry.trim()))
+            .ok_or_else(|| Error::ParseSymmetry(symmetry.to_string()))
+    }
+}
+
+/// The recipe for a random fill: the same seed, density, symmetry and region always produce the
+/// same cells, on every platform and release, so a start can be shared and replayed.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Soup {
+    pub seed:     u64,
+    /// The chance of each independent cell being alive, from 0 to 1.
+    pub density:  f64,
+    pub symmetry: Symmetry,
+    /// The part of the board to overwrite, or `None` for the whole board.
+    pub region:   Option<Bounds>,
+}
+
+impl Soup {
+    /// An asymmetric soup filling the whole board.
+    pub fn new(
+        seed: u64,
+        density: f64,
+    ) -> Self {
+        Self {
+            seed,
+            density,
+            symmetry: Symmetry::default(),
+            region: None,
+        }
+    }
+
+    /// Generates a `width` by `height` block of soup.
+    ///
+    /// Cells are visited in row major order and a cell draws from the generator only when it is the
+    /// first of its orbit under the symmetry, after which the whole orbit takes its value.
+    pub fn generate(
+        &self,
+        width: usize,
+        height: usize,
+    ) -> Result<Pattern, Error> {
+        if !(0.0..=1.0).contains(&self.density) {
+            return Err(Error::InvalidSoup {
+                reason: "density must be between 0 and 1",
+            });
+        }
+        if self.symmetry.needs_square() && width != height {
+            return Err(Error::InvalidSoup {
+                reason: "symmetry needs a square region",
+            });
+        }
+
+        let mut rng = SplitMix64(self.seed);
+        let mut pattern = Pattern::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let orbit = self.symmetry.orbit(x, y, width, height);
+                if orbit.iter().any(|&(ox, oy)| (oy, ox) < (y, x)) {
+                    continue;
+                }
+                let alive = rng.next_f64() < self.density;
+                for (ox, oy) in orbit {
+                    pattern.set(ox, oy, alive);
+                }
+            }
+        }
+
+        Ok(pattern)
+    }
+}
+
+/// The [SplitMix64] generator. It is fixed here rather than taken from `rand` because `rand` does
+/// not promise that its generators keep their output across releases.
+///
+/// [SplitMix64]: https://prng.di.unimi.it/splitmix64.c
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniform value in `[0, 1)` built from the top 53 bits of the next output.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Simulation {
+    /// Overwrites the soup's region, or the whole board when it has none, with the soup.
+    pub fn fill_soup(
+        &mut self,
+        soup: &Soup,
+    ) -> Result<(), Error> {
+        let (width, height) = (self.width() as i64, self.height() as i64);
+        let region = soup.region.unwrap_or_else(|| Bounds::new(0, 0, width - 1, height - 1));
+        self.check_region(region)?;
+
+        let pattern = soup.generate(region.width() as usize, region.height() as usize)?;
+        let (left, top) = (region.min_x as usize, region.min_y as usize);
+        self.replace_cellules(|cellules| {
+            for y in 0..pattern.height() {
+                for x in 0..pattern.width() {
+                    cellules.set(left + x, top + y, pattern.get(x, y));
+                }
+            }
+        });
+        self.board_edited();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Message;
+
+    fn soup(
+        seed: u64,
+        symmetry: Symmetry,
+    ) -> Soup {
+        Soup {
+            symmetry,
+            ..Soup::new(seed, 0.5)
+        }
+    }
+
+    fn cells(pattern: &Pattern) -> Vec<bool> {
+        (0..pattern.height()).flat_map(|y| (0..pattern.width()).map(move |x| pattern.get(x, y))).collect()
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_board() {
+        for &symmetry in &Symmetry::ALL {
+            let first = soup(7, symmetry).generate(16, 16).unwrap();
+            assert_eq!(soup(7, symmetry).generate(16, 16).unwrap(), first, "{}", symmetry);
+            assert_ne!(soup(8, symmetry).generate(16, 16).unwrap(), first, "{}", symmetry);
+        }
+
+        let mut simulation = Simulation::builder().width(16).height(12).build().unwrap();
+        simulation.fill_soup(&Soup::new(7, 0.5)).unwrap();
+        let filled = simulation.to_pattern();
+        simulation.fill_soup(&Soup::new(7, 0.5)).unwrap();
+        assert_eq!(simulation.to_pattern(), filled);
+        assert_eq!(cells(&filled), cells(&Soup::new(7, 0.5).generate(16, 12).unwrap()));
+    }
+
+    #[test]
+    fn each_symmetry_is_symmetric() {
+        let (n, w, h) = (12, 13, 10);
+        for seed in 0..8 {
+            let c2 = soup(seed, Symmetry::C2).generate(w, h).unwrap();
+            let d2 = soup(seed, Symmetry::D2).generate(w, h).unwrap();
+            let d4 = soup(seed, Symmetry::D4).generate(w, h).unwrap();
+            for y in 0..h {
+                for x in 0..w {
+                    assert_eq!(c2.get(x, y), c2.get(w - 1 - x, h - 1 - y));
+                    assert_eq!(d2.get(x, y), d2.get(w - 1 - x, y));
+                    assert_eq!(d4.get(x, y), d4.get(w - 1 - x, y));
+                    assert_eq!(d4.get(x, y), d4.get(x, h - 1 - y));
+                }
+            }
+
+            let c4 = soup(seed, Symmetry::C4).generate(n, n).unwrap();
+            let d8 = soup(seed, Symmetry::D8).generate(n, n).unwrap();
+            for y in 0..n {
+                for x in 0..n {
+                    assert_eq!(c4.get(x, y), c4.get(n - 1 - y, x));
+                    assert_eq!(d8.get(x, y), d8.get(n - 1 - y, x));
+                    assert_eq!(d8.get(x, y), d8.get(y, x));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn square_symmetries_need_a_square() {
+        for &symmetry in &[Symmetry::C4, Symmetry::D8] {
+            assert!(matches!(soup(1, symmetry).generate(8, 9), Err(Error::InvalidSoup { .. })));
+        }
+        assert!(Soup::new(1, 1.5).generate(8, 8).is_err());
+    }
+
+    #[test]
+    fn random_seeded_reads_from_json() {
+        let json = r#"{"RandomSeeded":{"seed":7,"density":0.5,"symmetry":"D4"}}"#;
+        let message: Message = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            message,
+            Message::RandomSeeded {
+                seed:     7,
+                density:  0.5,
+                symmetry: Symmetry::D4,
+            }
+        );
+
+        let mut simulation = Simulation::builder().width(10).height(10).build().unwrap();
+        simulation.update(message).unwrap();
+        assert_eq!(cells(&simulation.to_pattern()), cells(&soup(7, Symmetry::D4).generate(10, 10).unwrap()));
+    }
+}
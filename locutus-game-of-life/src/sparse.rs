@@ -0,0 +1,305 @@
+use crate::{
+    deps::{
+        serde,
+        tracing::info,
+    },
+    Bounds,
+    Control,
+    Error,
+    Pattern,
+    Rule,
+    State,
+    UniverseMessage,
+};
+use std::{
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    convert::TryFrom,
+};
+
+const NEIGHBORHOOD: [(i64, i64); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+/// An unbounded Life-like universe that stores only its live cells.
+///
+/// Unlike the toroidal [`crate::Simulation`] there are no edges: patterns keep growing for as long as
+/// they expand and spaceships fly off forever. Each step costs time proportional to the population
+/// rather than the area, and the serialized form holds only the live cells.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "SparseLifeRepr", into = "SparseLifeRepr")]
+pub struct SparseLife {
+    state:      State,
+    rule:       Rule,
+    generation: u64,
+    alive:      HashSet<(i64, i64)>,
+    counts:     HashMap<(i64, i64), u8>,
+}
+
+impl SparseLife {
+    /// An empty universe running `rule`.
+    ///
+    /// Rules with `B0` would turn on the infinite background in one generation, so they are
+    /// rejected, and so are Generations and colored rules since only the positions of live cells are
+    /// stored. Only the eight Moore neighbors are counted, which rules out Larger than Life rules as
+    /// well.
+    pub fn new(rule: Rule) -> Result<Self, Error> {
+        Self::check_rule(rule)?;
+        Ok(Self {
+            state: State::default(),
+            rule,
+            generation: 0,
+            alive: HashSet::new(),
+            counts: HashMap::new(),
+        })
+    }
+
+    /// Places `pattern` with its top left cell on the origin, running the pattern's rule if it names
+    /// one and Conway's Life otherwise.
+    pub fn from_pattern(pattern: &Pattern) -> Result<Self, Error> {
+        let mut universe = Self::new(pattern.rule().unwrap_or_default())?;
+        universe.alive = pattern.alive_cells().map(|(x, y)| (x as i64, y as i64)).collect();
+        Ok(universe)
+    }
+
+    fn check_rule(rule: Rule) -> Result<(), Error> {
+        if rule.born(0) || rule.states() > 2 || rule.teams() > 1 || !rule.is_life_like() {
+            Err(Error::Unsupported {
+                engine:    "SparseLife",
+                operation: format!("rule {}", rule),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn set_rule(
+        &mut self,
+        rule: Rule,
+    ) -> Result<(), Error> {
+        Self::check_rule(rule)?;
+        self.rule = rule;
+        Ok(())
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn population(&self) -> usize {
+        self.alive.len()
+    }
+
+    pub fn get(
+        &self,
+        x: i64,
+        y: i64,
+    ) -> bool {
+        self.alive.contains(&(x, y))
+    }
+
+    pub fn set(
+        &mut self,
+        x: i64,
+        y: i64,
+        alive: bool,
+    ) {
+        if alive {
+            self.alive.insert((x, y));
+        } else {
+            self.alive.remove(&(x, y));
+        }
+    }
+
+    /// The coordinates of every live cell, in no particular order.
+    pub fn alive_cells(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.alive.iter().copied()
+    }
+
+    /// The smallest rectangle holding every live cell, or `None` for an empty universe.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.alive
+            .iter()
+            .map(|&(x, y)| Bounds::new(x, y, x, y))
+            .fold(None, |acc: Option<Bounds>, cell| Some(acc.map_or(cell, |acc| acc.union(&cell))))
+    }
+
+    /// The live cells inside [`SparseLife::bounds`], along with the rule. Cells spread too far apart
+    /// for a [`Pattern`] to hold fail with [`Error::PatternTooLarge`].
+    pub fn to_pattern(&self) -> Result<Pattern, Error> {
+        let mut pattern = match self.bounds() {
+            Some(bounds) => {
+                let mut pattern = Pattern::for_bounds(bounds)?;
+                for &(x, y) in &self.alive {
+                    pattern.set((x - bounds.min_x) as usize, (y - bounds.min_y) as usize, true);
+                }
+                pattern
+            }
+            None => Pattern::new(0, 0),
+        };
+        pattern.set_rule(Some(self.rule));
+        Ok(pattern)
+    }
+
+    /// Kills every cell and rewinds the generation counter.
+    pub fn clear(&mut self) {
+        self.alive.clear();
+        self.generation = 0;
+    }
+
+    /// Advances the universe one generation. Coordinates wrap at the limits of an `i64`.
+    pub fn step(&mut self) {
+        self.counts.clear();
+        for &(x, y) in &self.alive {
+            for &(dx, dy) in &NEIGHBORHOOD {
+                *self.counts.entry((x.wrapping_add(dx), y.wrapping_add(dy))).or_insert(0) += 1;
+            }
+        }
+
+        let rule = self.rule;
+        let alive = &self.alive;
+        let mut next: HashSet<_> = self
+            .counts
+            .iter()
+            .filter(|&(cell, &count)| rule.next(alive.contains(cell), count as usize))
+            .map(|(&cell, _)| cell)
+            .collect();
+        if rule.survives(0) {
+            next.extend(alive.iter().filter(|cell| !self.counts.contains_key(cell)));
+        }
+
+        self.alive = next;
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    pub fn advance(
+        &mut self,
+        generations: u64,
+    ) {
+        for _ in 0..generations {
+            self.step();
+        }
+    }
+
+    /// Drives the universe with a [`UniverseMessage`].
+    pub fn update(
+        &mut self,
+        msg: UniverseMessage,
+    ) -> Result<(), Error> {
+        use State::*;
+        if self.state.ended() {
+            return Err(Error::GameOver);
+        }
+
+        match msg {
+            UniverseMessage::Control(Control::Start) => {
+                self.state.try_transition(Running)?;
+                info!("Start");
+            }
+            UniverseMessage::Control(Control::Step) => {
+                self.step();
+            }
+            UniverseMessage::Control(Control::Reset) => {
+                self.clear();
+                info!("Reset");
+            }
+            UniverseMessage::Control(Control::Stop) => {
+                self.state.try_transition(Pausing)?;
+                info!("Stop");
+            }
+            UniverseMessage::SetRule(rule) => {
+                self.set_rule(rule)?;
+                info!("SetRule {}", rule);
+            }
+            UniverseMessage::Control(Control::Tick) => {
+                if self.state.run() {
+                    self.step();
+                }
+            }
+            UniverseMessage::Control(Control::End) => {
+                self.state.try_transition(Ended)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The wire form of a [`SparseLife`]: just the live cells, sorted so equal universes serialize
+/// identically.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SparseLifeRepr {
+    state:      State,
+    rule:       Rule,
+    generation: u64,
+    alive:      Vec<(i64, i64)>,
+}
+
+impl From<SparseLife> for SparseLifeRepr {
+    fn from(universe: SparseLife) -> Self {
+        let mut alive: Vec<_> = universe.alive.into_iter().collect();
+        alive.sort_unstable_by_key(|&(x, y)| (y, x));
+        Self {
+            state: universe.state,
+            rule: universe.rule,
+            generation: universe.generation,
+            alive,
+        }
+    }
+}
+
+/// Rejects the rules [`SparseLife::new`] does, since a deserialized universe may come from anywhere.
+impl TryFrom<SparseLifeRepr> for SparseLife {
+    type Error = Error;
+
+    fn try_from(repr: SparseLifeRepr) -> Result<Self, Self::Error> {
+        Self::check_rule(repr.rule)?;
+        Ok(Self {
+            state:      repr.state,
+            rule:       repr.rule,
+            generation: repr.generation,
+            alive:      repr.alive.into_iter().collect(),
+            counts:     HashMap::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn far_apart_cells_do_not_fit_a_pattern() {
+        let mut universe = SparseLife::new(Rule::default()).unwrap();
+        universe.set(-3, 0, true);
+        universe.set(i64::MAX, i64::MIN, true);
+        assert!(matches!(universe.to_pattern(), Err(Error::PatternTooLarge { .. })));
+
+        universe.set(i64::MAX, i64::MIN, false);
+        assert_eq!(universe.to_pattern().unwrap().alive_cells().collect::<Vec<_>>(), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn deserializing_checks_the_rule() {
+        let mut universe = SparseLife::new("B36/S23".parse().unwrap()).unwrap();
+        universe.set(2, -1, true);
+        universe.set(-5, 7, true);
+        let json = serde_json::to_string(&universe).unwrap();
+        let read: SparseLife = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.rule(), universe.rule());
+        assert_eq!(read.bounds(), universe.bounds());
+
+        for rule in &["B3/S23/3", "B03/S23", "QuadLife", "R2,C0,M1,S4..7,B5..7,NM"] {
+            let json = json.replace("B36/S23", rule);
+            assert!(serde_json::from_str::<SparseLife>(&json).is_err(), "{}", rule);
+        }
+    }
+}
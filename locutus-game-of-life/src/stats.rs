@@ -0,0 +1,93 @@
+use crate::{
+    deps::serde,
+    Bounds,
+};
+use std::collections::{
+    vec_deque,
+    VecDeque,
+};
+
+/// A summary of one generation of a board.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Stats {
+    /// How many steps led to this generation.
+    pub generation: u64,
+    pub population: usize,
+    /// Cells that came alive in the step that led to this generation.
+    pub births:     usize,
+    /// Cells that died in the step that led to this generation.
+    pub deaths:     usize,
+    /// The smallest rectangle holding every live cell, or `None` for an empty board.
+    pub bounds:     Option<Bounds>,
+}
+
+/// The [`Stats`] of the last `capacity` generations, oldest first.
+#[derive(Debug, Clone)]
+pub struct StatsSeries {
+    capacity: usize,
+    stats:    VecDeque<Stats>,
+}
+
+impl StatsSeries {
+    pub const DEFAULT_CAPACITY: usize = 1 << 10;
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            stats: VecDeque::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.stats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stats.is_empty()
+    }
+
+    pub fn latest(&self) -> Option<&Stats> {
+        self.stats.back()
+    }
+
+    pub fn iter(&self) -> vec_deque::Iter<'_, Stats> {
+        self.stats.iter()
+    }
+
+    /// Appends `stats`, dropping the oldest entry once the series is full.
+    pub fn push(
+        &mut self,
+        stats: Stats,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.stats.len() == self.capacity {
+            self.stats.pop_front();
+        }
+        self.stats.push_back(stats);
+    }
+
+    pub fn clear(&mut self) {
+        self.stats.clear();
+    }
+}
+
+impl std::default::Default for StatsSeries {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl<'a> IntoIterator for &'a StatsSeries {
+    type IntoIter = vec_deque::Iter<'a, Stats>;
+    type Item = &'a Stats;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
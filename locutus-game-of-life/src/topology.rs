@@ -0,0 +1,336 @@
+use crate::{
+    deps::serde,
+    Error,
+};
+use std::{
+    fmt,
+    str::FromStr,
+};
+
+/// How the edges of a bounded board are glued together, which decides the neighbors of the cells
+/// along them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Topology {
+    /// Opposite edges are joined, so whatever leaves one side comes back on the other.
+    #[default]
+    Torus,
+    /// Everything past the edges is permanently dead.
+    Plane,
+    /// Every edge is a mirror, so the cells just past it are reflections of the cells just inside.
+    Mirror,
+    /// Left and right edges are joined as on a torus, top and bottom with a left to right twist.
+    KleinBottle,
+    /// Both pairs of opposite edges are joined with a twist, making the real projective plane.
+    CrossSurface,
+}
+
+impl Topology {
+    pub const ALL: [Topology; 5] = [
+        Topology::Torus,
+        Topology::Plane,
+        Topology::Mirror,
+        Topology::KleinBottle,
+        Topology::CrossSurface,
+    ];
+
+    /// Maps the possibly off board coordinate `(x, y)` of a `width` by `height` board to the cell
+    /// it stands for, or `None` when it lies in the dead space around a [`Topology::Plane`].
+    pub fn resolve(
+        &self,
+        x: isize,
+        y: isize,
+        width: usize,
+        height: usize,
+    ) -> Option<(usize, usize)> {
+        let (w, h) = (width as isize, height as isize);
+        let wrap = |coord: isize, range: isize| coord.rem_euclid(range) as usize;
+        let crossings_odd = |coord: isize, range: isize| coord.div_euclid(range) % 2 != 0;
+
+        match self {
+            Topology::Torus => Some((wrap(x, w), wrap(y, h))),
+            Topology::Plane => {
+                if 0 <= x && x < w && 0 <= y && y < h {
+                    Some((x as usize, y as usize))
+                } else {
+                    None
+                }
+            }
+            Topology::Mirror => {
+                let reflect = |coord: isize, range: isize| {
+                    let folded = wrap(coord, 2 * range);
+                    if folded < range as usize {
+                        folded
+                    } else {
+                        2 * range as usize - 1 - folded
+                    }
+                };
+                Some((reflect(x, w), reflect(y, h)))
+            }
+            Topology::KleinBottle => {
+                let mut col = wrap(x, w);
+                if crossings_odd(y, h) {
+                    col = width - 1 - col;
+                }
+                Some((col, wrap(y, h)))
+            }
+            Topology::CrossSurface => {
+                let (mut col, mut row) = (wrap(x, w), wrap(y, h));
+                if crossings_odd(y, h) {
+                    col = width - 1 - col;
+                }
+                if crossings_odd(x, w) {
+                    row = height - 1 - row;
+                }
+                Some((col, row))
+            }
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Topology::Torus => "torus",
+            Topology::Plane => "plane",
+            Topology::Mirror => "mirror",
+            Topology::KleinBottle => "klein-bottle",
+            Topology::CrossSurface => "cross-surface",
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Topology {
+    type Err = Error;
+
+    fn from_str(topology: &str) -> Result<Self, Self::Err> {
+        Topology::ALL
+            .iter()
+            .copied()
+            .find(|candidate| candidate.as_str().eq_ignore_ascii_case(topology.trim()))
+            .ok_or_else(|| Error::ParseTopology(topology.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Blend,
+        Message,
+        Pattern,
+        Simulation,
+    };
+
+    /// An off board coordinate and the cell it should resolve to.
+    type Resolved = ((isize, isize), Option<(usize, usize)>);
+
+    /// Checks where each off board coordinate of a 4 by 3 board ends up, and that every cell on the
+    /// board stays put.
+    fn resolves(
+        topology: Topology,
+        expected: &[Resolved],
+    ) {
+        for &((x, y), cell) in expected {
+            assert_eq!(topology.resolve(x, y, 4, 3), cell, "{} ({}, {})", topology, x, y);
+        }
+        for y in 0..3 {
+            for x in 0..4 {
+                assert_eq!(topology.resolve(x as isize, y as isize, 4, 3), Some((x, y)), "{}", topology);
+            }
+        }
+    }
+
+    #[test]
+    fn torus_wraps_both_ways() {
+        resolves(Topology::Torus, &[
+            ((-1, -1), Some((3, 2))),
+            ((4, 3), Some((0, 0))),
+            ((4, -1), Some((0, 2))),
+            ((-1, 3), Some((3, 0))),
+            ((-1, 1), Some((3, 1))),
+            ((2, -1), Some((2, 2))),
+            ((-5, 7), Some((3, 1))),
+        ]);
+    }
+
+    #[test]
+    fn plane_has_nothing_past_the_edges() {
+        resolves(Topology::Plane, &[
+            ((-1, -1), None),
+            ((4, 3), None),
+            ((-1, 1), None),
+            ((4, 1), None),
+            ((2, -1), None),
+            ((2, 3), None),
+        ]);
+    }
+
+    #[test]
+    fn mirror_reflects_at_the_edges() {
+        resolves(Topology::Mirror, &[
+            ((-1, -1), Some((0, 0))),
+            ((4, 3), Some((3, 2))),
+            ((4, -1), Some((3, 0))),
+            ((-1, 3), Some((0, 2))),
+            ((-2, 1), Some((1, 1))),
+            ((2, -1), Some((2, 0))),
+            ((8, 6), Some((0, 0))),
+        ]);
+    }
+
+    #[test]
+    fn klein_bottle_twists_top_and_bottom() {
+        resolves(Topology::KleinBottle, &[
+            ((-1, -1), Some((0, 2))),
+            ((4, 3), Some((3, 0))),
+            ((4, -1), Some((3, 2))),
+            ((-1, 3), Some((0, 0))),
+            ((-1, 1), Some((3, 1))),
+            ((4, 1), Some((0, 1))),
+            ((1, -1), Some((2, 2))),
+            ((1, 3), Some((2, 0))),
+            ((1, 6), Some((1, 0))),
+        ]);
+    }
+
+    #[test]
+    fn cross_surface_twists_both_ways() {
+        resolves(Topology::CrossSurface, &[
+            ((-1, -1), Some((0, 0))),
+            ((4, 3), Some((3, 2))),
+            ((4, -1), Some((3, 0))),
+            ((-1, 3), Some((0, 2))),
+            ((-1, 0), Some((3, 2))),
+            ((4, 1), Some((0, 1))),
+            ((1, -1), Some((2, 2))),
+            ((1, 3), Some((2, 0))),
+            ((8, 6), Some((0, 0))),
+        ]);
+    }
+
+    /// A `width` by `height` board over `topology` with a glider heading for its bottom right corner.
+    fn glider(
+        topology: Topology,
+        width: usize,
+        height: usize,
+    ) -> Simulation {
+        let mut simulation = Simulation::builder()
+            .width(width)
+            .height(height)
+            .topology(topology)
+            .cycle_window(0)
+            .build()
+            .unwrap();
+        simulation.place_pattern(&Pattern::named("glider").unwrap(), 4, 4, Blend::Or);
+        simulation
+    }
+
+    fn alive(
+        simulation: &Simulation,
+        x: usize,
+        y: usize,
+    ) -> bool {
+        simulation.cellule_at(x, y).unwrap().alive()
+    }
+
+    /// Steps `simulation` alongside a torus `scale` times its size that is unfolded from it, with
+    /// `copy` mapping each cell of the torus to the cell of the board it copies, and checks that the
+    /// two agree every generation.
+    fn matches_unfolded<F: Fn(usize, usize) -> (usize, usize)>(
+        mut simulation: Simulation,
+        scale: (usize, usize),
+        copy: F,
+    ) {
+        let (width, height) = (simulation.width() * scale.0, simulation.height() * scale.1);
+        let mut unfolded = Simulation::builder().width(width).height(height).cycle_window(0).build().unwrap();
+        for y in 0..height {
+            for x in 0..width {
+                let (from_x, from_y) = copy(x, y);
+                if alive(&simulation, from_x, from_y) {
+                    unfolded.set_cell(x, y, 1).unwrap();
+                }
+            }
+        }
+
+        for generation in 0..40 {
+            for y in 0..height {
+                for x in 0..width {
+                    let (from_x, from_y) = copy(x, y);
+                    let context = format!("{} generation {} ({}, {})", simulation.topology(), generation, x, y);
+                    assert_eq!(alive(&unfolded, x, y), alive(&simulation, from_x, from_y), "{}", context);
+                }
+            }
+            simulation.update(Message::Step).unwrap();
+            unfolded.update(Message::Step).unwrap();
+        }
+    }
+
+    #[test]
+    fn a_glider_crosses_the_edges_of_a_torus() {
+        let mut simulation = glider(Topology::Torus, 8, 8);
+        let start = simulation.to_pattern();
+        for _ in 0..32 {
+            simulation.update(Message::Step).unwrap();
+        }
+        assert_eq!(simulation.to_pattern(), start);
+    }
+
+    #[test]
+    fn a_glider_crashes_into_the_corner_of_a_plane() {
+        let mut simulation = glider(Topology::Plane, 8, 8);
+        for _ in 0..20 {
+            simulation.update(Message::Step).unwrap();
+        }
+        // The glider is squashed into a block in the corner.
+        let block = [(6, 6), (7, 6), (6, 7), (7, 7)];
+        let alive: Vec<_> = simulation.to_pattern().alive_cells().collect();
+        assert_eq!(alive, block);
+    }
+
+    #[test]
+    fn a_glider_crosses_a_mirror() {
+        let (width, height) = (8, 7);
+        matches_unfolded(glider(Topology::Mirror, width, height), (2, 2), |x, y| {
+            let fold = |coord: usize, size: usize| {
+                if coord < size {
+                    coord
+                } else {
+                    2 * size - 1 - coord
+                }
+            };
+            (fold(x, width), fold(y, height))
+        });
+    }
+
+    #[test]
+    fn a_glider_crosses_the_twist_of_a_klein_bottle() {
+        let (width, height) = (8, 7);
+        matches_unfolded(glider(Topology::KleinBottle, width, height), (1, 2), |x, y| {
+            if y < height {
+                (x, y)
+            } else {
+                (width - 1 - x, y - height)
+            }
+        });
+    }
+
+    #[test]
+    fn a_glider_crosses_the_twists_of_a_cross_surface() {
+        let (width, height) = (8, 7);
+        matches_unfolded(glider(Topology::CrossSurface, width, height), (2, 2), |x, y| {
+            match (x < width, y < height) {
+                (true, true) => (x, y),
+                (false, true) => (x - width, height - 1 - y),
+                (true, false) => (width - 1 - x, y - height),
+                (false, false) => (2 * width - 1 - x, 2 * height - 1 - y),
+            }
+        });
+    }
+}
@@ -0,0 +1,807 @@
+//! [Langton's ant] and its generalisation to [turmites]: agents walking over a board of colored
+//! cells, recoloring each cell they leave and turning by its color and their own state.
+//!
+//! [Langton's ant]: https://en.wikipedia.org/wiki/Langton%27s_ant
+//! [turmites]: https://en.wikipedia.org/wiki/Turmite
+
+use crate::{
+    check_dimensions,
+    deps::{
+        rand::{
+            thread_rng,
+            Rng,
+        },
+        serde,
+        tracing::info,
+    },
+    Control,
+    Error,
+    Soup,
+    State,
+    Symmetry,
+    Topology,
+};
+use std::{
+    fmt,
+    str::FromStr,
+};
+
+/// Which way an ant faces.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Heading {
+    #[default]
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Heading {
+    /// The headings in clockwise order.
+    pub const ALL: [Heading; 4] = [Heading::North, Heading::East, Heading::South, Heading::West];
+
+    /// The step one cell forward takes, with `y` growing downwards.
+    pub fn offset(&self) -> (isize, isize) {
+        match self {
+            Heading::North => (0, -1),
+            Heading::East => (1, 0),
+            Heading::South => (0, 1),
+            Heading::West => (-1, 0),
+        }
+    }
+
+    /// The heading after `quarters` quarter turns clockwise.
+    fn rotated(
+        &self,
+        quarters: usize,
+    ) -> Self {
+        Self::ALL[(*self as usize + quarters) % 4]
+    }
+}
+
+/// A turn an ant makes before stepping forward, with the numbers Golly gives it in turmite rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Turn {
+    /// Keep going straight, `N` or 1.
+    None,
+    /// A quarter turn clockwise, `R` or 2.
+    Right,
+    /// A half turn, `U` or 4.
+    UTurn,
+    /// A quarter turn counterclockwise, `L` or 8.
+    Left,
+}
+
+impl Turn {
+    pub const ALL: [Turn; 4] = [Turn::None, Turn::Right, Turn::UTurn, Turn::Left];
+
+    pub fn code(&self) -> u8 {
+        match self {
+            Turn::None => 1,
+            Turn::Right => 2,
+            Turn::UTurn => 4,
+            Turn::Left => 8,
+        }
+    }
+
+    pub fn letter(&self) -> char {
+        match self {
+            Turn::None => 'N',
+            Turn::Right => 'R',
+            Turn::UTurn => 'U',
+            Turn::Left => 'L',
+        }
+    }
+
+    pub fn from_code(code: u64) -> Option<Self> {
+        Self::ALL.iter().copied().find(|turn| u64::from(turn.code()) == code)
+    }
+
+    pub fn from_letter(letter: char) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|turn| turn.letter() == letter.to_ascii_uppercase())
+    }
+
+    /// The same turn seen in a mirror.
+    fn mirrored(&self) -> Self {
+        match self {
+            Turn::Right => Turn::Left,
+            Turn::Left => Turn::Right,
+            turn => *turn,
+        }
+    }
+
+    /// How many quarter turns clockwise the turn is.
+    fn quarters(&self) -> usize {
+        match self {
+            Turn::None => 0,
+            Turn::Right => 1,
+            Turn::UTurn => 2,
+            Turn::Left => 3,
+        }
+    }
+}
+
+/// What an ant in some state does on a cell of some color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Transition {
+    /// The color the cell is left with.
+    pub color: u8,
+    pub turn:  Turn,
+    /// The state the ant moves on in.
+    pub state: u8,
+}
+
+/// The transition table of a [`Turmites`] board, giving for each ant state and cell color the
+/// color to write, the turn to make and the next state.
+///
+/// Rules are written the way Golly writes them. Langton's ant and its generalisations with a single
+/// state are a string of turns, one per color, such as `RL` or `LLRR`, where each color is
+/// followed by the next one and the last color by the first. Any other turmite is a table such as
+/// `{{{1,2,0},{0,8,0}}}`, which lists for every state the `{color,turn,state}` triple of every
+/// color, with turns numbered 1 for none, 2 for right, 4 for a U-turn and 8 for left.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct TurmiteRule {
+    colors: u8,
+    states: u8,
+    table:  Vec<Transition>,
+}
+
+impl Default for TurmiteRule {
+    /// Langton's ant, `RL`.
+    fn default() -> Self {
+        Self::ant(&[Turn::Right, Turn::Left]).expect("RL is a valid ant")
+    }
+}
+
+impl TurmiteRule {
+    /// The most colors a rule may have.
+    pub const MAX_COLORS: usize = 16;
+    /// The most states a rule may have.
+    pub const MAX_STATES: usize = 16;
+
+    /// A single state ant that makes `turns[c]` on color `c` and leaves the cell with the next color.
+    pub fn ant(turns: &[Turn]) -> Result<Self, Error> {
+        if turns.is_empty() || turns.len() > Self::MAX_COLORS {
+            return Err(Error::ParseRule {
+                rule:   turns.iter().map(Turn::letter).collect(),
+                reason: "ants need between 1 and 16 turns",
+            });
+        }
+
+        let colors = turns.len();
+        Ok(Self {
+            colors: colors as u8,
+            states: 1,
+            table:  turns
+                .iter()
+                .enumerate()
+                .map(|(color, &turn)| {
+                    Transition {
+                        color: ((color + 1) % colors) as u8,
+                        turn,
+                        state: 0,
+                    }
+                })
+                .collect(),
+        })
+    }
+
+    pub fn colors(&self) -> u8 {
+        self.colors
+    }
+
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    /// What an ant in `state` does on a cell of `color`.
+    pub fn transition(
+        &self,
+        state: u8,
+        color: u8,
+    ) -> Transition {
+        self.table[state as usize * self.colors as usize + color as usize]
+    }
+
+    /// The turns of a rule that can be written as a string of turns.
+    fn as_ant(&self) -> Option<Vec<Turn>> {
+        let cycles = self
+            .table
+            .iter()
+            .enumerate()
+            .all(|(color, transition)| transition.color as usize == (color + 1) % self.colors as usize);
+        if self.states == 1 && cycles {
+            Some(self.table.iter().map(|transition| transition.turn).collect())
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for TurmiteRule {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        if let Some(turns) = self.as_ant() {
+            return turns.iter().try_for_each(|turn| write!(f, "{}", turn.letter()));
+        }
+
+        f.write_str("{")?;
+        for (state, row) in self.table.chunks(self.colors as usize).enumerate() {
+            if state > 0 {
+                f.write_str(",")?;
+            }
+            f.write_str("{")?;
+            for (color, transition) in row.iter().enumerate() {
+                if color > 0 {
+                    f.write_str(",")?;
+                }
+                write!(f, "{{{},{},{}}}", transition.color, transition.turn.code(), transition.state)?;
+            }
+            f.write_str("}")?;
+        }
+        f.write_str("}")
+    }
+}
+
+impl FromStr for TurmiteRule {
+    type Err = Error;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason| {
+            Error::ParseRule {
+                rule: rule.to_string(),
+                reason,
+            }
+        };
+        let trimmed = rule.trim();
+
+        if !trimmed.starts_with('{') {
+            let turns = trimmed
+                .chars()
+                .map(|letter| Turn::from_letter(letter).ok_or_else(|| invalid("ants turn with L, R, N or U")))
+                .collect::<Result<Vec<_>, _>>()?;
+            return Self::ant(&turns).map_err(|_| invalid("ants need between 1 and 16 turns"));
+        }
+
+        let table = match parse_table(trimmed).ok_or_else(|| invalid("malformed turmite table"))? {
+            (Node::List(states), "") => states,
+            _ => return Err(invalid("malformed turmite table")),
+        };
+        if table.is_empty() || table.len() > Self::MAX_STATES {
+            return Err(invalid("turmites need between 1 and 16 states"));
+        }
+
+        let states = table.len();
+        let mut colors = None;
+        let mut transitions = Vec::new();
+        for row in table {
+            let row = match row {
+                Node::List(row) if !row.is_empty() && row.len() <= Self::MAX_COLORS => row,
+                _ => return Err(invalid("every state needs a list of between 1 and 16 colors")),
+            };
+            let row_colors = *colors.get_or_insert(row.len());
+            if row_colors != row.len() {
+                return Err(invalid("every state needs a transition for every color"));
+            }
+            for triple in row {
+                let (color, turn, state) = match triple {
+                    Node::List(triple) => {
+                        match triple.as_slice() {
+                            [Node::Number(color), Node::Number(turn), Node::Number(state)] => (*color, *turn, *state),
+                            _ => return Err(invalid("transitions are {color,turn,state} triples")),
+                        }
+                    }
+                    Node::Number(_) => return Err(invalid("transitions are {color,turn,state} triples")),
+                };
+                if color >= row_colors as u64 {
+                    return Err(invalid("transition writes a color the rule does not have"));
+                }
+                if state >= states as u64 {
+                    return Err(invalid("transition moves to a state the rule does not have"));
+                }
+                transitions.push(Transition {
+                    color: color as u8,
+                    turn:  Turn::from_code(turn).ok_or_else(|| invalid("turns are 1, 2, 4 or 8"))?,
+                    state: state as u8,
+                });
+            }
+        }
+
+        Ok(Self {
+            colors: colors.unwrap_or(1) as u8,
+            states: states as u8,
+            table:  transitions,
+        })
+    }
+}
+
+/// A number or a braced list in a turmite table.
+enum Node {
+    Number(u64),
+    List(Vec<Node>),
+}
+
+/// Parses the node at the start of `text`, returning it and the text after it.
+fn parse_table(text: &str) -> Option<(Node, &str)> {
+    let text = text.trim_start();
+    if let Some(mut rest) = text.strip_prefix('{') {
+        let mut nodes = Vec::new();
+        loop {
+            let (node, after) = parse_table(rest)?;
+            nodes.push(node);
+            let after = after.trim_start();
+            if let Some(after) = after.strip_prefix(',') {
+                rest = after;
+            } else {
+                return Some((Node::List(nodes), after.strip_prefix('}')?.trim_start()));
+            }
+        }
+    }
+
+    let end = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let number = text[..end].parse().ok()?;
+    Some((Node::Number(number), &text[end..]))
+}
+
+/// An agent on a [`Turmites`] board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Ant {
+    x:        usize,
+    y:        usize,
+    heading:  Heading,
+    state:    u8,
+    /// Whether the ant came back through an odd number of twisted or mirrored edges, which swaps
+    /// its left and right.
+    mirrored: bool,
+}
+
+impl Ant {
+    /// An ant in state 0 at `(x, y)`.
+    pub fn new(
+        x: usize,
+        y: usize,
+        heading: Heading,
+    ) -> Self {
+        Self {
+            x,
+            y,
+            heading,
+            state: 0,
+            mirrored: false,
+        }
+    }
+
+    pub fn x(&self) -> usize {
+        self.x
+    }
+
+    pub fn y(&self) -> usize {
+        self.y
+    }
+
+    pub fn heading(&self) -> Heading {
+        self.heading
+    }
+
+    pub fn state(&self) -> u8 {
+        self.state
+    }
+
+    pub fn mirrored(&self) -> bool {
+        self.mirrored
+    }
+}
+
+/// The messages a [`Turmites`] board is driven with.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum TurmiteMessage {
+    Control(Control),
+    Random,
+    /// Recolors the board from a reproducible [`Soup`].
+    RandomSeeded {
+        seed:     u64,
+        density:  f64,
+        symmetry: Symmetry,
+    },
+    /// Moves the cell at a row major index on to the next color.
+    ToggleCellule(usize),
+    SetTopology(Topology),
+}
+
+impl From<Control> for TurmiteMessage {
+    fn from(control: Control) -> Self {
+        TurmiteMessage::Control(control)
+    }
+}
+
+/// A board of colored cells with ants walking over it.
+///
+/// Each tick the ants take a turn in the order they were added. An ant looks up its state and the
+/// color under it in the [`TurmiteRule`], recolors the cell, turns, moves on to its next state and
+/// steps forward. Ants may share a cell, each seeing the colors written by the ants before it.
+///
+/// Ants leave the board the way the [`Topology`] says. They come back on the other side of a
+/// [`Topology::Torus`], fall off a [`Topology::Plane`] for good, and bounce back off the edges of a
+/// [`Topology::Mirror`]. Crossing a twisted edge of a [`Topology::KleinBottle`] or
+/// [`Topology::CrossSurface`] brings them back mirrored, as does bouncing, so that they turn left
+/// where they would have turned right.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Turmites {
+    state:    State,
+    ticks:    usize,
+    width:    usize,
+    height:   usize,
+    topology: Topology,
+    rule:     TurmiteRule,
+    cells:    Vec<u8>,
+    ants:     Vec<Ant>,
+    /// The ants as they were added, which resetting puts back.
+    start:    Vec<Ant>,
+}
+
+impl Turmites {
+    /// A board of `width` columns by `height` rows of color 0, without ants.
+    pub fn new(
+        width: usize,
+        height: usize,
+        rule: TurmiteRule,
+    ) -> Result<Self, Error> {
+        let len = check_dimensions(width, height)?;
+
+        Ok(Self {
+            state: State::default(),
+            ticks: 0,
+            width,
+            height,
+            topology: Topology::default(),
+            rule,
+            cells: vec![0; len],
+            ants: Vec::new(),
+            start: Vec::new(),
+        })
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn ticks(&self) -> usize {
+        self.ticks
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(
+        &mut self,
+        topology: Topology,
+    ) {
+        self.topology = topology;
+    }
+
+    pub fn rule(&self) -> &TurmiteRule {
+        &self.rule
+    }
+
+    /// The ants still on the board, in the order they move.
+    pub fn ants(&self) -> &[Ant] {
+        &self.ants
+    }
+
+    /// Puts `ant` on the board, after the ants already on it.
+    ///
+    /// # Panics
+    ///
+    /// When the ant is off the board.
+    pub fn add_ant(
+        &mut self,
+        ant: Ant,
+    ) {
+        assert!(ant.x < self.width && ant.y < self.height, "ant placed off the board");
+        self.ants.push(ant);
+        self.start.push(ant);
+    }
+
+    /// The color of every cell in row major order, `width()` cells to a row.
+    pub fn cells(&self) -> impl ExactSizeIterator<Item = u8> + '_ {
+        self.cells.iter().copied()
+    }
+
+    pub fn get(
+        &self,
+        x: usize,
+        y: usize,
+    ) -> u8 {
+        self.cells[y * self.width + x]
+    }
+
+    /// Sets the cell at `(x, y)` to `color`, wrapped into the colors of the rule.
+    pub fn set(
+        &mut self,
+        x: usize,
+        y: usize,
+        color: u8,
+    ) {
+        self.cells[y * self.width + x] = color % self.rule.colors;
+    }
+
+    /// Recolors every cell 0 and puts the ants back where they started.
+    pub fn clear(&mut self) {
+        self.cells.iter_mut().for_each(|color| *color = 0);
+        self.ants = self.start.clone();
+    }
+
+    /// Moves every ant once, dropping those that walk off a [`Topology::Plane`].
+    pub fn step(&mut self) {
+        let mut ants = std::mem::take(&mut self.ants);
+        ants.retain_mut(|ant| self.move_ant(ant));
+        self.ants = ants;
+        self.ticks += 1;
+    }
+
+    /// Runs one move of `ant`, returning whether it is still on the board.
+    fn move_ant(
+        &mut self,
+        ant: &mut Ant,
+    ) -> bool {
+        let idx = ant.y * self.width + ant.x;
+        let transition = self.rule.transition(ant.state, self.cells[idx]);
+        let turn = if ant.mirrored {
+            transition.turn.mirrored()
+        } else {
+            transition.turn
+        };
+        self.cells[idx] = transition.color;
+        ant.state = transition.state;
+        ant.heading = ant.heading.rotated(turn.quarters());
+
+        let (dx, dy) = ant.heading.offset();
+        let (ahead_x, ahead_y) = (ant.x as isize + dx, ant.y as isize + dy);
+        let (x, y) = match self.topology.resolve(ahead_x, ahead_y, self.width, self.height) {
+            Some(cell) => cell,
+            None => return false,
+        };
+        let crossed_x = ahead_x < 0 || ahead_x >= self.width as isize;
+        let crossed_y = ahead_y < 0 || ahead_y >= self.height as isize;
+        match self.topology {
+            Topology::Mirror if crossed_x || crossed_y => {
+                ant.heading = ant.heading.rotated(2);
+                ant.mirrored = !ant.mirrored;
+            }
+            Topology::KleinBottle if crossed_y => ant.mirrored = !ant.mirrored,
+            Topology::CrossSurface if crossed_x || crossed_y => ant.mirrored = !ant.mirrored,
+            _ => {}
+        }
+        ant.x = x;
+        ant.y = y;
+        true
+    }
+
+    /// Drives the board with a [`TurmiteMessage`]. Random fills recolor the cells and leave the ants
+    /// where they are, with a seeded soup coloring its live cells 1. Toggling a cell moves it on to
+    /// the next color, and resetting clears the board and puts the ants back where they started.
+    pub fn update(
+        &mut self,
+        msg: TurmiteMessage,
+    ) -> Result<(), Error> {
+        use State::*;
+        if self.state.ended() {
+            return Err(Error::GameOver);
+        }
+
+        match msg {
+            TurmiteMessage::Random => {
+                let colors = self.rule.colors;
+                let mut rng = thread_rng();
+                self.cells.iter_mut().for_each(|color| *color = rng.gen_range(0, colors));
+                info!("Random");
+            }
+            TurmiteMessage::RandomSeeded {
+                seed,
+                density,
+                symmetry,
+            } => {
+                let soup = Soup {
+                    symmetry,
+                    ..Soup::new(seed, density)
+                };
+                let pattern = soup.generate(self.width, self.height)?;
+                for y in 0..self.height {
+                    for x in 0..self.width {
+                        self.cells[y * self.width + x] = pattern.get(x, y) as u8;
+                    }
+                }
+                info!("RandomSeeded {} {} {}", seed, density, symmetry);
+            }
+            TurmiteMessage::Control(Control::Start) => {
+                self.state.try_transition(Running)?;
+                info!("Start");
+            }
+            TurmiteMessage::Control(Control::Step) => {
+                self.step();
+            }
+            TurmiteMessage::Control(Control::Reset) => {
+                self.clear();
+                info!("Reset");
+            }
+            TurmiteMessage::Control(Control::Stop) => {
+                self.state.try_transition(Pausing)?;
+                info!("Stop");
+            }
+            TurmiteMessage::ToggleCellule(idx) => {
+                let colors = self.rule.colors;
+                if let Some(color) = self.cells.get_mut(idx) {
+                    *color = (*color + 1) % colors;
+                }
+            }
+            TurmiteMessage::SetTopology(topology) => {
+                self.topology = topology;
+                info!("SetTopology {}", topology);
+            }
+            TurmiteMessage::Control(Control::Tick) => {
+                if self.state.run() {
+                    self.step();
+                }
+            }
+            TurmiteMessage::Control(Control::End) => {
+                self.state.try_transition(Ended)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(
+        width: usize,
+        height: usize,
+        rule: &str,
+        topology: Topology,
+    ) -> Turmites {
+        let mut turmites = Turmites::new(width, height, rule.parse().unwrap()).unwrap();
+        turmites.set_topology(topology);
+        turmites
+    }
+
+    fn population(turmites: &Turmites) -> usize {
+        turmites.cells().filter(|&color| color != 0).count()
+    }
+
+    #[test]
+    fn langtons_ant_builds_highway() {
+        let mut turmites = board(160, 160, "RL", Topology::Torus);
+        turmites.add_ant(Ant::new(80, 80, Heading::North));
+        for _ in 0..11_000 {
+            turmites.step();
+        }
+
+        // Once on the highway the ant repeats itself every 104 steps, moving two cells diagonally
+        // and leaving 12 more cells colored behind it.
+        for _ in 0..5 {
+            let (before, ant) = (population(&turmites), turmites.ants()[0]);
+            for _ in 0..104 {
+                turmites.step();
+            }
+            let after = turmites.ants()[0];
+
+            assert_eq!(population(&turmites), before + 12);
+            assert_eq!(after.heading(), ant.heading());
+            assert_eq!((after.x() as isize - ant.x() as isize).abs(), 2);
+            assert_eq!((after.y() as isize - ant.y() as isize).abs(), 2);
+        }
+    }
+
+    #[test]
+    fn ants_fall_off_plane() {
+        let mut turmites = board(3, 3, "N", Topology::Plane);
+        turmites.add_ant(Ant::new(0, 0, Heading::North));
+        turmites.add_ant(Ant::new(0, 0, Heading::East));
+        turmites.step();
+
+        assert_eq!(turmites.ants(), [Ant::new(1, 0, Heading::East)]);
+
+        turmites.step();
+        turmites.step();
+        assert!(turmites.ants().is_empty());
+
+        turmites.clear();
+        assert_eq!(turmites.ants(), [Ant::new(0, 0, Heading::North), Ant::new(0, 0, Heading::East)]);
+    }
+
+    #[test]
+    fn mirror_bounces_and_flips_chirality() {
+        let mut turmites = board(3, 3, "NR", Topology::Mirror);
+        turmites.add_ant(Ant::new(0, 1, Heading::West));
+        turmites.step();
+
+        let ant = turmites.ants()[0];
+        assert_eq!((ant.x(), ant.y(), ant.heading(), ant.mirrored()), (0, 1, Heading::East, true));
+
+        // The cell it bounced back onto is color 1, where a right turn is now a left one.
+        turmites.step();
+        let ant = turmites.ants()[0];
+        assert_eq!((ant.x(), ant.y(), ant.heading(), ant.mirrored()), (0, 0, Heading::North, true));
+    }
+
+    #[test]
+    fn klein_bottle_flips_chirality_across_twisted_edge() {
+        let run = |topology| {
+            let mut turmites = board(4, 3, "NR", topology);
+            turmites.set(1, 2, 1);
+            turmites.set(2, 2, 1);
+            turmites.add_ant(Ant::new(1, 0, Heading::North));
+            turmites.step();
+            let crossed = turmites.ants()[0];
+            turmites.step();
+            (crossed, turmites.ants()[0])
+        };
+
+        let (crossed, turned) = run(Topology::Torus);
+        assert_eq!((crossed.x(), crossed.y(), crossed.mirrored()), (1, 2, false));
+        assert_eq!(turned.heading(), Heading::East);
+
+        let (crossed, turned) = run(Topology::KleinBottle);
+        assert_eq!((crossed.x(), crossed.y(), crossed.mirrored()), (2, 2, true));
+        assert_eq!(turned.heading(), Heading::West);
+
+        // The untwisted edge of the bottle leaves the ant as it was.
+        let mut turmites = board(4, 3, "N", Topology::KleinBottle);
+        turmites.add_ant(Ant::new(3, 1, Heading::East));
+        turmites.step();
+        assert_eq!(turmites.ants(), [Ant::new(0, 1, Heading::East)]);
+    }
+
+    #[test]
+    fn serde_round_trips_ants() {
+        let mut turmites = board(5, 4, "{{{1,1,1},{0,8,0}},{{1,4,0},{1,2,1}}}", Topology::Mirror);
+        turmites.add_ant(Ant::new(0, 1, Heading::West));
+        turmites.add_ant(Ant::new(3, 2, Heading::South));
+        turmites.step();
+        assert_eq!(turmites.ants().iter().map(|ant| (ant.state(), ant.mirrored())).collect::<Vec<_>>(), [
+            (1, true),
+            (1, false),
+        ]);
+
+        let json = serde_json::to_string(&turmites).unwrap();
+        let restored = serde_json::from_str::<Turmites>(&json).unwrap();
+        assert_eq!(restored.ants(), turmites.ants());
+        assert_eq!(restored.rule(), turmites.rule());
+        assert_eq!(restored.cells().collect::<Vec<_>>(), turmites.cells().collect::<Vec<_>>());
+        assert_eq!((restored.ticks(), restored.topology()), (1, Topology::Mirror));
+
+        let mut stepped = (restored, turmites);
+        stepped.0.step();
+        stepped.1.step();
+        assert_eq!(stepped.0.ants(), stepped.1.ants());
+    }
+
+    #[test]
+    fn rules_round_trip_through_strings() {
+        for rule in &["RL", "LLRR", "RLNU", "{{{1,2,1},{0,8,0}},{{1,4,0},{1,1,1}}}"] {
+            assert_eq!(rule.parse::<TurmiteRule>().unwrap().to_string(), *rule);
+        }
+        assert_eq!("{{{1,2,0},{0,8,0}}}".parse::<TurmiteRule>().unwrap().to_string(), "RL");
+        assert!("RX".parse::<TurmiteRule>().is_err());
+        assert!("{{{1,3,0},{0,8,0}}}".parse::<TurmiteRule>().is_err());
+        assert!("{{{2,2,0},{0,8,0}}}".parse::<TurmiteRule>().is_err());
+        assert!("{{{1,2,1},{0,8,0}}}".parse::<TurmiteRule>().is_err());
+    }
+}
@@ -0,0 +1,599 @@
+//! [Wireworld], a four state automaton for building digital circuits, along with readers for the
+//! circuit files written by Golly (multi-state RLE) and MCell.
+//!
+//! [Wireworld]: https://conwaylife.com/wiki/Wireworld
+
+use crate::{
+    check_dimensions,
+    deps::{
+        serde,
+        tracing::info,
+    },
+    Control,
+    Error,
+    State,
+    Topology,
+};
+
+const NEIGHBORHOOD: [(isize, isize); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+/// A cell of a [`Wireworld`] board.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum WireCell {
+    #[default]
+    Empty,
+    /// Wire that carries electrons.
+    Conductor,
+    /// The front of an electron.
+    Head,
+    /// The back of an electron, which keeps it from turning around.
+    Tail,
+}
+
+impl WireCell {
+    pub const ALL: [WireCell; 4] = [WireCell::Empty, WireCell::Head, WireCell::Tail, WireCell::Conductor];
+
+    /// The state number of the cell in Golly and MCell files: 0 for empty, 1 for an electron head, 2
+    /// for a tail and 3 for a conductor.
+    pub fn state(&self) -> usize {
+        match self {
+            WireCell::Empty => 0,
+            WireCell::Head => 1,
+            WireCell::Tail => 2,
+            WireCell::Conductor => 3,
+        }
+    }
+
+    pub fn from_state(state: usize) -> Option<Self> {
+        Self::ALL.get(state).copied()
+    }
+
+    /// The cell in the next generation, given how many of its eight neighbors are electron heads.
+    pub fn next(
+        &self,
+        heads: usize,
+    ) -> Self {
+        match self {
+            WireCell::Empty => WireCell::Empty,
+            WireCell::Head => WireCell::Tail,
+            WireCell::Tail => WireCell::Conductor,
+            WireCell::Conductor if heads == 1 || heads == 2 => WireCell::Head,
+            WireCell::Conductor => WireCell::Conductor,
+        }
+    }
+
+    /// The cell a click turns this one into, cycling through empty, conductor, head and tail.
+    fn toggled(&self) -> Self {
+        match self {
+            WireCell::Empty => WireCell::Conductor,
+            WireCell::Conductor => WireCell::Head,
+            WireCell::Head => WireCell::Tail,
+            WireCell::Tail => WireCell::Empty,
+        }
+    }
+}
+
+/// The messages a [`Wireworld`] board is driven with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum WireworldMessage {
+    Control(Control),
+    /// Cycles the cell at a row major index through empty, conductor, head and tail.
+    ToggleCellule(usize),
+    SetTopology(Topology),
+}
+
+impl From<Control> for WireworldMessage {
+    fn from(control: Control) -> Self {
+        WireworldMessage::Control(control)
+    }
+}
+
+/// A bounded Wireworld board.
+///
+/// Electron heads become tails, tails become conductor again, and a conductor becomes a head when
+/// one or two of its eight neighbors are heads. Circuits are normally laid out on a
+/// [`Topology::Plane`], which is what a new board starts with.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Wireworld {
+    state:    State,
+    ticks:    usize,
+    width:    usize,
+    height:   usize,
+    topology: Topology,
+    cells:    Vec<WireCell>,
+    #[serde(skip)]
+    back:     Vec<WireCell>,
+}
+
+impl Wireworld {
+    /// An empty board of `width` columns by `height` rows.
+    pub fn new(
+        width: usize,
+        height: usize,
+    ) -> Result<Self, Error> {
+        let len = check_dimensions(width, height)?;
+
+        Ok(Self {
+            state: State::default(),
+            ticks: 0,
+            width,
+            height,
+            topology: Topology::Plane,
+            cells: vec![WireCell::Empty; len],
+            back: Vec::new(),
+        })
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn ticks(&self) -> usize {
+        self.ticks
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(
+        &mut self,
+        topology: Topology,
+    ) {
+        self.topology = topology;
+    }
+
+    /// Every cell in row major order, `width()` cells to a row.
+    pub fn cells(&self) -> impl ExactSizeIterator<Item = WireCell> + '_ {
+        self.cells.iter().copied()
+    }
+
+    pub fn get(
+        &self,
+        x: usize,
+        y: usize,
+    ) -> WireCell {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn set(
+        &mut self,
+        x: usize,
+        y: usize,
+        cell: WireCell,
+    ) {
+        self.cells[y * self.width + x] = cell;
+    }
+
+    /// How many electrons are on the board, counted by their heads.
+    pub fn electrons(&self) -> usize {
+        self.cells.iter().filter(|&&cell| cell == WireCell::Head).count()
+    }
+
+    /// Copies `circuit` onto the board with its top left cell at `(x, y)`. Cells that fall past the
+    /// edges of the board are dropped.
+    pub fn place(
+        &mut self,
+        circuit: &Wireworld,
+        x: usize,
+        y: usize,
+    ) {
+        for dy in 0..circuit.height.min(self.height.saturating_sub(y)) {
+            for dx in 0..circuit.width.min(self.width.saturating_sub(x)) {
+                self.set(x + dx, y + dy, circuit.get(dx, dy));
+            }
+        }
+    }
+
+    /// Empties every cell.
+    pub fn clear(&mut self) {
+        self.cells.iter_mut().for_each(|cell| *cell = WireCell::Empty);
+    }
+
+    fn heads_around(
+        &self,
+        x: usize,
+        y: usize,
+    ) -> usize {
+        NEIGHBORHOOD
+            .iter()
+            .filter_map(|&(dx, dy)| {
+                self.topology
+                    .resolve(x as isize + dx, y as isize + dy, self.width, self.height)
+            })
+            .filter(|&(x, y)| self.get(x, y) == WireCell::Head)
+            .count()
+    }
+
+    /// Advances the board one generation.
+    pub fn step(&mut self) {
+        let mut next = std::mem::take(&mut self.back);
+        next.clear();
+        next.extend(
+            self.cells
+                .iter()
+                .enumerate()
+                .map(|(idx, cell)| cell.next(self.heads_around(idx % self.width, idx / self.width))),
+        );
+        self.back = std::mem::replace(&mut self.cells, next);
+        self.ticks += 1;
+    }
+
+    /// Drives the board with a [`WireworldMessage`].
+    pub fn update(
+        &mut self,
+        msg: WireworldMessage,
+    ) -> Result<(), Error> {
+        use State::*;
+        if self.state.ended() {
+            return Err(Error::GameOver);
+        }
+
+        match msg {
+            WireworldMessage::Control(Control::Start) => {
+                self.state.try_transition(Running)?;
+                info!("Start");
+            }
+            WireworldMessage::Control(Control::Step) => {
+                self.step();
+            }
+            WireworldMessage::Control(Control::Reset) => {
+                self.clear();
+                info!("Reset");
+            }
+            WireworldMessage::Control(Control::Stop) => {
+                self.state.try_transition(Pausing)?;
+                info!("Stop");
+            }
+            WireworldMessage::Control(Control::Tick) => {
+                if self.state.run() {
+                    self.step();
+                }
+            }
+            WireworldMessage::Control(Control::End) => {
+                self.state.try_transition(Ended)?;
+            }
+            WireworldMessage::ToggleCellule(idx) => {
+                if let Some(cell) = self.cells.get_mut(idx) {
+                    *cell = cell.toggled();
+                }
+            }
+            WireworldMessage::SetTopology(topology) => {
+                self.topology = topology;
+                info!("SetTopology {}", topology);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses a circuit saved by Golly as multi-state RLE, where `.` is an empty cell and `A`, `B`
+    /// and `C` are a head, a tail and a conductor.
+    ///
+    /// Leading `#` lines are skipped and the optional `x = m, y = n, rule = WireWorld` header sets
+    /// the board size, which otherwise just fits the circuit. A header naming another rule is
+    /// rejected.
+    pub fn from_rle(rle: &str) -> Result<Self, Error> {
+        let mut body = Body::new("RLE");
+        let mut in_body = false;
+
+        for (idx, line) in rle.lines().enumerate() {
+            let lineno = idx + 1;
+            let trimmed = line.trim();
+            if !in_body {
+                if trimmed.starts_with('#') || trimmed.is_empty() {
+                    continue;
+                } else if trimmed.starts_with('x') && body.size.is_none() {
+                    body.size = Some(parse_header(line, lineno)?);
+                    continue;
+                }
+                in_body = true;
+            }
+            if body.feed(line, lineno, 1)? {
+                break;
+            }
+        }
+
+        body.finish()
+    }
+
+    /// Parses a circuit saved by MCell. The file starts with a `#MCell` line, `#BOARD 80x60` sets
+    /// the board size, with the circuit centered on it, and the `#L` lines hold the cells in the
+    /// same run length encoding as [`Wireworld::from_rle`], with rows separated by `$`.
+    pub fn from_mcl(mcl: &str) -> Result<Self, Error> {
+        const FORMAT: &str = "MCell";
+        let parse_error = |line, column, reason: &str| {
+            Error::Parse {
+                format: FORMAT,
+                line,
+                column,
+                reason: reason.to_string(),
+            }
+        };
+
+        let mut lines = mcl.lines().enumerate();
+        match lines.next() {
+            Some((_, first)) if first.trim_start().starts_with("#MCell") => {}
+            _ => return Err(parse_error(1, 1, "expected a `#MCell` line")),
+        }
+
+        let mut board = None;
+        let mut body = Body::new(FORMAT);
+        for (idx, line) in lines {
+            let lineno = idx + 1;
+            let trimmed = line.trim_start();
+            let column = line.len() - trimmed.len() + 1;
+            if let Some(data) = trimmed.strip_prefix("#L") {
+                body.feed(data, lineno, column + 2)?;
+            } else if let Some(size) = trimmed.strip_prefix("#BOARD") {
+                let dimension = |value: Option<&str>| value.and_then(|value| value.trim().parse::<usize>().ok());
+                let mut values = size.split('x');
+                match (dimension(values.next()), dimension(values.next())) {
+                    (Some(width), Some(height)) => board = Some((width, height)),
+                    _ => return Err(parse_error(lineno, column, "expected `#BOARD <width>x<height>`")),
+                }
+            }
+        }
+
+        let circuit = body.finish()?;
+        match board {
+            Some((width, height)) if width >= circuit.width && height >= circuit.height => {
+                let mut centered = Self::new(width, height)?;
+                centered.place(&circuit, (width - circuit.width) / 2, (height - circuit.height) / 2);
+                Ok(centered)
+            }
+            _ => Ok(circuit),
+        }
+    }
+}
+
+/// Parses an `x = m, y = n, rule = WireWorld` RLE header into the board size.
+fn parse_header(
+    line: &str,
+    lineno: usize,
+) -> Result<(usize, usize), Error> {
+    let parse_error = |reason: String| {
+        Error::Parse {
+            format: "RLE",
+            line: lineno,
+            column: 1,
+            reason,
+        }
+    };
+
+    let (mut width, mut height) = (None, None);
+    for field in line.split(',') {
+        let (key, value) = field
+            .split_once('=')
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .ok_or_else(|| parse_error(format!("expected `key = value`, found {:?}", field)))?;
+        let dimension = || {
+            value
+                .parse::<usize>()
+                .map_err(|_| parse_error(format!("invalid {} dimension {:?}", key, value)))
+        };
+        match key {
+            "x" => width = Some(dimension()?),
+            "y" => height = Some(dimension()?),
+            "rule" if !value.eq_ignore_ascii_case("WireWorld") => {
+                return Err(parse_error(format!("expected the WireWorld rule, found {:?}", value)));
+            }
+            _ => {}
+        }
+    }
+
+    match (width, height) {
+        (Some(width), Some(height)) => Ok((width, height)),
+        _ => Err(parse_error("header must contain both `x` and `y`".to_string())),
+    }
+}
+
+/// The run length encoded cells shared by the RLE and MCell formats, which may be spread over
+/// several lines, and the size of the board they go on when it is known up front.
+struct Body {
+    format: &'static str,
+    size:   Option<(usize, usize)>,
+    x:      usize,
+    y:      usize,
+    run:    Option<(usize, usize, usize)>,
+    cells:  Vec<(usize, usize, WireCell)>,
+}
+
+impl Body {
+    fn new(format: &'static str) -> Self {
+        Self {
+            format,
+            size: None,
+            x: 0,
+            y: 0,
+            run: None,
+            cells: Vec::new(),
+        }
+    }
+
+    fn parse_error<S: Into<String>>(
+        &self,
+        line: usize,
+        column: usize,
+        reason: S,
+    ) -> Error {
+        Error::Parse {
+            format: self.format,
+            line,
+            column,
+            reason: reason.into(),
+        }
+    }
+
+    /// Decodes `text`, which starts at `first_column` of line `lineno`. Returns whether the `!` that
+    /// ends the cells was reached.
+    fn feed(
+        &mut self,
+        text: &str,
+        lineno: usize,
+        first_column: usize,
+    ) -> Result<bool, Error> {
+        for (col, ch) in text.chars().enumerate() {
+            let column = first_column + col;
+            let count = self.run.map(|(count, _, _)| count).unwrap_or(1);
+
+            match ch {
+                '0'..='9' => {
+                    let digit = ch.to_digit(10).unwrap_or_default() as usize;
+                    let (count, line, column) = self.run.unwrap_or((0, lineno, column));
+                    let count = count
+                        .checked_mul(10)
+                        .and_then(|count| count.checked_add(digit))
+                        .ok_or_else(|| self.parse_error(line, column, "run count is too large"))?;
+                    self.run = Some((count, line, column));
+                    continue;
+                }
+                '.' | 'b' => {
+                    self.x = self.x.saturating_add(count);
+                }
+                'A'..='C' => {
+                    let end = self
+                        .x
+                        .checked_add(count)
+                        .ok_or_else(|| self.parse_error(lineno, column, "run extends past the end of the row"))?;
+                    if let Some((width, height)) = self.size {
+                        if end > width || self.y >= height {
+                            return Err(self.parse_error(
+                                lineno,
+                                column,
+                                format!("cell lies outside the x = {}, y = {} bounds", width, height),
+                            ));
+                        }
+                    }
+                    let cell = WireCell::from_state(ch as usize - 'A' as usize + 1).unwrap_or_default();
+                    let y = self.y;
+                    self.cells.extend((self.x..end).map(|x| (x, y, cell)));
+                    self.x = end;
+                }
+                '$' => {
+                    self.y = self.y.saturating_add(count);
+                    self.x = 0;
+                }
+                '!' => {
+                    self.run = None;
+                    return Ok(true);
+                }
+                ch if ch.is_whitespace() => continue,
+                ch if ch.is_ascii_alphabetic() => {
+                    return Err(self.parse_error(
+                        lineno,
+                        column,
+                        format!("unsupported cell state {:?}, only `.`, `A`, `B` and `C` are allowed", ch),
+                    ));
+                }
+                ch => return Err(self.parse_error(lineno, column, format!("unexpected character {:?}", ch))),
+            }
+
+            self.run = None;
+        }
+        Ok(false)
+    }
+
+    /// Lays the decoded cells out on a board of the known size, or one that just fits them.
+    fn finish(self) -> Result<Wireworld, Error> {
+        if let Some((_, line, column)) = self.run {
+            return Err(self.parse_error(line, column, "run count is not followed by a cell or `$`"));
+        }
+
+        let (width, height) = self.size.unwrap_or_else(|| {
+            self.cells
+                .iter()
+                .fold((1, 1), |(width, height), &(x, y, _)| (width.max(x + 1), height.max(y + 1)))
+        });
+        let mut board = Wireworld::new(width, height)?;
+        for &(x, y, cell) in &self.cells {
+            board.set(x, y, cell);
+        }
+        Ok(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn electron_cycles_head_tail_conductor() {
+        let mut board = Wireworld::from_rle(".A.!").unwrap();
+        let middle = |board: &Wireworld| board.get(1, 0);
+
+        assert_eq!(middle(&board), WireCell::Head);
+        board.step();
+        assert_eq!(middle(&board), WireCell::Tail);
+        board.step();
+        assert_eq!(middle(&board), WireCell::Conductor);
+        board.step();
+        assert_eq!(middle(&board), WireCell::Conductor);
+        assert_eq!(board.ticks(), 3);
+        assert!(board.cells().enumerate().all(|(idx, cell)| idx == 1 || cell == WireCell::Empty));
+    }
+
+    #[test]
+    fn conductor_fires_on_one_or_two_heads() {
+        for heads in 0..=NEIGHBORHOOD.len() {
+            let mut board = Wireworld::new(3, 3).unwrap();
+            board.set(1, 1, WireCell::Conductor);
+            for &(dx, dy) in &NEIGHBORHOOD[..heads] {
+                board.set((1 + dx) as usize, (1 + dy) as usize, WireCell::Head);
+            }
+            board.step();
+
+            let expected = match heads {
+                1 | 2 => WireCell::Head,
+                _ => WireCell::Conductor,
+            };
+            assert_eq!(board.get(1, 1), expected, "{} heads", heads);
+            assert_eq!(WireCell::Conductor.next(heads), expected, "{} heads", heads);
+        }
+    }
+
+    #[test]
+    fn electron_travels_along_wire() {
+        let mut board = Wireworld::from_rle("BACCC!").unwrap();
+        for tick in 1..=3 {
+            board.step();
+            assert_eq!(board.electrons(), 1);
+            assert_eq!(board.get(1 + tick, 0), WireCell::Head);
+            assert_eq!(board.get(tick, 0), WireCell::Tail);
+        }
+    }
+
+    #[test]
+    fn importers_reject_unknown_states() {
+        let position = |result: Result<Wireworld, Error>| {
+            match result {
+                Err(Error::Parse { line, column, .. }) => Some((line, column)),
+                _ => None,
+            }
+        };
+
+        assert_eq!(position(Wireworld::from_rle("#C circuit\nx = 3, y = 1, rule = WireWorld\nADC!")), Some((3, 2)));
+        assert_eq!(position(Wireworld::from_rle("x = 3, y = 1, rule = Life\nAAC!")), Some((1, 1)));
+        assert_eq!(position(Wireworld::from_mcl("#MCell 4.20\n#L CCz")), Some((2, 6)));
+        assert!(Wireworld::from_mcl("#MCell 4.20\n#L 3C!").is_ok());
+    }
+
+    #[test]
+    fn mcell_circuit_is_centered_on_board() {
+        let board = Wireworld::from_mcl("#MCell 4.20\n#BOARD 5x3\n#L ABC!").unwrap();
+
+        assert_eq!((board.width(), board.height()), (5, 3));
+        assert_eq!(board.get(1, 1), WireCell::Head);
+        assert_eq!(board.get(2, 1), WireCell::Tail);
+        assert_eq!(board.get(3, 1), WireCell::Conductor);
+        assert_eq!(board.cells().filter(|&cell| cell != WireCell::Empty).count(), 3);
+    }
+}
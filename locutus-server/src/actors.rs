@@ -9,37 +9,159 @@ use crate::deps::{
         self as actor,
         Actor,
     },
-    parking_lot::{
-        Mutex,
-        MutexGuard,
+    parking_lot::Mutex,
+    serde::{
+        self,
+        de::DeserializeOwned,
     },
-    serde,
-    tracing::info,
 };
 use std::fmt;
 
-pub struct GameOfLife {
-    id:   actor::Id,
-    game: Mutex<gameoflife::Simulation>,
-    tx:   Sender<gameoflife::Message>,
-    rx:   Receiver<gameoflife::Message>,
+/// A simulation that a connection can be served by, see [`SessionActor`].
+pub trait Engine: serde::Serialize + Send + 'static {
+    /// The name of the actor serving the engine.
+    const NAME: &'static str;
+
+    /// The messages the engine is driven with, which include the [`gameoflife::Control`] messages
+    /// every session sends.
+    type Message: From<gameoflife::Control> + DeserializeOwned + Clone + Send + 'static;
+
+    fn update(
+        &mut self,
+        message: Self::Message,
+    ) -> Result<(), gameoflife::Error>;
+
+    /// The messages that get a new session going.
+    fn opening(&self) -> &'static [Self::Message];
+
+    /// What the periodic progress line in the log says about the simulation.
+    fn progress(&self) -> String;
 }
 
-impl GameOfLife {
-    pub fn new() -> Self {
-        let (tx, rx) = channel::unbounded::<gameoflife::Message>();
-        GameOfLife {
+impl Engine for gameoflife::Simulation {
+    const NAME: &'static str = "GameOfLife";
+
+    type Message = gameoflife::Message;
+
+    fn update(
+        &mut self,
+        message: Self::Message,
+    ) -> Result<(), gameoflife::Error> {
+        gameoflife::Simulation::update(self, message)
+    }
+
+    fn opening(&self) -> &'static [Self::Message] {
+        &[gameoflife::Message::Random, gameoflife::Message::Start]
+    }
+
+    fn progress(&self) -> String {
+        let stats = self.stats();
+        match self.rule().teams() {
+            1 => format!("generation {}, population {}", stats.generation, stats.population),
+            _ => format!("generation {}, teams {:?}", stats.generation, self.team_populations()),
+        }
+    }
+}
+
+impl Engine for gameoflife::Wireworld {
+    const NAME: &'static str = "Wireworld";
+
+    type Message = gameoflife::WireworldMessage;
+
+    fn update(
+        &mut self,
+        message: Self::Message,
+    ) -> Result<(), gameoflife::Error> {
+        gameoflife::Wireworld::update(self, message)
+    }
+
+    fn opening(&self) -> &'static [Self::Message] {
+        &[gameoflife::WireworldMessage::Control(gameoflife::Control::Start)]
+    }
+
+    fn progress(&self) -> String {
+        format!("generation {}, electrons {}", self.ticks(), self.electrons())
+    }
+}
+
+impl Engine for gameoflife::LineAutomaton {
+    const NAME: &'static str = "LineAutomaton";
+
+    type Message = gameoflife::LineMessage;
+
+    fn update(
+        &mut self,
+        message: Self::Message,
+    ) -> Result<(), gameoflife::Error> {
+        gameoflife::LineAutomaton::update(self, message)
+    }
+
+    fn opening(&self) -> &'static [Self::Message] {
+        &[gameoflife::LineMessage::Control(gameoflife::Control::Start)]
+    }
+
+    fn progress(&self) -> String {
+        format!("rule {}, generation {}, population {}", self.rule(), self.ticks(), self.population())
+    }
+}
+
+impl Engine for gameoflife::Turmites {
+    const NAME: &'static str = "Turmites";
+
+    type Message = gameoflife::TurmiteMessage;
+
+    fn update(
+        &mut self,
+        message: Self::Message,
+    ) -> Result<(), gameoflife::Error> {
+        gameoflife::Turmites::update(self, message)
+    }
+
+    fn opening(&self) -> &'static [Self::Message] {
+        &[gameoflife::TurmiteMessage::Control(gameoflife::Control::Start)]
+    }
+
+    fn progress(&self) -> String {
+        format!("generation {}, ants {}", self.ticks(), self.ants().len())
+    }
+}
+
+/// Serves one connection by feeding the messages it is sent to an [`Engine`].
+pub struct SessionActor<E: Engine> {
+    id:     actor::Id,
+    engine: Mutex<E>,
+    tx:     Sender<E::Message>,
+    rx:     Receiver<E::Message>,
+}
+
+impl<E: Engine> SessionActor<E> {
+    pub fn new(engine: E) -> Self {
+        let (tx, rx) = channel::unbounded::<E::Message>();
+        SessionActor {
             id: crate::deps::locutus_actor::Id::random(),
-            game: Mutex::new(gameoflife::Simulation::new()),
+            engine: Mutex::new(engine),
             tx,
             rx,
         }
     }
+
+    pub fn opening(&self) -> &'static [E::Message] {
+        self.engine.lock().opening()
+    }
+
+    pub fn progress(&self) -> String {
+        self.engine.lock().progress()
+    }
+
+    /// How many messages are waiting for [`Actor::on_tick`].
+    pub fn pending(&self) -> usize {
+        self.rx.len()
+    }
 }
 
-impl<'a> Actor<'a> for GameOfLife {
-    type Message = gameoflife::Message;
-    type State = &'a Mutex<gameoflife::Simulation>;
+impl<'a, E: Engine> Actor<'a> for SessionActor<E> {
+    type Message = E::Message;
+    type State = &'a Mutex<E>;
 
     fn send(
         &self,
@@ -55,12 +177,12 @@ impl<'a> Actor<'a> for GameOfLife {
         self.rx
             .recv()
             .map_err(|_err| gameoflife::Error::Unknown)
-            .and_then(|msg| self.game.lock().update(msg))
+            .and_then(|msg| self.engine.lock().update(msg))
             .map_err(|err| err.into())
     }
 
     fn state(&'a self) -> Self::State {
-        &self.game
+        &self.engine
     }
 
     fn id(&self) -> &actor::Id {
@@ -68,15 +190,15 @@ impl<'a> Actor<'a> for GameOfLife {
     }
 
     fn name(&self) -> &'static str {
-        "GameOfLife"
+        E::NAME
     }
 }
 
-impl fmt::Debug for GameOfLife {
+impl<E: Engine> fmt::Debug for SessionActor<E> {
     fn fmt(
         &self,
         f: &mut fmt::Formatter,
     ) -> fmt::Result {
-        f.debug_struct("GameOfLife").field("id", &self.id()).finish()
+        f.debug_struct(E::NAME).field("id", &self.id()).finish()
     }
 }
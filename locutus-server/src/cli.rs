@@ -1,9 +1,24 @@
 use crate::deps::{
+    gameoflife::{
+        self,
+        LineRule,
+        Rule,
+        StabilizationPolicy,
+        Topology,
+        TurmiteRule,
+        Wireworld,
+    },
     structopt::StructOpt,
     tracing::Level,
 };
 
-use std::net::IpAddr;
+use std::{
+    net::IpAddr,
+    path::PathBuf,
+};
+
+/// The circuit Wireworld sessions start from when no `--circuit` is given.
+const DEFAULT_CIRCUIT: &str = include_str!("../circuits/clock.rle");
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "locutus-server", about = "simulation server")]
@@ -22,4 +37,47 @@ pub struct Args {
 
     #[structopt(long, default_value = "16")]
     pub sim_threads: usize,
+
+    #[structopt(long, default_value = "80")]
+    pub board_width: usize,
+
+    #[structopt(long, default_value = "60")]
+    pub board_height: usize,
+
+    #[structopt(long, default_value = "B3/S23")]
+    pub rule: Rule,
+
+    #[structopt(long, default_value = "torus")]
+    pub topology: Topology,
+
+    #[structopt(long, default_value = "continue")]
+    pub on_stabilize: StabilizationPolicy,
+
+    /// The rule of one dimensional sessions, such as `W30` or `K3,R1,T777`.
+    #[structopt(long, default_value = "W30")]
+    pub line_rule: LineRule,
+
+    /// The rule of turmite sessions, either a string of turns such as `RL` or a Golly turmite table.
+    #[structopt(long, default_value = "RL")]
+    pub turmite_rule: TurmiteRule,
+
+    /// A Wireworld circuit in Golly RLE or, with an `.mcl` extension, MCell format.
+    #[structopt(long, parse(from_os_str))]
+    pub circuit: Option<PathBuf>,
+}
+
+impl Args {
+    /// Reads the `--circuit` file, or the built in clock circuit when there is none.
+    pub fn circuit(&self) -> Result<Wireworld, Box<dyn std::error::Error>> {
+        let path = match &self.circuit {
+            Some(path) => path,
+            None => return Ok(Wireworld::from_rle(DEFAULT_CIRCUIT)?),
+        };
+        let contents = std::fs::read_to_string(path)?;
+        let circuit: Result<_, gameoflife::Error> = match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("mcl") => Wireworld::from_mcl(&contents),
+            _ => Wireworld::from_rle(&contents),
+        };
+        Ok(circuit?)
+    }
 }
@@ -33,6 +33,13 @@ async fn main() {
     config.port = args.port;
     config.tick = std::time::Duration::from_millis(1000 / args.tick_hertz);
     config.sim_threads = args.sim_threads;
+    config.board_width = args.board_width;
+    config.board_height = args.board_height;
+    config.rule = args.rule;
+    config.topology = args.topology;
+    config.on_stabilize = args.on_stabilize;
+    config.line_rule = args.line_rule;
+    let circuit = args.circuit().expect("could not load the Wireworld circuit");
 
-    crate::server::serve(config).await.expect("failed to run server");
+    crate::server::serve(config, circuit, args.turmite_rule).await.expect("failed to run server");
 }
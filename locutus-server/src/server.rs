@@ -10,8 +10,8 @@ use std::{
 
 use crate::{
     actors::{
-        self,
-        GameOfLife,
+        Engine,
+        SessionActor,
     },
     deps::{
         futures_util::{
@@ -22,10 +22,12 @@ use crate::{
             SinkExt,
             StreamExt,
         },
+        locutus_actor::Actor,
         rayon::{
             ThreadPool,
             ThreadPoolBuilder,
         },
+        serde::de::DeserializeOwned,
         tokio::net::TcpStream,
         tokio_tungstenite::WebSocketStream,
     },
@@ -34,7 +36,6 @@ use crate::{
 use crate::deps::{
     crossbeam::channel,
     gameoflife,
-    locutus_actor::Actor,
     serde_json as json,
     tracing::{
         error,
@@ -43,7 +44,16 @@ use crate::deps::{
         warn,
         Level,
     },
-    tungstenite::Message,
+    tungstenite::{
+        handshake::server::{
+            Callback,
+            ErrorResponse,
+            Request,
+            Response,
+        },
+        http::StatusCode,
+        Message,
+    },
 };
 
 macro_rules! panic_on_err {
@@ -76,6 +86,12 @@ pub struct Config {
     pub sim_threads:           usize,
     pub sim_thread_stack_size: usize,
     pub tick:                  Duration,
+    pub board_width:           usize,
+    pub board_height:          usize,
+    pub rule:                  gameoflife::Rule,
+    pub topology:              gameoflife::Topology,
+    pub on_stabilize:          gameoflife::StabilizationPolicy,
+    pub line_rule:             gameoflife::LineRule,
 }
 
 impl std::default::Default for Config {
@@ -86,14 +102,126 @@ impl std::default::Default for Config {
             sim_threads:           16,
             sim_thread_stack_size: 2 << 20,
             tick:                  Duration::from_millis(33),
+            board_width:           gameoflife::Simulation::DEFAULT_WIDTH,
+            board_height:          gameoflife::Simulation::DEFAULT_HEIGHT,
+            rule:                  gameoflife::Rule::default(),
+            topology:              gameoflife::Topology::default(),
+            on_stabilize:          gameoflife::StabilizationPolicy::Continue,
+            line_rule:             gameoflife::LineRule::default(),
         }
     }
 }
 
-#[tracing::instrument]
-pub async fn serve(config: Config) -> std::result::Result<(), Box<dyn std::error::Error>> {
+impl Config {
+    fn simulation(&self) -> Result<gameoflife::Simulation, gameoflife::Error> {
+        gameoflife::Simulation::builder()
+            .width(self.board_width)
+            .height(self.board_height)
+            .rule(self.rule)
+            .topology(self.topology)
+            .on_stabilize(self.on_stabilize)
+            .build()
+    }
+
+    /// A board of the configured size, or larger if `circuit` does not fit, with `circuit` in the
+    /// middle.
+    fn wireworld(
+        &self,
+        circuit: &gameoflife::Wireworld,
+    ) -> Result<gameoflife::Wireworld, gameoflife::Error> {
+        let width = self.board_width.max(circuit.width());
+        let height = self.board_height.max(circuit.height());
+        let mut board = gameoflife::Wireworld::new(width, height)?;
+        board.place(circuit, (width - circuit.width()) / 2, (height - circuit.height()) / 2);
+        Ok(board)
+    }
+
+    /// A one dimensional automaton as wide as the board, keeping as many rows of history as the
+    /// board is high.
+    fn line(&self) -> Result<gameoflife::LineAutomaton, gameoflife::Error> {
+        let mut automaton = gameoflife::LineAutomaton::new(self.board_width, self.board_height, self.line_rule)?;
+        automaton.set_topology(self.topology);
+        Ok(automaton)
+    }
+
+    /// A board of the configured size and topology with a single ant in the middle, facing north.
+    fn turmites(
+        &self,
+        rule: &gameoflife::TurmiteRule,
+    ) -> Result<gameoflife::Turmites, gameoflife::Error> {
+        let mut board = gameoflife::Turmites::new(self.board_width, self.board_height, rule.clone())?;
+        board.set_topology(self.topology);
+        board.add_ant(gameoflife::Ant::new(
+            self.board_width / 2,
+            self.board_height / 2,
+            gameoflife::Heading::North,
+        ));
+        Ok(board)
+    }
+}
+
+/// The automata a client can pick from by the path it connects to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Automaton {
+    /// `/` or `/life`.
+    GameOfLife,
+    /// `/wireworld`.
+    Wireworld,
+    /// `/line`.
+    Line,
+    /// `/turmite`.
+    Turmites,
+}
+
+impl Automaton {
+    fn from_path(path: &str) -> Option<Self> {
+        match path.trim_matches('/') {
+            "" | "life" => Some(Automaton::GameOfLife),
+            "wireworld" => Some(Automaton::Wireworld),
+            "line" => Some(Automaton::Line),
+            "turmite" => Some(Automaton::Turmites),
+            _ => None,
+        }
+    }
+}
+
+/// Picks the automaton of a connection from the path of its handshake request, refusing paths
+/// that name none.
+struct PickAutomaton<'a>(&'a mut Option<Automaton>);
+
+impl Callback for PickAutomaton<'_> {
+    fn on_request(
+        self,
+        request: &Request,
+        response: Response,
+    ) -> Result<Response, ErrorResponse> {
+        *self.0 = Automaton::from_path(request.uri().path());
+        match self.0 {
+            Some(_) => Ok(response),
+            None => {
+                let mut not_found = ErrorResponse::new(Some(format!("no automaton at {}", request.uri().path())));
+                *not_found.status_mut() = StatusCode::NOT_FOUND;
+                Err(not_found)
+            }
+        }
+    }
+}
+
+#[tracing::instrument(skip(circuit, turmite_rule))]
+pub async fn serve(
+    config: Config,
+    circuit: gameoflife::Wireworld,
+    turmite_rule: gameoflife::TurmiteRule,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
     use crate::deps::tokio::net::TcpListener;
 
+    config.simulation()?;
+    config.wireworld(&circuit)?;
+    config.line()?;
+    config.turmites(&turmite_rule)?;
+    let circuit = Arc::new(circuit);
+    let turmite_rule = Arc::new(turmite_rule);
+
     let sim_thread_pool = Arc::new(
         ThreadPoolBuilder::new()
             .num_threads(config.sim_threads)
@@ -119,7 +247,14 @@ pub async fn serve(config: Config) -> std::result::Result<(), Box<dyn std::error
                     .unwrap_or_else(panic_on_err!("connected streams should have a peer address"));
                 info!("Peer address: {}", peer);
 
-                tokio::spawn(accept_connection(peer, stream, sim_thread_pool.clone(), config.tick));
+                tokio::spawn(accept_connection(
+                    peer,
+                    stream,
+                    sim_thread_pool.clone(),
+                    config,
+                    circuit.clone(),
+                    turmite_rule.clone(),
+                ));
             }
             Err(err) => {
                 warn!("Shutting down server, error accepting connection - {:?}", err);
@@ -131,12 +266,14 @@ pub async fn serve(config: Config) -> std::result::Result<(), Box<dyn std::error
     Ok(())
 }
 
-#[tracing::instrument(skip(stream, sim_thread_pool))]
+#[tracing::instrument(skip(stream, sim_thread_pool, circuit, turmite_rule))]
 async fn accept_connection(
     peer: SocketAddr,
     stream: TcpStream,
     sim_thread_pool: Arc<ThreadPool>,
-    tick: Duration,
+    config: Config,
+    circuit: Arc<gameoflife::Wireworld>,
+    turmite_rule: Arc<gameoflife::TurmiteRule>,
 ) {
     let addr = stream
         .peer_addr()
@@ -144,19 +281,64 @@ async fn accept_connection(
 
     info!("Peer address: {}", addr);
 
-    let ws_stream = tokio_tungstenite::accept_async(stream)
-        .await
-        .unwrap_or_else(panic_on_err!("Error during the websocket handshake occurred"));
+    let mut automaton = None;
+    let ws_stream = match tokio_tungstenite::accept_hdr_async(stream, PickAutomaton(&mut automaton)).await {
+        Ok(ws_stream) => ws_stream,
+        Err(err) if automaton.is_none() => {
+            warn!("Rejected connection from {} for an unknown automaton: {}", addr, err);
+            return;
+        }
+        Err(err) => panic_on_err!("Error during the websocket handshake occurred")(err),
+    };
 
     info!("New WebSocket connection: {}", addr);
 
-    let actor = Arc::new(actors::GameOfLife::new());
+    match automaton {
+        Some(Automaton::Wireworld) => {
+            let board = config
+                .wireworld(&circuit)
+                .unwrap_or_else(panic_on_err!("config was validated on startup: {:?}", config));
+            host(ws_stream, Arc::new(SessionActor::new(board)), &sim_thread_pool, addr, config.tick).await
+        }
+        Some(Automaton::Line) => {
+            let automaton = config
+                .line()
+                .unwrap_or_else(panic_on_err!("config was validated on startup: {:?}", config));
+            host(ws_stream, Arc::new(SessionActor::new(automaton)), &sim_thread_pool, addr, config.tick).await
+        }
+        Some(Automaton::Turmites) => {
+            let board = config
+                .turmites(&turmite_rule)
+                .unwrap_or_else(panic_on_err!("config was validated on startup: {:?}", config));
+            host(ws_stream, Arc::new(SessionActor::new(board)), &sim_thread_pool, addr, config.tick).await
+        }
+        _ => {
+            let simulation = config
+                .simulation()
+                .unwrap_or_else(panic_on_err!("config was validated on startup: {:?}", config));
+            host(ws_stream, Arc::new(SessionActor::new(simulation)), &sim_thread_pool, addr, config.tick).await
+        }
+    }
+}
+
+/// Runs `actor` on the simulation pool and streams its state to the client until it disconnects.
+async fn host<E: Engine>(
+    ws_stream: WebSocketStream<TcpStream>,
+    actor: Arc<SessionActor<E>>,
+    sim_thread_pool: &ThreadPool,
+    addr: SocketAddr,
+    tick: Duration,
+) {
     info!("Created simulation actor for connection: {:?}", actor);
 
     let actor_clone = actor.clone();
+    // Large boards split their steps into bands that run on this same pool, so idle simulation
+    // threads help out busy sessions.
     sim_thread_pool.spawn(move || run_actor(actor_clone, tick));
     let connection_result = handle_connection(ws_stream, actor.clone(), tick).await;
-    actor.send(gameoflife::Message::End);
+    if let Err(err) = actor.send(gameoflife::Control::End.into()) {
+        warn!("Could not end the simulation actor {:?}: {}", actor, err);
+    }
     connection_result.unwrap_or_else(panic_on_err!(
         "Connection did not terminate gracefully: addr={}; actor={:?}",
         addr,
@@ -171,17 +353,21 @@ fn nano_now() -> u64 {
         .as_nanos() as u64
 }
 
-async fn handle_connection(
+async fn handle_connection<E: Engine>(
     websocket: WebSocketStream<TcpStream>,
-    actor: Arc<actors::GameOfLife>,
+    actor: Arc<SessionActor<E>>,
     tick: Duration,
 ) -> std::result::Result<(), Box<dyn std::error::Error>> {
     let (mut outstream, mut instream) = websocket.split();
     let mut interval = tokio::time::interval(tick);
     let mut interval_future = interval.next();
     let mut message_future = instream.next();
-    actor.send(gameoflife::Message::Random);
-    actor.send(gameoflife::Message::Start);
+    for message in actor.opening().iter().cloned() {
+        if let Err(err) = actor.send(message) {
+            error!("could not open the session, closing it: {}", err);
+            return Ok(());
+        }
+    }
 
     loop {
         match select(message_future, interval_future).await {
@@ -193,7 +379,17 @@ async fn handle_connection(
                         if msg.is_close() {
                             break;
                         } else {
-                            let json_str = bincode::serialize(&*actor.state())?;
+                            match decode::<E::Message>(&msg) {
+                                Some(Ok(message)) => {
+                                    if let Err(err) = actor.send(message) {
+                                        error!("could not forward a message, closing the session: {}", err);
+                                        break;
+                                    }
+                                }
+                                Some(Err(err)) => warn!("Ignoring a message that is not for {}: {}", E::NAME, err),
+                                None => {}
+                            }
+                            let json_str = bincode::serialize(&actor.state())?;
                             trace!("Sending: {} bytes", json_str.len());
                             let state_msg = Message::Binary(json_str);
                             outstream.send(state_msg).await?;
@@ -209,7 +405,7 @@ async fn handle_connection(
                 };
             }
             Either::Right((_, msg_fut_continue)) => {
-                let json_str = bincode::serialize(&*actor.state())?;
+                let json_str = bincode::serialize(&actor.state())?;
                 trace!("Sending: {} bytes", json_str.len());
                 let state_msg = Message::Binary(json_str);
                 outstream.send(state_msg).await?;
@@ -221,33 +417,54 @@ async fn handle_connection(
     Ok(())
 }
 
+/// Reads an engine message from the JSON in a text or binary frame, or `None` for control frames.
+fn decode<M: DeserializeOwned>(frame: &Message) -> Option<Result<M, json::Error>> {
+    match frame {
+        Message::Text(text) => Some(json::from_str(text)),
+        Message::Binary(bytes) => Some(json::from_slice(bytes)),
+        _ => None,
+    }
+}
+
 #[tracing::instrument]
-fn run_actor(
-    actor: Arc<GameOfLife>,
+fn run_actor<E: Engine>(
+    actor: Arc<SessionActor<E>>,
     tick: Duration,
 ) {
     let mut frames = 0;
-    info!("starting new actor: GameOfLife::{}", actor.id());
+    info!("starting new actor: {}::{}", actor.name(), actor.id());
     let ticker = channel::tick(tick);
 
     let mut start = nano_now();
     'update_loop: while let Ok(_tick) = ticker.recv() {
-        actor.send(gameoflife::Message::Tick);
-
-        if let Err(err) = actor.on_tick() {
-            error!("game over: {:?}", err);
+        if let Err(err) = actor.send(gameoflife::Control::Tick.into()) {
+            error!("could not tick, closing the session: {}", err);
             break 'update_loop;
         }
+
+        // Messages forwarded from the client queue up alongside the ticks, so work through all of
+        // them rather than one per tick.
+        while actor.pending() > 0 {
+            match actor.on_tick() {
+                Ok(()) => {}
+                Err(err) if err.recoverable() => warn!("rejected a message: {}", err),
+                Err(err) => {
+                    error!("game over: {:?}", err);
+                    break 'update_loop;
+                }
+            }
+        }
         frames += 1;
         if frames % 120 == 0 {
             let now = nano_now();
             info!(
-                "simulated {:>5} frames ({:.1} fps)",
+                "simulated {:>5} frames ({:.1} fps), {}",
                 frames,
-                120_000.0f64 / Duration::from_nanos(now - start).as_millis() as f64
+                120_000.0f64 / Duration::from_nanos(now - start).as_millis() as f64,
+                actor.progress()
             );
             start = now;
         }
     }
-    info!("terminating actor: GameOfLife::{}", actor.id());
+    info!("terminating actor: {}::{}", actor.name(), actor.id());
 }